@@ -0,0 +1,4 @@
+-- increased on every save, saves based on an older revision are refused.
+-- 0 is never stored, it is the revision of a page, that is not saved yet
+ALTER TABLE quests_pages
+    ADD COLUMN IF NOT EXISTS revision INTEGER NOT NULL DEFAULT 1 CHECK (revision >= 1);
@@ -0,0 +1,4 @@
+DROP TABLE quests_revisions;
+
+ALTER TABLE quests_pages
+    DROP COLUMN page_id;
@@ -0,0 +1,20 @@
+-- identity of the page, that is kept when pages are moved
+ALTER TABLE quests_pages
+    ADD COLUMN IF NOT EXISTS page_id UUID NOT NULL UNIQUE DEFAULT gen_random_uuid();
+
+-- every saved version of quest info and pages, rows are only ever inserted
+CREATE TABLE IF NOT EXISTS quests_revisions
+(
+    id         SERIAL PRIMARY KEY,
+    quest_id   UUID      NOT NULL,
+    author     UUID      NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    -- page number at the time of the revision, NULL for quest info
+    page       INTEGER CHECK (page >= 0),
+    -- quests_pages.page_id of the page, NULL for quest info
+    page_id    UUID,
+    -- see common::QuestRevisionContent for its format
+    content    JSONB     NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS quests_revisions_quest_id ON quests_revisions (quest_id, id);
@@ -0,0 +1,5 @@
+-- secret of the participation, that shuffles items of ordering and matching questions,
+-- see common::ShuffleSeed, random 64 bits of a UUID
+ALTER TABLE quests_applied
+    ADD COLUMN IF NOT EXISTS shuffle_seed BIGINT NOT NULL
+        DEFAULT ('x' || substr(md5(gen_random_uuid()::text), 1, 16))::bit(64)::bigint;
@@ -0,0 +1,4 @@
+-- format of the parsed page, see common::QUEST_PAGE_FORMAT,
+-- NULL for pages parsed before it was saved, these are parsed again on start
+ALTER TABLE quests_pages
+    ADD COLUMN IF NOT EXISTS parsed_format INTEGER;
@@ -0,0 +1,2 @@
+ALTER TABLE quests_pages
+    DROP COLUMN parsed;
@@ -0,0 +1,3 @@
+-- parsed pages are saved once quest is published
+ALTER TABLE quests_pages
+    ADD COLUMN IF NOT EXISTS parsed JSONB;
@@ -0,0 +1,9 @@
+ALTER TABLE quests
+    ADD COLUMN published BOOLEAN NOT NULL DEFAULT false;
+
+UPDATE quests
+SET published = true
+WHERE state IN (3, 4);
+
+ALTER TABLE quests
+    DROP COLUMN state;
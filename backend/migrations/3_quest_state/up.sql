@@ -0,0 +1,10 @@
+-- see common::QuestState for values
+ALTER TABLE quests
+    ADD COLUMN IF NOT EXISTS state SMALLINT NOT NULL DEFAULT 0 CHECK (state >= 0 AND state <= 5);
+
+UPDATE quests
+SET state = 3
+WHERE published;
+
+ALTER TABLE quests
+    DROP COLUMN IF EXISTS published;
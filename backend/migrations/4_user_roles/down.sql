@@ -0,0 +1,5 @@
+ALTER TABLE users
+    DROP COLUMN banned;
+
+ALTER TABLE users
+    DROP COLUMN role;
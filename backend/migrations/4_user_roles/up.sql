@@ -0,0 +1,6 @@
+-- see common::UserRole for values
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS role SMALLINT NOT NULL DEFAULT 0 CHECK (role >= 0 AND role <= 2);
+
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS banned BOOLEAN NOT NULL DEFAULT false;
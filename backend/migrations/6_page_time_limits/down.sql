@@ -0,0 +1,2 @@
+ALTER TABLE quests_applied
+    DROP COLUMN page_served_at;
@@ -0,0 +1,3 @@
+-- when current page was sent to the player, time limit of the page is counted from it
+ALTER TABLE quests_applied
+    ADD COLUMN IF NOT EXISTS page_served_at TIMESTAMP;
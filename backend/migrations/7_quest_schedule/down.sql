@@ -0,0 +1,4 @@
+ALTER TABLE quests
+    DROP COLUMN time_limit_seconds,
+    DROP COLUMN opens_at,
+    DROP COLUMN closes_at;
@@ -0,0 +1,4 @@
+ALTER TABLE quests
+    ADD COLUMN IF NOT EXISTS time_limit_seconds INTEGER CHECK (time_limit_seconds > 0),
+    ADD COLUMN IF NOT EXISTS opens_at           TIMESTAMP,
+    ADD COLUMN IF NOT EXISTS closes_at          TIMESTAMP;
@@ -0,0 +1,5 @@
+DROP TABLE quests_answers;
+
+ALTER TABLE quests_applied
+    DROP COLUMN score,
+    DROP COLUMN max_score;
@@ -0,0 +1,16 @@
+ALTER TABLE quests_applied
+    ADD COLUMN IF NOT EXISTS score     INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS max_score INTEGER NOT NULL DEFAULT 0 CHECK (max_score >= 0);
+
+-- every submitted answer, see common::Answer for its format
+CREATE TABLE IF NOT EXISTS quests_answers
+(
+    user_id  UUID    NOT NULL,
+    quest_id UUID    NOT NULL,
+    page     INTEGER NOT NULL CHECK (page >= 0),
+    question INTEGER NOT NULL CHECK (question >= 0),
+    answer   JSONB   NOT NULL,
+    score    INTEGER NOT NULL,
+    PRIMARY KEY (user_id, quest_id, page, question),
+    FOREIGN KEY (user_id, quest_id) REFERENCES quests_applied (user_id, quest_id) ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+ALTER TABLE quests_pages
+    ADD CONSTRAINT quests_pages_id_key UNIQUE (id);
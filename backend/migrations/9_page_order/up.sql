@@ -0,0 +1,3 @@
+-- quest can have any number of pages, they are told apart by (id, page)
+ALTER TABLE quests_pages
+    DROP CONSTRAINT IF EXISTS quests_pages_id_key;
@@ -0,0 +1,147 @@
+use crate::database::Database;
+use crate::error::ApiError;
+use crate::AppState;
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::header::SET_COOKIE;
+use axum::http::request::Parts;
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::Response;
+use axum_extra::headers::{Cookie, HeaderMapExt};
+use common::{UserId, UserRole};
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Logged in user, rejects with [`ApiError::LoginRequired`] otherwise
+pub struct AuthUser(pub UserId);
+
+/// User, if logged in
+pub struct MaybeAuthUser(pub Option<UserId>);
+
+/// Logged in user, who is at least a moderator
+pub struct Moderator(pub UserId);
+
+/// Logged in user, who is an admin
+pub struct Admin;
+
+impl AuthUser {
+    /// Current role of the user, banned users are rejected
+    pub async fn role(&self, database: &Database) -> Result<UserRole, ApiError> {
+        match database.get_user_role_banned(self.0 .0).await {
+            None => Err(ApiError::Internal),
+            Some((_, true)) => Err(ApiError::forbidden("account is banned")),
+            Some((role, false)) => Ok(role),
+        }
+    }
+
+    pub async fn is_moderator(&self, database: &Database) -> Result<bool, ApiError> {
+        Ok(self.role(database).await? >= UserRole::Moderator)
+    }
+
+    async fn require_role(
+        self,
+        database: &Database,
+        required: UserRole,
+    ) -> Result<UserId, ApiError> {
+        if self.role(database).await? < required {
+            return Err(ApiError::forbidden(format!("{required:?} role required")));
+        }
+        Ok(self.0)
+    }
+}
+
+/// Id from the session cookie, session itself may not exist
+pub fn session_id(headers: &HeaderMap) -> Option<Uuid> {
+    let cookie = headers.typed_get::<Cookie>()?;
+    Uuid::from_str(cookie.get("session")?).ok()
+}
+
+async fn session_user(parts: &Parts, state: &AppState) -> Option<UserId> {
+    let session_id = session_id(&parts.headers)?;
+    state
+        .sessions
+        .get(&state.database, session_id)
+        .await
+        .map(|session| session.user_id)
+}
+
+/// Prolongs used session, re-issuing its cookie
+pub async fn refresh_session(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let refreshed = match session_id(request.headers()) {
+        Some(session_id) if state.sessions.refresh(&state.database, session_id).await => {
+            Some(session_id)
+        }
+        _ => None,
+    };
+
+    let mut response = next.run(request).await;
+    // login and logout set their own cookie
+    if let Some(session_id) = refreshed {
+        if !response.headers().contains_key(SET_COOKIE) {
+            response
+                .headers_mut()
+                .insert(SET_COOKIE, state.sessions.cookie(session_id));
+        }
+    }
+    response
+}
+
+impl FromRequestParts<Arc<AppState>> for AuthUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        session_user(parts, state)
+            .await
+            .map(Self)
+            .ok_or(ApiError::LoginRequired)
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for MaybeAuthUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        Ok(Self(session_user(parts, state).await))
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for Moderator {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        AuthUser::from_request_parts(parts, state)
+            .await?
+            .require_role(&state.database, UserRole::Moderator)
+            .await
+            .map(Self)
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for Admin {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        AuthUser::from_request_parts(parts, state)
+            .await?
+            .require_role(&state.database, UserRole::Admin)
+            .await
+            .map(|_| Self)
+    }
+}
@@ -6,6 +6,7 @@ use thiserror::Error;
 pub struct Config {
     pub app: App,
     pub database: ConfigDatabase,
+    pub session: ConfigSession,
 }
 
 #[derive(Debug, Deserialize)]
@@ -16,6 +17,21 @@ pub struct ConfigDatabase {
     pub db_name: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ConfigSession {
+    pub lifetime_secs: u64,
+    pub refresh_after_secs: u64,
+    pub secure: bool,
+    pub same_site: SameSite,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct App {
     pub address: String,
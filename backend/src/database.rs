@@ -1,22 +1,57 @@
 use bigdecimal::{BigDecimal, ToPrimitive};
 use common::{
-    QuestId, QuestInfo, Timestamp, UserId, QUEST_HISTORY_PAGE_SIZE, USER_OWNED_QUESTS_PAGE_SIZE,
+    AdminUserRecord, Answer, Grade, QuestHistoryRecord, QuestId, QuestInfo, QuestPage,
+    QuestPageSource, QuestRevisionContent, QuestRevisionRecord, QuestState, ShuffleSeed, Timestamp,
+    UserId, UserRole, ADMIN_USERS_PAGE_SIZE, QUEST_HISTORY_PAGE_SIZE, QUEST_PAGE_FORMAT,
+    QUEST_REVISIONS_PAGE_SIZE, USER_OWNED_QUESTS_PAGE_SIZE,
 };
 use diesel::dsl::avg;
 use diesel::internal::derives::multiconnection::chrono::Utc;
 use diesel::r2d2::{self, ConnectionManager};
-use diesel::{BoolExpressionMethods, ExpressionMethods, JoinOnDsl};
-use diesel::{PgConnection, QueryDsl, RunQueryDsl};
+use diesel::{BoolExpressionMethods, Connection, ExpressionMethods, JoinOnDsl, OptionalExtension};
+use diesel::{PgConnection, PgExpressionMethods, QueryDsl, RunQueryDsl};
+use std::str::FromStr;
 use std::time::Duration;
 use tokio::time::sleep;
 use uuid::Uuid;
 
 type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 
+/// user, quest, started, finished, completed pages, score, max score
+type HistoryRow = (Uuid, Uuid, Timestamp, Option<Timestamp>, i32, i32, i32);
+
+fn history_record(
+    (user, quest, started_at, finished_at, completed_pages, score, max_score): HistoryRow,
+) -> QuestHistoryRecord {
+    QuestHistoryRecord {
+        user_id: UserId(user),
+        quest_id: QuestId(quest),
+        started_at,
+        finished_at,
+        completed_pages: completed_pages as u32,
+        score,
+        max_score: max_score as u32,
+    }
+}
+
 pub struct Database {
     pool: DbPool,
 }
 
+/// Why page source was not saved
+#[derive(Debug)]
+pub enum PageSaveError {
+    /// page was saved, appended or moved concurrently
+    Outdated,
+    Failed,
+}
+
+impl From<diesel::result::Error> for PageSaveError {
+    fn from(_: diesel::result::Error) -> Self {
+        Self::Failed
+    }
+}
+
 impl Database {
     pub fn new(pool: DbPool) -> Self {
         Self { pool }
@@ -56,6 +91,85 @@ impl Database {
         }
     }
 
+    pub async fn get_user_role_banned(&self, user_id: Uuid) -> Option<(UserRole, bool)> {
+        use crate::schema::users::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        let (got_role, got_banned) = users
+            .filter(id.eq(user_id))
+            .select((role, banned))
+            .first::<(i16, bool)>(&mut conn)
+            .ok()?;
+        Some((
+            UserRole::try_from(u8::try_from(got_role).ok()?).ok()?,
+            got_banned,
+        ))
+    }
+
+    pub async fn set_user_role(&self, user_id: Uuid, new_role: UserRole) -> Option<()> {
+        // Some on success
+        use crate::schema::users::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        let updated_rows = diesel::update(users)
+            .filter(id.eq(user_id))
+            .set(role.eq(u8::from(new_role) as i16))
+            .execute(&mut conn)
+            .ok();
+        match updated_rows {
+            Some(1) => Some(()),
+            _ => None,
+        }
+    }
+
+    pub async fn set_user_banned(&self, user_id: Uuid, new_banned: bool) -> Option<()> {
+        // Some on success
+        use crate::schema::users::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        let updated_rows = diesel::update(users)
+            .filter(id.eq(user_id))
+            .set(banned.eq(new_banned))
+            .execute(&mut conn)
+            .ok();
+        match updated_rows {
+            Some(1) => Some(()),
+            _ => None,
+        }
+    }
+
+    pub async fn get_users(&self, page: u32) -> Option<(Vec<AdminUserRecord>, u32)> {
+        use crate::schema::users::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+
+        let total_pages = ((users.count().get_result::<i64>(&mut conn).ok()? as f64)
+            / (ADMIN_USERS_PAGE_SIZE as f64))
+            .ceil() as u32;
+
+        if total_pages == 0 || page > total_pages {
+            return None;
+        }
+
+        let rows = users
+            .order_by(name.asc())
+            .select((id, name, email, role, banned))
+            .offset((ADMIN_USERS_PAGE_SIZE * (page as usize)) as i64)
+            .limit(ADMIN_USERS_PAGE_SIZE as i64)
+            .load::<(Uuid, String, String, i16, bool)>(&mut conn)
+            .ok()?;
+        Some((
+            rows.into_iter()
+                .map(|(got_id, got_name, got_email, got_role, got_banned)| {
+                    Some(AdminUserRecord {
+                        id: UserId(got_id),
+                        name: got_name,
+                        email: got_email,
+                        role: UserRole::try_from(u8::try_from(got_role).ok()?).ok()?,
+                        banned: got_banned,
+                    })
+                })
+                .collect::<Option<_>>()?,
+            total_pages,
+        ))
+    }
+
     pub async fn is_exist_user_avatar(&self, user_id: Uuid) -> Option<Uuid> {
         use crate::schema::avatars::dsl::*;
         let mut conn = self.get_conn_to_death().await;
@@ -79,27 +193,35 @@ impl Database {
     pub async fn find_user_by_email_or_name(
         &self,
         input: &str,
-    ) -> Option<(Uuid, String, String, Option<Uuid>)> {
+    ) -> Option<(Uuid, String, String, Option<Uuid>, UserRole)> {
+        // names are alphanumeric, so id can't be mistaken for one
         use crate::schema::users::dsl::*;
         let mut conn = self.get_conn_to_death().await;
         let res = if input.contains('@') {
             users
                 .filter(email.eq(input))
-                .select((id, name, email))
-                .first::<(Uuid, String, String)>(&mut conn)
+                .select((id, name, email, role))
+                .first::<(Uuid, String, String, i16)>(&mut conn)
+                .ok()
+        } else if let Ok(input_id) = Uuid::from_str(input) {
+            users
+                .filter(id.eq(input_id))
+                .select((id, name, email, role))
+                .first::<(Uuid, String, String, i16)>(&mut conn)
                 .ok()
         } else {
             users
                 .filter(name.eq(input))
-                .select((id, name, email))
-                .first::<(Uuid, String, String)>(&mut conn)
+                .select((id, name, email, role))
+                .first::<(Uuid, String, String, i16)>(&mut conn)
                 .ok()
         };
         match res {
             None => None,
-            Some((user_id, user_name, user_email)) => {
+            Some((user_id, user_name, user_email, user_role)) => {
+                let user_role = UserRole::try_from(u8::try_from(user_role).ok()?).ok()?;
                 let user_avatar = self.is_exist_user_avatar(user_id).await;
-                Some((user_id, user_name, user_email, user_avatar))
+                Some((user_id, user_name, user_email, user_avatar, user_role))
             }
         }
     }
@@ -163,48 +285,48 @@ impl Database {
     }
 
     pub async fn get_quest(&self, quest_id: Uuid) -> Option<QuestInfo> {
-        use crate::schema::quests::dsl::*;
         let mut conn = self.get_conn_to_death().await;
-        let result = quests
-            .filter(id.eq(quest_id))
-            .select((id, owner, title, description, pages, published))
-            .first::<(Uuid, Uuid, Option<String>, Option<String>, i32, bool)>(&mut conn)
-            .ok();
-        result.map(
-            |(got_id, got_owner, got_title, got_description, got_pages, got_published)| QuestInfo {
-                id: QuestId(got_id),
-                owner: UserId(got_owner),
-                title: got_title.unwrap_or(String::from("")),
-                description: got_description.unwrap_or(String::from("")),
-                pages: got_pages as u32, //todo possibly not good, but i want to see guy who will create 2 billion pages
-                published: got_published,
-            },
-        )
+        quest_info_row(&mut conn, quest_id)
     }
 
-    pub async fn update_quest(&self, quest_info: QuestInfo) -> Option<()> {
+    /// Saves quest info, keeping the saved quest as a revision by `author`
+    pub async fn update_quest(&self, quest_info: QuestInfo, author: UserId) -> Option<()> {
         // Some on success
         use crate::schema::quests::dsl::*;
         let mut conn = self.get_conn_to_death().await;
-        let updated_rows = diesel::update(quests)
-            .filter(id.eq(quest_info.id.0))
-            .set((
-                title.eq(quest_info.title),
-                description.eq(quest_info.description),
-            )) //todo look at me
-            .execute(&mut conn)
-            .ok();
-        match updated_rows {
-            Some(1) => Some(()),
-            _ => None,
-        }
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let updated_rows = diesel::update(quests)
+                .filter(id.eq(quest_info.id.0))
+                .set((
+                    title.eq(&quest_info.title),
+                    description.eq(&quest_info.description),
+                    time_limit_seconds.eq(quest_info.time_limit.map(|x| x.as_secs() as i32)),
+                    opens_at.eq(quest_info.opens_at),
+                    closes_at.eq(quest_info.closes_at),
+                )) //todo look at me
+                .execute(conn)?;
+            if updated_rows != 1 {
+                return Err(diesel::result::Error::RollbackTransaction);
+            }
+            // only some of the fields are saved, so the quest is read back
+            let saved = quest_info_row(conn, quest_info.id.0)
+                .ok_or(diesel::result::Error::RollbackTransaction)?;
+            insert_quest_revision(
+                conn,
+                quest_info.id,
+                author,
+                None,
+                &QuestRevisionContent::Info(saved),
+            )
+        })
+        .ok()
     }
 
     pub async fn get_user_quest_history(
         &self,
         user_id_input: Uuid,
         page: u32,
-    ) -> Option<(Vec<(Uuid, Timestamp, Option<Timestamp>, u32)>, u32)> {
+    ) -> Option<(Vec<QuestHistoryRecord>, u32)> {
         use crate::schema::quests_applied::dsl::*;
         let mut conn = self.get_conn_to_death().await;
 
@@ -223,16 +345,62 @@ impl Database {
         Some((
             quests_applied
                 .filter(user_id.eq(user_id_input))
-                .select((quest_id, started_at, finished_at, completed_pages))
+                .select((
+                    user_id,
+                    quest_id,
+                    started_at,
+                    finished_at,
+                    completed_pages,
+                    score,
+                    max_score,
+                ))
                 .offset((QUEST_HISTORY_PAGE_SIZE * (page as usize)) as i64)
                 .limit(QUEST_HISTORY_PAGE_SIZE as i64)
-                .load::<(Uuid, Timestamp, Option<Timestamp>, i32)>(&mut conn)
+                .load::<HistoryRow>(&mut conn)
                 .ok()
-                .map(|rows| {
-                    rows.into_iter()
-                        .map(|(q_id, s_at, f_at, pages)| (q_id, s_at, f_at, pages as u32))
-                        .collect()
-                })?,
+                .map(|rows| rows.into_iter().map(history_record).collect())?,
+            total_pages,
+        ))
+    }
+
+    pub async fn get_quest_results(
+        &self,
+        quest_id_input: Uuid,
+        page: u32,
+    ) -> Option<(Vec<QuestHistoryRecord>, u32)> {
+        // every participant of the quest, best scores first
+        use crate::schema::quests_applied::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+
+        let total_pages = (quests_applied
+            .filter(quest_id.eq(quest_id_input))
+            .count()
+            .get_result::<i64>(&mut conn)
+            .ok()? as usize)
+            .div_ceil(QUEST_HISTORY_PAGE_SIZE) as u32;
+
+        if page > total_pages {
+            return None;
+        }
+
+        Some((
+            quests_applied
+                .filter(quest_id.eq(quest_id_input))
+                .select((
+                    user_id,
+                    quest_id,
+                    started_at,
+                    finished_at,
+                    completed_pages,
+                    score,
+                    max_score,
+                ))
+                .order_by((score.desc(), started_at.asc()))
+                .offset((QUEST_HISTORY_PAGE_SIZE * (page as usize)) as i64)
+                .limit(QUEST_HISTORY_PAGE_SIZE as i64)
+                .load::<HistoryRow>(&mut conn)
+                .ok()
+                .map(|rows| rows.into_iter().map(history_record).collect())?,
             total_pages,
         ))
     }
@@ -252,8 +420,31 @@ impl Database {
             .map(|(s_at, f_at, pages)| (s_at, f_at, pages as u32))
     }
 
-    pub async fn first_join_quest(&self, user_id_input: Uuid, quest_id_input: Uuid) -> Option<()> {
+    /// Seed, that shuffles questions for the player, see [`ShuffleSeed`]
+    pub async fn get_shuffle_seed(
+        &self,
+        user_id_input: Uuid,
+        quest_id_input: Uuid,
+    ) -> Option<ShuffleSeed> {
+        use crate::schema::quests_applied::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        quests_applied
+            .filter(user_id.eq(user_id_input).and(quest_id.eq(quest_id_input)))
+            .select(shuffle_seed)
+            .first::<i64>(&mut conn)
+            .ok()
+            .map(|seed| ShuffleSeed(seed as u64))
+    }
+
+    pub async fn first_join_quest(
+        &self,
+        user_id_input: Uuid,
+        quest_id_input: Uuid,
+        max_score_input: u32,
+    ) -> Option<()> {
         use crate::schema::quests_applied::dsl::*;
+        // published quests are checked to fit
+        let max_score_input = i32::try_from(max_score_input).ok()?;
         let mut conn = self.get_conn_to_death().await;
 
         diesel::insert_into(quests_applied)
@@ -262,6 +453,7 @@ impl Database {
                 quest_id.eq(quest_id_input),
                 started_at.eq(Utc::now().naive_utc()),
                 completed_pages.eq(0),
+                max_score.eq(max_score_input),
             ))
             .execute(&mut conn)
             .ok()
@@ -311,20 +503,49 @@ impl Database {
         }
     }
 
-    pub async fn get_user_quest_last_completed_page(
+    pub async fn get_user_quest_progress(
         &self,
         user_id_input: Uuid,
         quest_id_input: Uuid,
-    ) -> Option<u32> {
+    ) -> Option<(u32, Option<Timestamp>)> {
+        // completed pages and when the next one was served
         use crate::schema::quests_applied::dsl::*;
         let mut conn = self.get_conn_to_death().await;
 
         quests_applied
             .filter(user_id.eq(user_id_input).and(quest_id.eq(quest_id_input)))
-            .select(completed_pages)
-            .first::<i32>(&mut conn)
+            .select((completed_pages, page_served_at))
+            .first::<(i32, Option<Timestamp>)>(&mut conn)
+            .ok()
+            .map(|(pages, served_at)| (pages as u32, served_at))
+    }
+
+    pub async fn set_user_page_served(
+        &self,
+        user_uuid: &Uuid,
+        quest_uuid: &Uuid,
+        page: u32,
+    ) -> Option<Timestamp> {
+        // returns when page was served first, sending it again doesn't restart its time
+        use crate::schema::quests_applied::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        diesel::update(quests_applied)
+            .filter(
+                quest_id
+                    .eq(quest_uuid)
+                    .and(user_id.eq(user_uuid))
+                    .and(completed_pages.eq(page as i32))
+                    .and(page_served_at.is_null()),
+            )
+            .set(page_served_at.eq(Utc::now().naive_utc()))
+            .execute(&mut conn)
+            .ok()?;
+        quests_applied
+            .filter(quest_id.eq(quest_uuid).and(user_id.eq(user_uuid)))
+            .select(page_served_at)
+            .first::<Option<Timestamp>>(&mut conn)
             .ok()
-            .map(|x| x as u32)
+            .flatten()
     }
 
     pub async fn update_user_last_completed_page(
@@ -333,12 +554,78 @@ impl Database {
         quest_uuid: &Uuid,
         last_completed: u32,
     ) -> Option<()> {
+        // Some on success, next page is not served yet
+        use crate::schema::quests_applied::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        let updated_rows = diesel::update(quests_applied)
+            .filter(quest_id.eq(quest_uuid).and(user_id.eq(user_uuid)))
+            .set((
+                completed_pages.eq(last_completed as i32),
+                page_served_at.eq(None::<Timestamp>),
+            ))
+            .execute(&mut conn)
+            .ok();
+        match updated_rows {
+            Some(1) => Some(()),
+            _ => None,
+        }
+    }
+
+    pub async fn save_page_answers(
+        &self,
+        user_uuid: &Uuid,
+        quest_uuid: &Uuid,
+        page_input: u32,
+        graded_answers: &[(&Answer, Grade)],
+    ) -> Option<()> {
+        // Some on success, completes the page and adds its score to the total
+        use crate::schema::{quests_answers, quests_applied};
+        let mut conn = self.get_conn_to_death().await;
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let mut page_score = 0i32;
+            for ((answer, grade), question_no) in graded_answers.iter().zip(0i32..) {
+                let answer = serde_json::to_value(answer)
+                    .map_err(|err| diesel::result::Error::SerializationError(Box::new(err)))?;
+                diesel::insert_into(quests_answers::table)
+                    .values((
+                        quests_answers::user_id.eq(user_uuid),
+                        quests_answers::quest_id.eq(quest_uuid),
+                        quests_answers::page.eq(page_input as i32),
+                        quests_answers::question.eq(question_no),
+                        quests_answers::answer.eq(answer),
+                        quests_answers::score.eq(grade.score),
+                    ))
+                    .execute(conn)?;
+                page_score = page_score.saturating_add(grade.score);
+            }
+            let updated_rows = diesel::update(quests_applied::table)
+                .filter(
+                    quests_applied::quest_id
+                        .eq(quest_uuid)
+                        .and(quests_applied::user_id.eq(user_uuid))
+                        .and(quests_applied::completed_pages.eq(page_input as i32)),
+                )
+                .set((
+                    quests_applied::completed_pages.eq(page_input as i32 + 1),
+                    quests_applied::page_served_at.eq(None::<Timestamp>),
+                    quests_applied::score.eq(quests_applied::score + page_score),
+                ))
+                .execute(conn)?;
+            if updated_rows != 1 {
+                return Err(diesel::result::Error::RollbackTransaction);
+            }
+            Ok(())
+        })
+        .ok()
+    }
+
+    pub async fn set_user_finished_quest(&self, user_uuid: &Uuid, quest_uuid: &Uuid) -> Option<()> {
         // Some on success
         use crate::schema::quests_applied::dsl::*;
         let mut conn = self.get_conn_to_death().await;
         let updated_rows = diesel::update(quests_applied)
             .filter(quest_id.eq(quest_uuid).and(user_id.eq(user_uuid)))
-            .set(completed_pages.eq(last_completed as i32))
+            .set(finished_at.eq(Utc::now().naive_utc()))
             .execute(&mut conn)
             .ok();
         match updated_rows {
@@ -366,8 +653,7 @@ impl Database {
                         (
                             elemuuid,
                             elem_optional_bigdec
-                                .map(|bigdec| bigdec.to_f64())
-                                .flatten()
+                                .and_then(|bigdec| bigdec.to_f64())
                                 .unwrap_or(0.),
                         )
                     })
@@ -375,44 +661,157 @@ impl Database {
             })
     }
 
-    pub async fn get_quest_page(&self, quest_id: Uuid, page_input: u32) -> Option<String> {
+    pub async fn get_quest_page(
+        &self,
+        quest_id: Uuid,
+        page_input: u32,
+    ) -> Option<(String, Option<u32>, u32)> {
+        // source, time limit in seconds and revision
         use crate::schema::quests_pages::dsl::*;
         let mut conn = self.get_conn_to_death().await;
         quests_pages
             .filter(id.eq(quest_id).and(page.eq(page_input as i32)))
+            .select((source, time_limit_seconds, revision))
+            .first::<(String, Option<i32>, i32)>(&mut conn)
+            .ok()
+            .map(|(page_source, time_limit, page_revision)| {
+                (
+                    page_source,
+                    time_limit.map(|x| x as u32),
+                    page_revision as u32,
+                )
+            })
+    }
+
+    pub async fn get_quest_pages(&self, quest_id: Uuid) -> Option<Vec<String>> {
+        // ordered by page number
+        use crate::schema::quests_pages::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        quests_pages
+            .filter(id.eq(quest_id))
+            .order_by(page.asc())
             .select(source)
-            .first::<String>(&mut conn)
+            .load::<String>(&mut conn)
+            .ok()
+    }
+
+    pub async fn get_quest_parsed_pages(
+        &self,
+        quest_id: Uuid,
+    ) -> Option<Vec<(Option<serde_json::Value>, Option<u32>, Option<u32>)>> {
+        // ordered by page number, parsed page is saved on publish along with its format,
+        // time limit is in seconds
+        use crate::schema::quests_pages::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        quests_pages
+            .filter(id.eq(quest_id))
+            .order_by(page.asc())
+            .select((parsed, parsed_format, time_limit_seconds))
+            .load::<(Option<serde_json::Value>, Option<i32>, Option<i32>)>(&mut conn)
+            .ok()
+            .map(|x| {
+                x.into_iter()
+                    .map(|(page_parsed, page_format, time_limit)| {
+                        (
+                            page_parsed,
+                            page_format.map(|x| x as u32),
+                            time_limit.map(|x| x as u32),
+                        )
+                    })
+                    .collect()
+            })
+    }
+
+    /// Sources of published quests pages, that are not saved parsed in `format`
+    pub async fn get_outdated_parsed_pages(&self, format: u32) -> Option<Vec<(Uuid, u32, String)>> {
+        use crate::schema::{quests, quests_pages};
+        let mut conn = self.get_conn_to_death().await;
+        let published = [QuestState::Published, QuestState::PublishedReviewed]
+            .map(|state| u8::from(state) as i16);
+        quests_pages::table
+            .filter(
+                quests_pages::id
+                    .eq_any(
+                        quests::table
+                            .filter(quests::state.eq_any(published))
+                            .select(quests::id),
+                    )
+                    .and(quests_pages::parsed_format.is_distinct_from(format as i32)),
+            )
+            .order_by((quests_pages::id, quests_pages::page))
+            .select((quests_pages::id, quests_pages::page, quests_pages::source))
+            .load::<(Uuid, i32, String)>(&mut conn)
             .ok()
+            .map(|pages| {
+                pages
+                    .into_iter()
+                    .map(|(quest_id, page, source)| (quest_id, page as u32, source))
+                    .collect()
+            })
     }
 
-    pub async fn update_quest_pages(&self, quest_info: &QuestInfo) -> Option<()> {
+    /// Saves parsed page in `format`
+    pub async fn set_parsed_page(
+        &self,
+        quest_id: Uuid,
+        page_input: u32,
+        parsed_page: &QuestPage,
+        format: u32,
+    ) -> Option<()> {
         // Some on success
-        use crate::schema::quests::dsl::*;
+        use crate::schema::quests_pages::dsl::*;
         let mut conn = self.get_conn_to_death().await;
-        let updated_rows = diesel::update(quests)
-            .filter(id.eq(quest_info.id.0))
-            .set(pages.eq(quest_info.pages as i32))
+        let parsed_page = serde_json::to_value(parsed_page).ok()?;
+        let updated_rows = diesel::update(quests_pages)
+            .filter(id.eq(quest_id).and(page.eq(page_input as i32)))
+            .set((parsed.eq(parsed_page), parsed_format.eq(format as i32)))
             .execute(&mut conn)
-            .ok();
-        match updated_rows {
-            Some(1) => Some(()),
-            _ => None,
-        }
+            .ok()?;
+        (updated_rows == 1).then_some(())
     }
 
-    pub async fn set_published_quest(&self, quest_info: &QuestInfo) -> Option<()> {
+    /// Changes quest state, only if it was not changed concurrently.
+    ///
+    /// Parsed pages are saved along, if provided.
+    pub async fn set_quest_state(
+        &self,
+        quest_info: &QuestInfo,
+        new_state: QuestState,
+        parsed_pages: Option<&[QuestPage]>,
+    ) -> Option<()> {
         // Some on success
-        use crate::schema::quests::dsl::*;
+        use crate::schema::{quests, quests_pages};
         let mut conn = self.get_conn_to_death().await;
-        let updated_rows = diesel::update(quests)
-            .filter(id.eq(quest_info.id.0))
-            .set(published.eq(true))
-            .execute(&mut conn)
-            .ok();
-        match updated_rows {
-            Some(1) => Some(()),
-            _ => None,
-        }
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            for (parsed_page, page_no) in parsed_pages.unwrap_or_default().iter().zip(0i32..) {
+                let parsed_page = serde_json::to_value(parsed_page)
+                    .map_err(|err| diesel::result::Error::SerializationError(Box::new(err)))?;
+                diesel::update(quests_pages::table)
+                    .filter(
+                        quests_pages::id
+                            .eq(quest_info.id.0)
+                            .and(quests_pages::page.eq(page_no)),
+                    )
+                    .set((
+                        quests_pages::parsed.eq(parsed_page),
+                        quests_pages::parsed_format.eq(QUEST_PAGE_FORMAT as i32),
+                    ))
+                    .execute(conn)?;
+            }
+            let updated_rows = diesel::update(quests::table)
+                .filter(
+                    quests::id
+                        .eq(quest_info.id.0)
+                        .and(quests::state.eq(u8::from(quest_info.state) as i16)),
+                )
+                .set(quests::state.eq(u8::from(new_state) as i16))
+                .execute(conn)?;
+            if updated_rows != 1 {
+                return Err(diesel::result::Error::RollbackTransaction);
+            }
+            Ok(())
+        })
+        .ok()
     }
 
     pub async fn get_owned_quests(&self, owner_id: Uuid, page: u32) -> Option<(Vec<Uuid>, u32)> {
@@ -443,37 +842,757 @@ impl Database {
         ))
     }
 
+    /// Saves page source, if its revision is still the current one. Page right after the last one
+    /// is appended, if its revision is 0, along with the page count of the quest.
+    /// Saved page is kept as a revision by `author`.
+    ///
+    /// New revision on success.
     pub async fn update_quest_page(
-        // or insert new
         &self,
-        quest_id: QuestId,
+        quest_info: &QuestInfo,
+        page_input: u32,
+        page_source: QuestPageSource,
+        author: UserId,
+    ) -> Result<u32, PageSaveError> {
+        use crate::schema::quests_pages::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        let time_limit_seconds_input = page_source.time_limit.map(|x| x.as_secs() as i32);
+
+        conn.transaction::<_, PageSaveError, _>(|conn| {
+            let current = quests_pages
+                .filter(id.eq(quest_info.id.0).and(page.eq(page_input as i32)))
+                .select((revision, page_id))
+                .for_update()
+                .first::<(i32, Uuid)>(conn)
+                .optional()?;
+            let (new_revision, saved_page_id) = match current {
+                None if page_source.revision == 0 => {
+                    // concurrent inserts of the same page are not overwritten
+                    let inserted_page_id = diesel::insert_into(quests_pages)
+                        .values((
+                            id.eq(quest_info.id.0),
+                            page.eq(page_input as i32),
+                            source.eq(&page_source.source),
+                            time_limit_seconds.eq(time_limit_seconds_input),
+                            revision.eq(1),
+                        ))
+                        .on_conflict_do_nothing()
+                        .returning(page_id)
+                        .get_result::<Uuid>(conn)
+                        .optional()?
+                        .ok_or(PageSaveError::Outdated)?;
+                    if page_input == quest_info.pages {
+                        set_quest_pages_count(conn, quest_info, quest_info.pages + 1).map_err(
+                            |err| match err {
+                                diesel::result::Error::RollbackTransaction => {
+                                    PageSaveError::Outdated
+                                }
+                                _ => PageSaveError::Failed,
+                            },
+                        )?;
+                    }
+                    (1, inserted_page_id)
+                }
+                Some((current_revision, current_page_id))
+                    if current_revision as u32 == page_source.revision =>
+                {
+                    diesel::update(quests_pages)
+                        .filter(id.eq(quest_info.id.0).and(page.eq(page_input as i32)))
+                        .set((
+                            source.eq(&page_source.source),
+                            time_limit_seconds.eq(time_limit_seconds_input),
+                            revision.eq(revision + 1),
+                        ))
+                        .execute(conn)?;
+                    (page_source.revision + 1, current_page_id)
+                }
+                _ => return Err(PageSaveError::Outdated),
+            };
+            insert_quest_revision(
+                conn,
+                quest_info.id,
+                author,
+                Some(saved_page_id),
+                &QuestRevisionContent::Page {
+                    page: page_input,
+                    source: QuestPageSource {
+                        revision: new_revision,
+                        ..page_source
+                    },
+                },
+            )?;
+            Ok(new_revision)
+        })
+    }
+
+    /// Inserts page at `page_input`, moving it and the following ones one page further.
+    /// Inserted page is kept as a revision by `author`
+    pub async fn insert_quest_page(
+        &self,
+        quest_info: &QuestInfo,
         page_input: u32,
-        source_input: String,
-        time_limit_seconds_input: Option<u32>,
+        page_source: QuestPageSource,
+        author: UserId,
     ) -> Option<()> {
         // Some on success
+        use crate::schema::quests_pages;
+        let mut conn = self.get_conn_to_death().await;
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let moves = (page_input..quest_info.pages).map(|old| (old, old + 1));
+            move_quest_pages(conn, quest_info.id, quest_info.pages + 1, moves)?;
+            let inserted_page_id = diesel::insert_into(quests_pages::table)
+                .values((
+                    quests_pages::id.eq(quest_info.id.0),
+                    quests_pages::page.eq(page_input as i32),
+                    quests_pages::source.eq(&page_source.source),
+                    quests_pages::time_limit_seconds
+                        .eq(page_source.time_limit.map(|x| x.as_secs() as i32)),
+                ))
+                .returning(quests_pages::page_id)
+                .get_result::<Uuid>(conn)?;
+            set_quest_pages_count(conn, quest_info, quest_info.pages + 1)?;
+            insert_quest_revision(
+                conn,
+                quest_info.id,
+                author,
+                Some(inserted_page_id),
+                &QuestRevisionContent::Page {
+                    page: page_input,
+                    source: QuestPageSource {
+                        revision: 1,
+                        ..page_source
+                    },
+                },
+            )
+        })
+        .ok()
+    }
+
+    /// Saves page source over the page with `page_id_input`, wherever it is now, or appends it
+    /// with the same identity, if it was deleted since. Restored page is kept as a revision
+    /// by `author`.
+    ///
+    /// Number of the restored page on success.
+    pub async fn restore_quest_page(
+        &self,
+        quest_info: &QuestInfo,
+        page_id_input: Uuid,
+        page_source: QuestPageSource,
+        author: UserId,
+    ) -> Result<u32, PageSaveError> {
         use crate::schema::quests_pages::dsl::*;
         let mut conn = self.get_conn_to_death().await;
+        let time_limit_seconds_input = page_source.time_limit.map(|x| x.as_secs() as i32);
+
+        conn.transaction::<_, PageSaveError, _>(|conn| {
+            let current_page = quests_pages
+                .filter(id.eq(quest_info.id.0).and(page_id.eq(page_id_input)))
+                .select(page)
+                .for_update()
+                .first::<i32>(conn)
+                .optional()?;
+            let (restored_page, new_revision) = match current_page {
+                Some(current_page) => {
+                    // editors of the page are outdated afterwards
+                    let new_revision = diesel::update(quests_pages)
+                        .filter(id.eq(quest_info.id.0).and(page_id.eq(page_id_input)))
+                        .set((
+                            source.eq(&page_source.source),
+                            time_limit_seconds.eq(time_limit_seconds_input),
+                            revision.eq(revision + 1),
+                        ))
+                        .returning(revision)
+                        .get_result::<i32>(conn)?;
+                    (current_page as u32, new_revision as u32)
+                }
+                None => {
+                    diesel::insert_into(quests_pages)
+                        .values((
+                            id.eq(quest_info.id.0),
+                            page.eq(quest_info.pages as i32),
+                            source.eq(&page_source.source),
+                            time_limit_seconds.eq(time_limit_seconds_input),
+                            page_id.eq(page_id_input),
+                        ))
+                        .execute(conn)?;
+                    set_quest_pages_count(conn, quest_info, quest_info.pages + 1).map_err(
+                        |err| match err {
+                            diesel::result::Error::RollbackTransaction => PageSaveError::Outdated,
+                            _ => PageSaveError::Failed,
+                        },
+                    )?;
+                    (quest_info.pages, 1)
+                }
+            };
+            insert_quest_revision(
+                conn,
+                quest_info.id,
+                author,
+                Some(page_id_input),
+                &QuestRevisionContent::Page {
+                    page: restored_page,
+                    source: QuestPageSource {
+                        revision: new_revision,
+                        ..page_source
+                    },
+                },
+            )?;
+            Ok(restored_page)
+        })
+    }
+
+    /// Deletes page at `page_input`, moving the following ones one page closer
+    pub async fn delete_quest_page(&self, quest_info: &QuestInfo, page_input: u32) -> Option<()> {
+        // Some on success
+        use crate::schema::quests_pages;
+        let mut conn = self.get_conn_to_death().await;
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::delete(quests_pages::table)
+                .filter(
+                    quests_pages::id
+                        .eq(quest_info.id.0)
+                        .and(quests_pages::page.eq(page_input as i32)),
+                )
+                .execute(conn)?;
+            let moves = (page_input + 1..quest_info.pages).map(|old| (old, old - 1));
+            move_quest_pages(conn, quest_info.id, quest_info.pages, moves)?;
+            set_quest_pages_count(conn, quest_info, quest_info.pages - 1)
+        })
+        .ok()
+    }
+
+    /// Puts pages in the new order, `order[i]` is the old number of the page, that becomes i-th
+    pub async fn reorder_quest_pages(&self, quest_info: &QuestInfo, order: &[u32]) -> Option<()> {
+        // Some on success
+        let mut conn = self.get_conn_to_death().await;
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let moves = order.iter().zip(0..).map(|(&old, new)| (old, new));
+            move_quest_pages(conn, quest_info.id, quest_info.pages, moves)?;
+            // fails, if pages were added or removed concurrently
+            set_quest_pages_count(conn, quest_info, quest_info.pages)
+        })
+        .ok()
+    }
+
+    pub async fn get_quest_revisions(
+        &self,
+        quest_id_input: Uuid,
+        page: u32,
+    ) -> Option<(Vec<QuestRevisionRecord>, u32)> {
+        // the newest first
+        use crate::schema::quests_revisions::dsl::{self as revisions, quests_revisions};
+        let mut conn = self.get_conn_to_death().await;
+
+        let total_pages = (quests_revisions
+            .filter(revisions::quest_id.eq(quest_id_input))
+            .count()
+            .get_result::<i64>(&mut conn)
+            .ok()? as usize)
+            .div_ceil(QUEST_REVISIONS_PAGE_SIZE) as u32;
+
+        if page > total_pages {
+            return None;
+        }
+
+        Some((
+            quests_revisions
+                .filter(revisions::quest_id.eq(quest_id_input))
+                .select((
+                    revisions::id,
+                    revisions::author,
+                    revisions::created_at,
+                    revisions::page,
+                ))
+                .order_by(revisions::id.desc())
+                .offset((QUEST_REVISIONS_PAGE_SIZE * (page as usize)) as i64)
+                .limit(QUEST_REVISIONS_PAGE_SIZE as i64)
+                .load::<(i32, Uuid, Timestamp, Option<i32>)>(&mut conn)
+                .ok()?
+                .into_iter()
+                .map(|(id, author, created_at, page)| QuestRevisionRecord {
+                    id: id as u32,
+                    author: UserId(author),
+                    created_at,
+                    page: page.map(|page| page as u32),
+                })
+                .collect(),
+            total_pages,
+        ))
+    }
 
-        let source_input_clone = source_input.clone();
-        let time_limit_seconds_input = time_limit_seconds_input.map(|x| x as i32);
-        let time_limit_seconds_input_clone = time_limit_seconds_input.clone();
+    /// Content of the revision, and identity of its page for page revisions
+    pub async fn get_quest_revision(
+        &self,
+        quest_id_input: Uuid,
+        revision_id: u32,
+    ) -> Option<(QuestRevisionContent, Option<Uuid>)> {
+        use crate::schema::quests_revisions::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        let (revision, revision_page_id) = quests_revisions
+            .filter(quest_id.eq(quest_id_input).and(id.eq(revision_id as i32)))
+            .select((content, page_id))
+            .first::<(serde_json::Value, Option<Uuid>)>(&mut conn)
+            .ok()?;
+        Some((serde_json::from_value(revision).ok()?, revision_page_id))
+    }
 
-        diesel::insert_into(quests_pages)
+    pub async fn insert_session(
+        &self,
+        session_id: Uuid,
+        session_user_id: Uuid,
+        session_expires_at: Timestamp,
+    ) -> Option<()> {
+        // Some on success
+        use crate::schema::sessions::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        diesel::insert_into(sessions)
             .values((
-                id.eq(quest_id.0),
-                page.eq(page_input as i32),
-                source.eq(source_input),
-                time_limit_seconds.eq(time_limit_seconds_input),
-            ))
-            .on_conflict((id, page))
-            .do_update()
-            .set((
-                source.eq(source_input_clone),
-                time_limit_seconds.eq(time_limit_seconds_input_clone),
+                id.eq(session_id),
+                user_id.eq(session_user_id),
+                expires_at.eq(session_expires_at),
             ))
             .execute(&mut conn)
             .ok()
             .map(|_x| ())
     }
+
+    /// Owner and expiration of not yet expired session
+    pub async fn get_session(&self, session_id: Uuid) -> Option<(Uuid, Timestamp)> {
+        use crate::schema::sessions::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        sessions
+            .filter(id.eq(session_id))
+            .filter(expires_at.gt(Utc::now().naive_utc()))
+            .select((user_id, expires_at))
+            .first::<(Uuid, Timestamp)>(&mut conn)
+            .ok()
+    }
+
+    pub async fn prolong_session(
+        &self,
+        session_id: Uuid,
+        session_expires_at: Timestamp,
+    ) -> Option<()> {
+        // Some on success
+        use crate::schema::sessions::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        let updated_rows = diesel::update(sessions)
+            .filter(id.eq(session_id))
+            .set(expires_at.eq(session_expires_at))
+            .execute(&mut conn)
+            .ok();
+        match updated_rows {
+            Some(1) => Some(()),
+            _ => None,
+        }
+    }
+
+    pub async fn delete_session(&self, session_id: Uuid) -> Option<()> {
+        // Some on success, even if there was no such session
+        use crate::schema::sessions::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        diesel::delete(sessions.filter(id.eq(session_id)))
+            .execute(&mut conn)
+            .ok()
+            .map(|_x| ())
+    }
+
+    pub async fn delete_user_sessions(&self, session_user_id: Uuid) -> Option<()> {
+        // Some on success
+        use crate::schema::sessions::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        diesel::delete(sessions.filter(user_id.eq(session_user_id)))
+            .execute(&mut conn)
+            .ok()
+            .map(|_x| ())
+    }
+
+    pub async fn delete_expired_sessions(&self) -> Option<usize> {
+        // number of deleted sessions on success
+        use crate::schema::sessions::dsl::*;
+        let mut conn = self.get_conn_to_death().await;
+        diesel::delete(sessions.filter(expires_at.le(Utc::now().naive_utc())))
+            .execute(&mut conn)
+            .ok()
+    }
+}
+
+fn quest_info_row(conn: &mut PgConnection, quest_id: Uuid) -> Option<QuestInfo> {
+    use crate::schema::quests::dsl::*;
+    let result = quests
+        .filter(id.eq(quest_id))
+        .select((
+            (id, owner, title, description, pages, state),
+            (time_limit_seconds, opens_at, closes_at),
+        ))
+        .first::<(
+            (Uuid, Uuid, Option<String>, Option<String>, i32, i16),
+            (Option<i32>, Option<Timestamp>, Option<Timestamp>),
+        )>(conn)
+        .ok();
+    result.and_then(
+        |(
+            (got_id, got_owner, got_title, got_description, got_pages, got_state),
+            (got_time_limit, got_opens_at, got_closes_at),
+        )| {
+            Some(QuestInfo {
+                id: QuestId(got_id),
+                owner: UserId(got_owner),
+                title: got_title.unwrap_or(String::from("")),
+                description: got_description.unwrap_or(String::from("")),
+                pages: got_pages as u32, //todo possibly not good, but i want to see guy who will create 2 billion pages
+                state: QuestState::try_from(u8::try_from(got_state).ok()?).ok()?,
+                time_limit: got_time_limit.map(|secs| Duration::from_secs(secs as u64)),
+                opens_at: got_opens_at,
+                closes_at: got_closes_at,
+            })
+        },
+    )
+}
+
+/// Renumbers pages of the quest, `moves` are pairs of old and new page numbers, that are all
+/// less than `pages_count`. Moved pages are put after the last page first, so that they do not
+/// collide with the ones, that are not moved yet
+fn move_quest_pages(
+    conn: &mut PgConnection,
+    quest_id: QuestId,
+    pages_count: u32,
+    moves: impl IntoIterator<Item = (u32, u32)>,
+) -> diesel::QueryResult<()> {
+    use crate::schema::quests_pages::dsl::*;
+    let offset = pages_count as i32;
+    for (old, new) in moves {
+        diesel::update(quests_pages)
+            .filter(id.eq(quest_id.0).and(page.eq(old as i32)))
+            .set(page.eq(new as i32 + offset))
+            .execute(conn)?;
+    }
+    diesel::update(quests_pages)
+        .filter(id.eq(quest_id.0).and(page.ge(offset)))
+        // sources saved before the move are stale now
+        .set((page.eq(page - offset), revision.eq(revision + 1)))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Sets page count of the quest, rolling back, if it has changed since `quest_info` was read
+fn set_quest_pages_count(
+    conn: &mut PgConnection,
+    quest_info: &QuestInfo,
+    new_pages: u32,
+) -> diesel::QueryResult<()> {
+    use crate::schema::quests::dsl::*;
+    let updated_rows = diesel::update(quests)
+        .filter(
+            id.eq(quest_info.id.0)
+                .and(pages.eq(quest_info.pages as i32)),
+        )
+        .set(pages.eq(new_pages as i32))
+        .execute(conn)?;
+    if updated_rows != 1 {
+        return Err(diesel::result::Error::RollbackTransaction);
+    }
+    Ok(())
+}
+
+/// Appends revision of the quest, made now
+fn insert_quest_revision(
+    conn: &mut PgConnection,
+    quest_id_input: QuestId,
+    author_input: UserId,
+    page_id_input: Option<Uuid>,
+    revision: &QuestRevisionContent,
+) -> diesel::QueryResult<()> {
+    use crate::schema::quests_revisions::dsl::*;
+    let page_input = match revision {
+        QuestRevisionContent::Info(_) => None,
+        QuestRevisionContent::Page { page: page_no, .. } => Some(*page_no as i32),
+    };
+    let revision = serde_json::to_value(revision)
+        .map_err(|err| diesel::result::Error::SerializationError(Box::new(err)))?;
+    diesel::insert_into(quests_revisions)
+        .values((
+            quest_id.eq(quest_id_input.0),
+            author.eq(author_input.0),
+            created_at.eq(Utc::now().naive_utc()),
+            page.eq(page_input),
+            page_id.eq(page_id_input),
+            content.eq(revision),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod database_tests {
+    use super::{Database, PageSaveError};
+    use crate::quests_cache::{migrate_parsed_pages, QuestsCache};
+    use common::{QuestInfo, QuestPageSource, QuestRevisionContent, QuestState, UserId};
+    use diesel::connection::SimpleConnection;
+    use diesel::r2d2::{self, ConnectionManager};
+    use diesel::{Connection, PgConnection, RunQueryDsl};
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    /// Fresh database with every migration applied, on the server from `TEST_DATABASE_URL`
+    /// (e.g. `postgres://postgres@localhost:5432`), tests using it are ignored unless asked for
+    /// with `cargo test -- --ignored`
+    fn test_database() -> Database {
+        let server = std::env::var("TEST_DATABASE_URL")
+            .expect("TEST_DATABASE_URL must be set for database tests");
+        let server = server.trim_end_matches('/');
+        let name = format!("test_{}", Uuid::new_v4().simple());
+        let mut conn = PgConnection::establish(&format!("{server}/postgres")).unwrap();
+        diesel::sql_query(format!("CREATE DATABASE {name}"))
+            .execute(&mut conn)
+            .unwrap();
+
+        let url = format!("{server}/{name}");
+        let mut conn = PgConnection::establish(&url).unwrap();
+        let mut migrations = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/migrations"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        // numbered, same as `ls -v`
+        migrations.sort_by_key(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            name.split('_').next().unwrap().parse::<u32>().unwrap()
+        });
+        for migration in migrations {
+            let up = std::fs::read_to_string(migration.join("up.sql")).unwrap();
+            conn.batch_execute(&up).unwrap();
+        }
+
+        let pool = r2d2::Pool::builder()
+            .build(ConnectionManager::<PgConnection>::new(url))
+            .unwrap();
+        Database::new(pool)
+    }
+
+    async fn author_quest(database: &Database) -> (UserId, QuestInfo) {
+        let user_id = database
+            .insert_user("author", "author@example.com", "hash")
+            .await
+            .unwrap();
+        let quest_id = database.create_quest(user_id).await.unwrap();
+        (UserId(user_id), database.get_quest(quest_id).await.unwrap())
+    }
+
+    /// Appends pages with given sources, returning the newest revision of each
+    async fn append_pages(
+        database: &Database,
+        author: UserId,
+        quest_info: &mut QuestInfo,
+        sources: &[&str],
+    ) -> Vec<u32> {
+        let mut revisions = Vec::new();
+        for source in sources {
+            database
+                .update_quest_page(quest_info, quest_info.pages, page(source, 0), author)
+                .await
+                .unwrap();
+            *quest_info = database.get_quest(quest_info.id.0).await.unwrap();
+            let (newest, _) = database
+                .get_quest_revisions(quest_info.id.0, 0)
+                .await
+                .unwrap();
+            revisions.push(newest[0].id);
+        }
+        revisions
+    }
+
+    async fn page_sources(database: &Database, quest_info: &QuestInfo) -> Vec<String> {
+        let mut sources = Vec::new();
+        for page_number in 0..quest_info.pages {
+            let (source, _, _) = database
+                .get_quest_page(quest_info.id.0, page_number)
+                .await
+                .unwrap();
+            sources.push(source);
+        }
+        sources
+    }
+
+    async fn restore(
+        database: &Database,
+        author: UserId,
+        quest_info: &QuestInfo,
+        revision: u32,
+    ) -> u32 {
+        let Some((QuestRevisionContent::Page { source, .. }, Some(page_id))) =
+            database.get_quest_revision(quest_info.id.0, revision).await
+        else {
+            panic!("revision {revision} is not a page revision");
+        };
+        database
+            .restore_quest_page(quest_info, page_id, source, author)
+            .await
+            .unwrap()
+    }
+
+    fn page(source: &str, revision: u32) -> QuestPageSource {
+        QuestPageSource {
+            source: source.into(),
+            time_limit: None,
+            revision,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[ignore = "needs postgres at TEST_DATABASE_URL"]
+    async fn concurrent_appends() {
+        let database = test_database();
+        let database = Arc::new(database);
+        let (author, quest_info) = author_quest(&database).await;
+
+        let appends = ["first", "second"].map(|source| {
+            let database = database.clone();
+            let quest_info = quest_info.clone();
+            tokio::spawn(async move {
+                database
+                    .update_quest_page(&quest_info, 0, page(source, 0), author)
+                    .await
+            })
+        });
+        let mut saved = Vec::new();
+        for append in appends {
+            saved.push(append.await.unwrap());
+        }
+
+        // exactly one of them is saved, the other one is not written over it
+        let (saved_source, outdated) = match &saved[..] {
+            [Ok(1), Err(PageSaveError::Outdated)] => ("first", "second"),
+            [Err(PageSaveError::Outdated), Ok(1)] => ("second", "first"),
+            other => panic!("unexpected results {other:?}"),
+        };
+        let quest_info = database.get_quest(quest_info.id.0).await.unwrap();
+        assert_eq!(quest_info.pages, 1);
+        let (source, _, revision) = database.get_quest_page(quest_info.id.0, 0).await.unwrap();
+        assert_eq!((source.as_str(), revision), (saved_source, 1));
+
+        // the other tab appends after reloading
+        assert!(matches!(
+            database
+                .update_quest_page(&quest_info, 0, page(outdated, 0), author)
+                .await,
+            Err(PageSaveError::Outdated)
+        ));
+        assert_eq!(
+            database
+                .update_quest_page(&quest_info, 1, page(outdated, 0), author)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(database.get_quest(quest_info.id.0).await.unwrap().pages, 2);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs postgres at TEST_DATABASE_URL"]
+    async fn stale_revision() {
+        let database = test_database();
+        let (author, quest_info) = author_quest(&database).await;
+        let saved = database
+            .update_quest_page(&quest_info, 0, page("a", 0), author)
+            .await
+            .unwrap();
+        let quest_info = database.get_quest(quest_info.id.0).await.unwrap();
+
+        assert_eq!(
+            database
+                .update_quest_page(&quest_info, 0, page("b", saved), author)
+                .await
+                .unwrap(),
+            saved + 1
+        );
+        assert!(matches!(
+            database
+                .update_quest_page(&quest_info, 0, page("c", saved), author)
+                .await,
+            Err(PageSaveError::Outdated)
+        ));
+        let (source, _, _) = database.get_quest_page(quest_info.id.0, 0).await.unwrap();
+        assert_eq!(source, "b");
+    }
+
+    #[tokio::test]
+    #[ignore = "needs postgres at TEST_DATABASE_URL"]
+    async fn restore_after_reorder() {
+        let database = test_database();
+        let (author, mut quest_info) = author_quest(&database).await;
+        let revisions = append_pages(&database, author, &mut quest_info, &["a", "b", "c"]).await;
+        database
+            .update_quest_page(&quest_info, 1, page("b2", 1), author)
+            .await
+            .unwrap();
+        database
+            .reorder_quest_pages(&quest_info, &[1, 0, 2])
+            .await
+            .unwrap();
+
+        // the page is restored where it was moved to, not over the page now at its old place
+        assert_eq!(
+            restore(&database, author, &quest_info, revisions[1]).await,
+            0
+        );
+        let quest_info = database.get_quest(quest_info.id.0).await.unwrap();
+        assert_eq!(page_sources(&database, &quest_info).await, ["b", "a", "c"]);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs postgres at TEST_DATABASE_URL"]
+    async fn restore_after_delete() {
+        let database = test_database();
+        let (author, mut quest_info) = author_quest(&database).await;
+        let revisions = append_pages(&database, author, &mut quest_info, &["a", "b"]).await;
+        database.delete_quest_page(&quest_info, 0).await.unwrap();
+        let quest_info = database.get_quest(quest_info.id.0).await.unwrap();
+
+        // deleted page is appended
+        assert_eq!(
+            restore(&database, author, &quest_info, revisions[0]).await,
+            1
+        );
+        let quest_info = database.get_quest(quest_info.id.0).await.unwrap();
+        assert_eq!(page_sources(&database, &quest_info).await, ["b", "a"]);
+
+        // and is the same page afterwards, restoring it again does not append another one
+        assert_eq!(
+            restore(&database, author, &quest_info, revisions[0]).await,
+            1
+        );
+        let quest_info = database.get_quest(quest_info.id.0).await.unwrap();
+        assert_eq!(page_sources(&database, &quest_info).await, ["b", "a"]);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs postgres at TEST_DATABASE_URL"]
+    async fn outdated_parsed_pages() {
+        let database = test_database();
+        let (author, mut quest_info) = author_quest(&database).await;
+        append_pages(&database, author, &mut quest_info, &["Intro"]).await;
+        // published before parsed pages were saved
+        database
+            .set_quest_state(&quest_info, QuestState::Published, None)
+            .await
+            .unwrap();
+
+        // not parsed on load
+        assert!(QuestsCache::new()
+            .get_or_load(&database, quest_info.id.0)
+            .await
+            .is_none());
+
+        migrate_parsed_pages(&database).await;
+        let parsed_quest = QuestsCache::new()
+            .get_or_load(&database, quest_info.id.0)
+            .await
+            .unwrap();
+        assert_eq!(parsed_quest.pages.len(), 1);
+        assert!(database
+            .get_outdated_parsed_pages(common::QUEST_PAGE_FORMAT)
+            .await
+            .unwrap()
+            .is_empty());
+    }
 }
@@ -0,0 +1,110 @@
+//! Routing of the endpoint catalogue, handlers are checked against the types of their endpoint
+
+use crate::error::ApiError;
+use crate::AppState;
+use axum::extract::ws::WebSocketUpgrade;
+use axum::extract::{FromRequestParts, Multipart};
+use axum::handler::Handler;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Response;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use common::{
+    ApiResponse, Endpoint, FileBody, Method, MultipartBody, WsClientMessage, WsServerMessage,
+};
+use std::future::Future;
+use std::sync::Arc;
+
+/// Extractor of the request body `R`
+pub trait EndpointBody<R> {}
+
+impl<R> EndpointBody<R> for Json<R> {}
+
+impl EndpointBody<MultipartBody> for Multipart {}
+
+impl EndpointBody<WsClientMessage> for WebSocketUpgrade {}
+
+/// Response, that is not wrapped into [`ApiResponse`]
+pub trait RawResponse {}
+
+impl RawResponse for FileBody {}
+
+impl RawResponse for WsServerMessage {}
+
+/// Successful response of the endpoint `E`, headers may be set along the body
+pub trait EndpointResponse<E: Endpoint> {}
+
+impl<E: Endpoint> EndpointResponse<E> for Json<ApiResponse<E::Response, E::Error>> {}
+
+impl<E: Endpoint, R: EndpointResponse<E>> EndpointResponse<E> for (HeaderMap, R) {}
+
+impl<E: Endpoint, R: EndpointResponse<E>> EndpointResponse<E> for (StatusCode, HeaderMap, R) {}
+
+impl<E: Endpoint> EndpointResponse<E> for Response where E::Response: RawResponse {}
+
+/// Handler of the endpoint `E`
+///
+/// Body of the request is extracted by the last argument, endpoints without body
+/// extract only request parts. `M` tells these cases apart and is always inferred.
+pub trait EndpointHandler<E, M> {}
+
+pub struct NoBody;
+
+pub struct WithBody;
+
+macro_rules! endpoint_handler {
+    ($($param:ident),*) => {
+        impl<E, F, Fut, R, $($param,)*> EndpointHandler<E, (NoBody, $($param,)*)> for F
+        where
+            E: Endpoint<Request = ()>,
+            F: FnOnce($($param,)*) -> Fut,
+            Fut: Future<Output = Result<R, ApiError>>,
+            R: EndpointResponse<E>,
+            $($param: FromRequestParts<Arc<AppState>>,)*
+        {
+        }
+
+        impl<E, F, Fut, R, $($param,)* B> EndpointHandler<E, (WithBody, $($param,)* B)> for F
+        where
+            E: Endpoint,
+            F: FnOnce($($param,)* B) -> Fut,
+            Fut: Future<Output = Result<R, ApiError>>,
+            R: EndpointResponse<E>,
+            $($param: FromRequestParts<Arc<AppState>>,)*
+            B: EndpointBody<E::Request>,
+        {
+        }
+    };
+}
+
+endpoint_handler!();
+endpoint_handler!(T1);
+endpoint_handler!(T1, T2);
+endpoint_handler!(T1, T2, T3);
+endpoint_handler!(T1, T2, T3, T4);
+endpoint_handler!(T1, T2, T3, T4, T5);
+endpoint_handler!(T1, T2, T3, T4, T5, T6);
+
+pub trait EndpointRouter {
+    /// Routes the handler by the path and method of the endpoint
+    fn endpoint<E, H, T, M>(self, endpoint: E, handler: H) -> Self
+    where
+        E: Endpoint,
+        H: Handler<T, Arc<AppState>> + EndpointHandler<E, M>,
+        T: 'static;
+}
+
+impl EndpointRouter for Router<Arc<AppState>> {
+    fn endpoint<E, H, T, M>(self, _endpoint: E, handler: H) -> Self
+    where
+        E: Endpoint,
+        H: Handler<T, Arc<AppState>> + EndpointHandler<E, M>,
+        T: 'static,
+    {
+        let method_router = match E::METHOD {
+            Method::Get => get(handler),
+            Method::Post => post(handler),
+        };
+        self.route(E::PATH, method_router)
+    }
+}
@@ -0,0 +1,81 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use common::{ApiResponse, PublishError, QuestStateError};
+
+/// Result of api route, errors are sent as [`ApiResponse::Error`]
+pub type ApiResult<T, E = String> = Result<Json<ApiResponse<T, E>>, ApiError>;
+
+/// Error of api route, every variant has its own status code
+#[derive(Debug)]
+pub enum ApiError {
+    /// 401, no session or it has expired
+    LoginRequired,
+    /// 400, malformed request
+    BadRequest(String),
+    /// 403, logged in, but not allowed
+    Forbidden(String),
+    /// 404
+    NotFound(String),
+    /// 409, action is not possible in current state
+    Conflict(String),
+    /// 422, quest pages can not be played
+    Publish(PublishError),
+    /// 500, details are not exposed
+    Internal,
+}
+
+impl ApiError {
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::BadRequest(message.into())
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::Forbidden(message.into())
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::NotFound(message.into())
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::Conflict(message.into())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::LoginRequired => StatusCode::UNAUTHORIZED,
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Publish(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let message = match self {
+            ApiError::LoginRequired => String::from("login required"),
+            ApiError::BadRequest(message)
+            | ApiError::Forbidden(message)
+            | ApiError::NotFound(message)
+            | ApiError::Conflict(message) => message,
+            ApiError::Publish(err) => {
+                let error = QuestStateError::Unplayable(err);
+                return (
+                    status,
+                    Json(ApiResponse::<(), QuestStateError>::Error(error)),
+                )
+                    .into_response();
+            }
+            ApiError::Internal => String::from(
+                "internal server error, contact administrator with description of this situation",
+            ),
+        };
+        (status, Json(ApiResponse::<()>::Error(message))).into_response()
+    }
+}
@@ -1,18 +1,34 @@
+mod auth;
 mod config;
+mod endpoint;
+mod error;
+mod quests_cache;
 mod schema;
+mod sessions;
 
+use crate::auth::refresh_session;
 use crate::config::load_config;
 use crate::database::Database;
+use crate::endpoint::EndpointRouter;
+use crate::quests_cache::{migrate_parsed_pages, QuestsCache};
+use crate::route::admin_users::{ban_user, get_users, set_user_role, unban_user};
 use crate::route::create_quest::create_quest;
 use crate::route::get_applied_quests::get_applied_quests;
 use crate::route::get_avatar::get_avatar;
 use crate::route::get_quest_info::get_quest_info;
+use crate::route::get_quest_results::get_quest_results;
 use crate::route::get_quests_page::get_quest_page;
 use crate::route::get_user_info::get_user_info;
 use crate::route::get_user_owned_quests::get_user_owned_quests;
 use crate::route::login::login_user;
+use crate::route::logout::{logout, logout_all};
 use crate::route::partial_update_quest_info::partial_update_quest_info;
 use crate::route::publish_quest::publish_quest;
+use crate::route::quest_pages::{delete_quest_page, insert_quest_page, reorder_quest_pages};
+use crate::route::quest_revisions::{
+    diff_quest_revisions, get_quest_revisions, restore_quest_revision,
+};
+use crate::route::quest_state::{approve_quest, lock_quest, return_quest, submit_quest};
 use crate::route::quests_join::quest_join;
 use crate::route::quests_owner_rate::get_quests_owner_rate;
 use crate::route::register::register_user;
@@ -20,17 +36,16 @@ use crate::route::update_avatar::update_avatar;
 use crate::route::update_quest_page::update_quest_page;
 use crate::route::update_rate_comment::update_rate_comment;
 use crate::route::ws_quest_participation::ws_quest_participation_handler;
-use axum::routing::get;
-use axum::{routing::post, Router};
-use common::{AskQuestPage, UserId};
+use crate::sessions::Sessions;
+use axum::middleware;
+use axum::Router;
+use common::endpoints::*;
 use diesel::r2d2::{self, ConnectionManager};
 use diesel::PgConnection;
-use moka::future::Cache;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Mutex;
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
 mod database;
@@ -38,25 +53,16 @@ mod route;
 
 struct AppState {
     pub database: Database,
-    pub session_cache: Cache<Uuid, UserId>,
-    pub quests_cache: Mutex<HashMap<Uuid, HashMap<u32, AskQuestPage>>>,
+    pub sessions: Sessions,
+    pub quests_cache: QuestsCache,
+    /// ids of quests being locked, active players of these are bailed
+    pub locked_quests: broadcast::Sender<Uuid>,
 }
 
+const EXPIRED_SESSIONS_CLEANUP_PERIOD: Duration = Duration::from_secs(60 * 60);
+
 // possible improvement tasks
-// potentially redo cache into own struct and use its methods
 // guarantee session keys and other uuid uniqueness
-// remove ttl timeout?
-// user admin role and things with other posts
-
-// move to common
-#[derive(Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum ApiResponse<T> {
-    #[serde(rename = "error")]
-    Error(String),
-    #[serde(untagged)]
-    Response(T),
-}
 
 #[tokio::main]
 async fn main() {
@@ -77,45 +83,66 @@ async fn main() {
         .expect("Failed to create pool");
     println!("Success");
     let database = Database::new(db_pool);
-
-    let session_cache = Cache::builder()
-        .time_to_idle(Duration::from_secs(5 * 60))
-        //if you want to change, also look for Max-Age=300
-        .build();
+    migrate_parsed_pages(&database).await;
 
     let app_state = AppState {
         database,
-        session_cache,
-        quests_cache: Mutex::new(HashMap::new()),
+        sessions: Sessions::new(&config.session),
+        quests_cache: QuestsCache::new(),
+        locked_quests: broadcast::Sender::new(64),
     };
     let app_state = Arc::new(app_state);
 
+    let cleanup_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EXPIRED_SESSIONS_CLEANUP_PERIOD);
+        loop {
+            interval.tick().await;
+            cleanup_state.database.delete_expired_sessions().await;
+        }
+    });
+
     let app = Router::new()
-        .route("/api/login", post(login_user))
-        .route("/api/register", post(register_user))
-        .route("/api/get_user/{username_or_email}", get(get_user_info))
-        .route("/api/update_avatar", post(update_avatar))
-        .route("/api/get_avatar/{user_id}", get(get_avatar))
-        .route("/api/quests/create", post(create_quest))
-        .route("/api/quests/{id}/info", get(get_quest_info))
-        .route("/api/quests/{id}/info", post(partial_update_quest_info))
-        .route("/api/quests/{id}/page/{page}", get(get_quest_page))
-        .route("/api/quests/{id}/page/{page}", post(update_quest_page))
-        .route("/api/owned_quests/page/{page}", get(get_user_owned_quests))
-        .route("/api/quests/join/{id}", post(quest_join))
-        .route("/api/applied_quests/{page}", get(get_applied_quests))
-        .route("/api/quests/{id}/publish", post(publish_quest))
-        .route(
-            "/api/quests/{id}/update_rate_comment",
-            post(update_rate_comment),
-        )
+        .endpoint(Login, login_user)
+        .endpoint(Register, register_user)
+        .endpoint(Logout, logout)
+        .endpoint(LogoutAll, logout_all)
+        .endpoint(GetUserInfo, get_user_info)
+        .endpoint(UpdateAvatar, update_avatar)
+        .endpoint(GetAvatar, get_avatar)
+        .endpoint(CreateQuest, create_quest)
+        .endpoint(GetQuestInfo, get_quest_info)
+        .endpoint(UpdateQuestInfo, partial_update_quest_info)
+        .endpoint(GetQuestPage, get_quest_page)
+        .endpoint(UpdateQuestPage, update_quest_page)
+        .endpoint(InsertQuestPage, insert_quest_page)
+        .endpoint(DeleteQuestPage, delete_quest_page)
+        .endpoint(ReorderQuestPages, reorder_quest_pages)
+        .endpoint(GetOwnedQuests, get_user_owned_quests)
+        .endpoint(GetQuestRevisions, get_quest_revisions)
+        .endpoint(DiffQuestRevisions, diff_quest_revisions)
+        .endpoint(RestoreQuestRevision, restore_quest_revision)
+        .endpoint(JoinQuest, quest_join)
+        .endpoint(GetAppliedQuests, get_applied_quests)
+        .endpoint(GetQuestResults, get_quest_results)
+        .endpoint(PublishQuest, publish_quest)
+        .endpoint(SubmitQuest, submit_quest)
+        .endpoint(ReturnQuest, return_quest)
+        .endpoint(ApproveQuest, approve_quest)
+        .endpoint(LockQuest, lock_quest)
+        .endpoint(UpdateRateComment, update_rate_comment)
         // овнер не може бачити коменти і середній рейт на зараз на власні квести
         //      (середній рейт потенційно можна додати, коменти схоже потребують власної сторінки(?))
-        .route("/api/quests/owner_rate", get(get_quests_owner_rate))
-        .route("/api/ws/quest/{id}", get(ws_quest_participation_handler))
-        // todo .route("/api/ws/quest/:id"... а в ньому фактичне отримання пейджів...
-        //       по мірі отримання з ws відповідей змінювати completed_pages в таблиці апплайед,
-        //       а якшо останній пейдж поставити finished, etc
+        .endpoint(GetOwnersRate, get_quests_owner_rate)
+        .endpoint(QuestParticipation, ws_quest_participation_handler)
+        .endpoint(GetUsers, get_users)
+        .endpoint(SetUserRole, set_user_role)
+        .endpoint(BanUser, ban_user)
+        .endpoint(UnbanUser, unban_user)
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            refresh_session,
+        ))
         .fallback_service(tower_http::services::ServeDir::new("/usr/serve/"))
         .with_state(app_state);
 
@@ -124,5 +151,10 @@ async fn main() {
         .unwrap();
 
     println!("Starting server");
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
@@ -0,0 +1,140 @@
+use crate::database::Database;
+use common::{page_max_score, parse_quest_page, QuestPage, QUEST_PAGE_FORMAT};
+use moka::future::Cache;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+// cache is weighted by pages, so big quests push out more small ones
+const MAX_CACHED_PAGES: u64 = 10_000;
+const TIME_TO_IDLE: Duration = Duration::from_secs(60 * 60);
+
+pub struct ParsedPage {
+    /// page with correct answers, used for grading, and asked with answers stripped
+    pub page: QuestPage,
+    pub time_limit: Option<Duration>,
+}
+
+pub struct ParsedQuest {
+    pub pages: Box<[ParsedPage]>,
+}
+
+impl ParsedQuest {
+    pub fn page(&self, page: u32) -> Option<&ParsedPage> {
+        self.pages.get(page as usize)
+    }
+
+    /// Score for answering every question correctly, saturates at `u32::MAX`
+    pub fn max_score(&self) -> u32 {
+        self.pages.iter().fold(0, |score, parsed_page| {
+            score.saturating_add(page_max_score(&parsed_page.page))
+        })
+    }
+}
+
+/// Parsed pages of published quests.
+///
+/// Published quests can not be edited, so entries are never stale and are only evicted to save memory.
+pub struct QuestsCache {
+    cache: Cache<Uuid, Arc<ParsedQuest>>,
+}
+
+impl QuestsCache {
+    pub fn new() -> Self {
+        let cache = Cache::builder()
+            .max_capacity(MAX_CACHED_PAGES)
+            .weigher(|_, quest: &Arc<ParsedQuest>| {
+                u32::try_from(quest.pages.len()).unwrap_or(u32::MAX).max(1)
+            })
+            .time_to_idle(TIME_TO_IDLE)
+            .build();
+        Self { cache }
+    }
+
+    /// Returns cached quest, loading its pages on a miss.
+    ///
+    /// Concurrent misses for the same quest are resolved by a single load.
+    /// None on database failure, or if some page fails to parse.
+    pub async fn get_or_load(
+        &self,
+        database: &Database,
+        quest_id: Uuid,
+    ) -> Option<Arc<ParsedQuest>> {
+        self.cache
+            .optionally_get_with(quest_id, Self::load(database, quest_id))
+            .await
+    }
+
+    /// Drops cached quest, so it will be parsed again on the next access
+    pub async fn invalidate(&self, quest_id: Uuid) {
+        self.cache.invalidate(&quest_id).await;
+    }
+
+    async fn load(database: &Database, quest_id: Uuid) -> Option<Arc<ParsedQuest>> {
+        let saved_pages = database.get_quest_parsed_pages(quest_id).await?;
+        let pages = saved_pages
+            .into_iter()
+            .zip(0..)
+            .map(|((parsed, format, time_limit), page_no)| {
+                // pages are saved parsed on publish and migrated on start, never parsed here,
+                // as the source could mean something else to the current parser
+                let page = match (parsed, format) {
+                    (Some(parsed), Some(QUEST_PAGE_FORMAT)) => {
+                        match serde_json::from_value::<QuestPage>(parsed) {
+                            Ok(page) => page,
+                            Err(err) => {
+                                eprintln!(
+                                    "Page {page_no} of quest {quest_id} does not deserialize: {err}"
+                                );
+                                return None;
+                            }
+                        }
+                    }
+                    (_, format) => {
+                        eprintln!(
+                            "Page {page_no} of quest {quest_id} is saved in format {format:?}, \
+                             expected {QUEST_PAGE_FORMAT}"
+                        );
+                        return None;
+                    }
+                };
+                Some(ParsedPage {
+                    page,
+                    time_limit: time_limit.map(|secs| Duration::from_secs(secs.into())),
+                })
+            })
+            .collect::<Option<Box<[ParsedPage]>>>()?;
+        Some(Arc::new(ParsedQuest { pages }))
+    }
+}
+
+/// Parses again pages of published quests, that were saved in an older format or not saved
+/// parsed at all, so they can be loaded into the cache. Run on start, before serving players.
+///
+/// Pages, that fail to parse, are reported and left as they are, their quests fail to load.
+pub async fn migrate_parsed_pages(database: &Database) {
+    let Some(outdated) = database.get_outdated_parsed_pages(QUEST_PAGE_FORMAT).await else {
+        eprintln!("Failed to look up parsed pages to migrate");
+        return;
+    };
+    for (quest_id, page, source) in outdated {
+        match parse_quest_page(source) {
+            Ok(parsed) => {
+                if database
+                    .set_parsed_page(quest_id, page, &parsed, QUEST_PAGE_FORMAT)
+                    .await
+                    .is_some()
+                {
+                    println!(
+                        "Migrated page {page} of quest {quest_id} to format {QUEST_PAGE_FORMAT}"
+                    );
+                } else {
+                    eprintln!("Failed to save migrated page {page} of quest {quest_id}");
+                }
+            }
+            Err(errors) => {
+                eprintln!("Page {page} of quest {quest_id} does not parse any more: {errors:?}")
+            }
+        }
+    }
+}
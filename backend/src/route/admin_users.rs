@@ -0,0 +1,99 @@
+use crate::auth::Admin;
+use crate::error::{ApiError, ApiResult};
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::Json;
+use common::{AdminUsersPage, ApiResponse, UserId, UserRole};
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub async fn get_users(
+    state: State<Arc<AppState>>,
+    Path(page): Path<String>,
+    _: Admin,
+) -> ApiResult<AdminUsersPage> {
+    let requested_page = page
+        .parse::<u32>()
+        .map_err(|_| ApiError::bad_request("provided bad page number"))?;
+
+    let (data, total_pages) = state
+        .database
+        .get_users(requested_page)
+        .await
+        .ok_or_else(|| ApiError::not_found("there are no such page"))?;
+    Ok(Json(ApiResponse::Response(AdminUsersPage {
+        data: data.into_boxed_slice(),
+        page: requested_page,
+        total_pages,
+    })))
+}
+
+pub async fn set_user_role(
+    state: State<Arc<AppState>>,
+    Path(id): Path<String>,
+    _: Admin,
+    Json(new_role): Json<UserRole>,
+) -> ApiResult<()> {
+    if new_role == UserRole::Admin {
+        return Err(ApiError::forbidden(
+            "only users and moderators can be assigned",
+        ));
+    }
+
+    let user_uuid = managed_user(&state, &id).await?;
+    state
+        .database
+        .set_user_role(user_uuid, new_role)
+        .await
+        .ok_or(ApiError::Internal)?;
+    Ok(Json(ApiResponse::Response(())))
+}
+
+pub async fn ban_user(
+    state: State<Arc<AppState>>,
+    Path(id): Path<String>,
+    _: Admin,
+) -> ApiResult<()> {
+    set_user_banned(&state, &id, true).await
+}
+
+pub async fn unban_user(
+    state: State<Arc<AppState>>,
+    Path(id): Path<String>,
+    _: Admin,
+) -> ApiResult<()> {
+    set_user_banned(&state, &id, false).await
+}
+
+async fn set_user_banned(state: &AppState, id: &str, banned: bool) -> ApiResult<()> {
+    let user_uuid = managed_user(state, id).await?;
+
+    state
+        .database
+        .set_user_banned(user_uuid, banned)
+        .await
+        .ok_or(ApiError::Internal)?;
+
+    if banned {
+        // log out everywhere right away
+        state
+            .sessions
+            .revoke_user(&state.database, UserId(user_uuid))
+            .await
+            .ok_or(ApiError::Internal)?;
+    }
+
+    Ok(Json(ApiResponse::Response(())))
+}
+
+/// Users, that admins can manage (admins themselves can not be managed)
+async fn managed_user(state: &AppState, id: &str) -> Result<Uuid, ApiError> {
+    let user_uuid = Uuid::from_str(id).map_err(|_| ApiError::bad_request("bad user id"))?;
+
+    match state.database.get_user_role_banned(user_uuid).await {
+        None => Err(ApiError::not_found("user not found")),
+        Some((UserRole::Admin, _)) => Err(ApiError::forbidden("admins can not be managed")),
+        Some(_) => Ok(user_uuid),
+    }
+}
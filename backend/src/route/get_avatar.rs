@@ -1,30 +1,29 @@
-use crate::{ApiResponse, AppState};
+use crate::error::ApiError;
+use crate::AppState;
 use axum::body::Body;
 use axum::extract::{Path, State};
 use axum::http::header::CONTENT_TYPE;
-use axum::response::{IntoResponse, Response};
-use axum::{http::StatusCode, Json};
+use axum::http::StatusCode;
+use axum::response::Response;
 use std::str::FromStr;
 use std::sync::Arc;
 use uuid::Uuid;
 
-pub async fn get_avatar(Path(user_id): Path<String>, state: State<Arc<AppState>>) -> Response {
-    let user_uuid = match Uuid::from_str(user_id.as_str()) {
-        Ok(uuid) => uuid,
-        Err(_) => {
-            return Json(ApiResponse::<()>::Error(String::from("bad user id"))).into_response();
-        }
-    };
+pub async fn get_avatar(
+    Path(user_id): Path<String>,
+    state: State<Arc<AppState>>,
+) -> Result<Response, ApiError> {
+    let user_uuid =
+        Uuid::from_str(user_id.as_str()).map_err(|_| ApiError::bad_request("bad user id"))?;
 
-    match state.database.get_user_avatar(user_uuid).await {
-        None => Json(ApiResponse::<()>::Error(String::from(
-            "there are no avatar for such id",
-        )))
-        .into_response(),
-        Some((_, content_type, avatar_data)) => Response::builder()
-            .status(StatusCode::OK)
-            .header(CONTENT_TYPE, content_type)
-            .body(Body::from(avatar_data))
-            .unwrap(),
-    }
+    let (_, content_type, avatar_data) = state
+        .database
+        .get_user_avatar(user_uuid)
+        .await
+        .ok_or_else(|| ApiError::not_found("there are no avatar for such id"))?;
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, content_type)
+        .body(Body::from(avatar_data))
+        .map_err(|_| ApiError::Internal)
 }
@@ -1,32 +1,38 @@
-use crate::{ApiResponse, AppState};
+use crate::auth::{AuthUser, MaybeAuthUser};
+use crate::error::{ApiError, ApiResult};
+use crate::AppState;
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
 use axum::Json;
-use common::{QuestInfo};
+use common::{ApiResponse, QuestInfo};
 use std::str::FromStr;
 use std::sync::Arc;
 use uuid::Uuid;
 
 pub async fn get_quest_info(
     Path(id): Path<String>,
+    MaybeAuthUser(user_id): MaybeAuthUser,
     state: State<Arc<AppState>>,
-) -> (StatusCode, Json<ApiResponse<QuestInfo>>) {
-    let quest_id = match Uuid::from_str(id.as_str()) {
-        Ok(uuid) => uuid,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::Error(String::from("internal server error, contact administrator with description of this situation"))),
-            );
-        }
-    };
+) -> ApiResult<QuestInfo> {
+    let quest_id =
+        Uuid::from_str(id.as_str()).map_err(|_| ApiError::bad_request("bad quest id"))?;
+
+    let quest_info = state
+        .database
+        .get_quest(quest_id)
+        .await
+        .ok_or_else(|| ApiError::not_found("quest not found"))?;
 
-    if let Some(quest_info) = state.database.get_quest(quest_id).await {
-        return (StatusCode::OK, Json(ApiResponse::Response(quest_info)));
+    // quests in work are only seen by their authors and moderators
+    if !quest_info.state.is_published() {
+        let visible = match user_id {
+            None => false,
+            Some(user_id) if quest_info.owner == user_id => true,
+            Some(user_id) => AuthUser(user_id).is_moderator(&state.database).await?,
+        };
+        if !visible {
+            return Err(ApiError::not_found("quest not found"));
+        }
     }
 
-    (
-        StatusCode::NOT_FOUND,
-        Json(ApiResponse::Error(String::from("quest not found"))),
-    )
+    Ok(Json(ApiResponse::Response(quest_info)))
 }
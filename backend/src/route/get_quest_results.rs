@@ -0,0 +1,36 @@
+use crate::auth::AuthUser;
+use crate::error::{ApiError, ApiResult};
+use crate::route::quest_state::accessible_quest;
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::Json;
+use common::{ApiResponse, QuestHistoryPage};
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub async fn get_quest_results(
+    state: State<Arc<AppState>>,
+    Path((id, page)): Path<(String, String)>,
+    user: AuthUser,
+) -> ApiResult<QuestHistoryPage> {
+    let quest_id =
+        Uuid::from_str(id.as_str()).map_err(|_| ApiError::bad_request("provided bad quest id"))?;
+    let results_page = page
+        .parse::<u32>()
+        .map_err(|_| ApiError::bad_request("provided bad page number"))?;
+
+    // results are shown to the author and moderators only
+    accessible_quest(&state, &user, quest_id).await?;
+
+    let (results, total_pages) = state
+        .database
+        .get_quest_results(quest_id, results_page)
+        .await
+        .ok_or_else(|| ApiError::not_found("there are no such page"))?;
+    Ok(Json(ApiResponse::Response(QuestHistoryPage {
+        data: results.into_boxed_slice(),
+        page: results_page,
+        total_pages,
+    })))
+}
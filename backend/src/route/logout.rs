@@ -0,0 +1,43 @@
+use crate::auth::{session_id, AuthUser};
+use crate::error::ApiError;
+use crate::AppState;
+use axum::extract::State;
+use axum::http::header::SET_COOKIE;
+use axum::http::HeaderMap;
+use axum::Json;
+use common::ApiResponse;
+use std::sync::Arc;
+
+pub async fn logout(
+    state: State<Arc<AppState>>,
+    request_headers: HeaderMap,
+) -> Result<(HeaderMap, Json<ApiResponse<()>>), ApiError> {
+    // logging out without a session is fine, cookie is removed anyway
+    if let Some(session_id) = session_id(&request_headers) {
+        state
+            .sessions
+            .revoke(&state.database, session_id)
+            .await
+            .ok_or(ApiError::Internal)?;
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert(SET_COOKIE, state.sessions.removal_cookie());
+    Ok((headers, Json(ApiResponse::Response(()))))
+}
+
+/// Logs out every device of the user, including the current one
+pub async fn logout_all(
+    state: State<Arc<AppState>>,
+    AuthUser(user_id): AuthUser,
+) -> Result<(HeaderMap, Json<ApiResponse<()>>), ApiError> {
+    state
+        .sessions
+        .revoke_user(&state.database, user_id)
+        .await
+        .ok_or(ApiError::Internal)?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(SET_COOKIE, state.sessions.removal_cookie());
+    Ok((headers, Json(ApiResponse::Response(()))))
+}
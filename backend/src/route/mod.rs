@@ -1,13 +1,19 @@
+pub mod admin_users;
 pub mod create_quest;
 pub mod get_applied_quests;
 pub mod get_avatar;
 pub mod get_quest_info;
+pub mod get_quest_results;
 pub mod get_quests_page;
 pub mod get_user_info;
 pub mod get_user_owned_quests;
 pub mod login;
+pub mod logout;
 pub mod partial_update_quest_info;
 pub mod publish_quest;
+pub mod quest_pages;
+pub mod quest_revisions;
+pub mod quest_state;
 pub mod quests_join;
 pub mod quests_owner_rate;
 pub mod register;
@@ -1,90 +1,51 @@
-use crate::{ApiResponse, AppState};
+use crate::auth::AuthUser;
+use crate::error::{ApiError, ApiResult};
+use crate::route::quest_state::{editable_quest, parse_quest_id};
+use crate::AppState;
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
 use axum::Json;
-use axum_extra::headers::Cookie;
-use axum_extra::TypedHeader;
-use common::QuestInfo;
-use std::str::FromStr;
+use common::{ApiResponse, QuestId, QuestInfo};
 use std::sync::Arc;
-use uuid::Uuid;
+use std::time::Duration;
 
-pub async fn partial_update_quest_info(
-    Path(id): Path<String>,
-    state: State<Arc<AppState>>,
-    TypedHeader(session): TypedHeader<Cookie>,
-    Json(payload): Json<QuestInfo>,
-) -> (StatusCode, Json<ApiResponse<()>>) {
-    let session_value = match session.get("session") {
-        None => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::Error(String::from("login required"))),
-            );
-        }
-        Some(value) => value,
-    };
+const MAX_QUEST_TIME_LIMIT: Duration = Duration::from_secs(7 * 24 * 60 * 60);
 
-    let session_uuid = match Uuid::from_str(session_value) {
-        Ok(uuid) => uuid,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::Error(String::from("internal server error, contact administrator with description of this situation"))),
-            );
-        }
-    };
-
-    let user_id = match state.session_cache.get(&session_uuid).await {
-        None => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::Error(String::from("login required"))),
-            );
+/// Checks quest info fields, that are editable by the author
+pub fn check_quest_info(quest_info: &QuestInfo) -> Result<(), ApiError> {
+    if quest_info
+        .time_limit
+        .is_some_and(|limit| limit.as_secs() == 0 || limit > MAX_QUEST_TIME_LIMIT)
+    {
+        return Err(ApiError::bad_request(
+            "time limit must be from 1 second to 7 days",
+        ));
+    }
+    if let (Some(opens_at), Some(closes_at)) = (quest_info.opens_at, quest_info.closes_at) {
+        if opens_at >= closes_at {
+            return Err(ApiError::bad_request("quest must open before it closes"));
         }
-        Some(user_id) => user_id,
-    };
+    }
+    Ok(())
+}
 
-    let quest_id = match Uuid::from_str(id.as_str()) {
-        Ok(uuid) => uuid,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::Error(String::from("internal server error, contact administrator with description of this situation"))),
-            );
-        }
-    };
+pub async fn partial_update_quest_info(
+    Path(id): Path<String>,
+    state: State<Arc<AppState>>,
+    user: AuthUser,
+    Json(mut payload): Json<QuestInfo>,
+) -> ApiResult<()> {
+    let quest_id = parse_quest_id(&id)?;
 
-    if let Some(quest_info) = state.database.get_quest(quest_id).await {
-        if quest_info.owner != user_id {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::Error(String::from(
-                    "you do now own this quest",
-                ))),
-            );
-        }
-        if quest_info.published {
-            return (
-                StatusCode::FORBIDDEN,
-                Json(ApiResponse::Error(String::from(
-                    "not updatable after publish",
-                ))),
-            );
-        }
-    } else {
-        return (
-            StatusCode::NOT_FOUND,
-            Json(ApiResponse::Error(String::from("there are no such quest"))),
-        );
-    }
+    editable_quest(&state, &user, quest_id).await?;
 
-    if let Some(quest_info) = state.database.update_quest(payload).await {
-        return (StatusCode::OK, Json(ApiResponse::Response(quest_info)));
-    }
+    check_quest_info(&payload)?;
 
-    (
-        StatusCode::NOT_FOUND,
-        Json(ApiResponse::Error(String::from("quest not found"))),
-    )
+    // quest is identified by the path, not by the payload
+    payload.id = QuestId(quest_id);
+    state
+        .database
+        .update_quest(payload, user.0)
+        .await
+        .ok_or_else(|| ApiError::not_found("quest not found"))?;
+    Ok(Json(ApiResponse::Response(())))
 }
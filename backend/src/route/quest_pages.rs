@@ -0,0 +1,86 @@
+use crate::auth::AuthUser;
+use crate::error::{ApiError, ApiResult};
+use crate::route::quest_state::{editable_quest, parse_quest_id};
+use crate::route::update_quest_page::check_time_limit;
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::Json;
+use common::{ApiResponse, QuestPageSource};
+use std::sync::Arc;
+
+fn parse_page(page: &str) -> Result<u32, ApiError> {
+    page.parse::<u32>()
+        .map_err(|_| ApiError::bad_request("provided bad page number"))
+}
+
+pub async fn insert_quest_page(
+    state: State<Arc<AppState>>,
+    Path((id, page)): Path<(String, String)>,
+    user: AuthUser,
+    Json(page_source): Json<QuestPageSource>,
+) -> ApiResult<()> {
+    let quest_info = editable_quest(&state, &user, parse_quest_id(&id)?).await?;
+
+    check_time_limit(page_source.time_limit)?;
+
+    let quest_page = parse_page(&page)?;
+    if quest_page > quest_info.pages {
+        return Err(ApiError::bad_request(
+            "provided page number exceeding current possible page number",
+        ));
+    }
+
+    state
+        .database
+        .insert_quest_page(&quest_info, quest_page, page_source, user.0)
+        .await
+        .ok_or(ApiError::Internal)?;
+
+    Ok(Json(ApiResponse::Response(())))
+}
+
+pub async fn delete_quest_page(
+    state: State<Arc<AppState>>,
+    Path((id, page)): Path<(String, String)>,
+    user: AuthUser,
+) -> ApiResult<()> {
+    let quest_info = editable_quest(&state, &user, parse_quest_id(&id)?).await?;
+
+    let quest_page = parse_page(&page)?;
+    if quest_page >= quest_info.pages {
+        return Err(ApiError::bad_request("provided page does not exist"));
+    }
+
+    state
+        .database
+        .delete_quest_page(&quest_info, quest_page)
+        .await
+        .ok_or(ApiError::Internal)?;
+
+    Ok(Json(ApiResponse::Response(())))
+}
+
+pub async fn reorder_quest_pages(
+    state: State<Arc<AppState>>,
+    Path(id): Path<String>,
+    user: AuthUser,
+    Json(order): Json<Vec<u32>>,
+) -> ApiResult<()> {
+    let quest_info = editable_quest(&state, &user, parse_quest_id(&id)?).await?;
+
+    let mut sorted = order.clone();
+    sorted.sort_unstable();
+    if !sorted.into_iter().eq(0..quest_info.pages) {
+        return Err(ApiError::bad_request(
+            "order must list every page number exactly once",
+        ));
+    }
+
+    state
+        .database
+        .reorder_quest_pages(&quest_info, &order)
+        .await
+        .ok_or(ApiError::Internal)?;
+
+    Ok(Json(ApiResponse::Response(())))
+}
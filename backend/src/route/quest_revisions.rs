@@ -0,0 +1,107 @@
+use crate::auth::AuthUser;
+use crate::error::{ApiError, ApiResult};
+use crate::route::partial_update_quest_info::check_quest_info;
+use crate::route::quest_state::{accessible_quest, editable_quest, parse_quest_id};
+use crate::route::update_quest_page::page_save_error;
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::Json;
+use common::{
+    diff_lines, ApiResponse, DiffLine, QuestInfo, QuestRevisionContent, QuestRevisionsPage,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+async fn get_revision(
+    state: &AppState,
+    quest_id: Uuid,
+    revision: &str,
+) -> Result<(QuestRevisionContent, Option<Uuid>), ApiError> {
+    let revision = revision
+        .parse::<u32>()
+        .map_err(|_| ApiError::bad_request("provided bad revision"))?;
+    state
+        .database
+        .get_quest_revision(quest_id, revision)
+        .await
+        .ok_or_else(|| ApiError::not_found("there are no such revision"))
+}
+
+pub async fn get_quest_revisions(
+    state: State<Arc<AppState>>,
+    Path((id, page)): Path<(String, String)>,
+    user: AuthUser,
+) -> ApiResult<QuestRevisionsPage> {
+    let quest_id = parse_quest_id(&id)?;
+    let revisions_page = page
+        .parse::<u32>()
+        .map_err(|_| ApiError::bad_request("provided bad page number"))?;
+
+    accessible_quest(&state, &user, quest_id).await?;
+
+    let (revisions, total_pages) = state
+        .database
+        .get_quest_revisions(quest_id, revisions_page)
+        .await
+        .ok_or_else(|| ApiError::not_found("there are no such page"))?;
+    Ok(Json(ApiResponse::Response(QuestRevisionsPage {
+        data: revisions.into_boxed_slice(),
+        page: revisions_page,
+        total_pages,
+    })))
+}
+
+pub async fn diff_quest_revisions(
+    state: State<Arc<AppState>>,
+    Path((id, revision, other)): Path<(String, String, String)>,
+    user: AuthUser,
+) -> ApiResult<Vec<DiffLine>> {
+    let quest_id = parse_quest_id(&id)?;
+
+    accessible_quest(&state, &user, quest_id).await?;
+
+    let (revision, _) = get_revision(&state, quest_id, &revision).await?;
+    let (other, _) = get_revision(&state, quest_id, &other).await?;
+    Ok(Json(ApiResponse::Response(diff_lines(
+        &revision.text(),
+        &other.text(),
+    ))))
+}
+
+pub async fn restore_quest_revision(
+    state: State<Arc<AppState>>,
+    Path((id, revision)): Path<(String, String)>,
+    user: AuthUser,
+) -> ApiResult<()> {
+    let quest_id = parse_quest_id(&id)?;
+
+    let quest_info = editable_quest(&state, &user, quest_id).await?;
+
+    // restored content is saved as a new revision
+    match get_revision(&state, quest_id, &revision).await? {
+        (QuestRevisionContent::Info(info), _) => {
+            let info = QuestInfo {
+                id: quest_info.id,
+                ..info
+            };
+            // limits could have changed since the revision was saved
+            check_quest_info(&info)?;
+            state
+                .database
+                .update_quest(info, user.0)
+                .await
+                .ok_or(ApiError::Internal)?;
+        }
+        // page is found by its identity, as it could be moved or deleted since
+        (QuestRevisionContent::Page { source, .. }, Some(page_id)) => {
+            state
+                .database
+                .restore_quest_page(&quest_info, page_id, source, user.0)
+                .await
+                .map_err(page_save_error)?;
+        }
+        (QuestRevisionContent::Page { .. }, None) => return Err(ApiError::Internal),
+    }
+
+    Ok(Json(ApiResponse::Response(())))
+}
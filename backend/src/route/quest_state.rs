@@ -0,0 +1,149 @@
+use crate::auth::{AuthUser, Moderator};
+use crate::error::{ApiError, ApiResult};
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::Json;
+use common::{
+    parse_quest_pages, ApiResponse, QuestAction, QuestInfo, QuestState, QuestStateError, UserId,
+};
+use std::str::FromStr;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub async fn submit_quest(
+    Path(id): Path<String>,
+    AuthUser(user_id): AuthUser,
+    state: State<Arc<AppState>>,
+) -> ApiResult<QuestState, QuestStateError> {
+    transition_quest(&state, user_id, &id, QuestAction::Submit).await
+}
+
+pub async fn return_quest(
+    Path(id): Path<String>,
+    Moderator(user_id): Moderator,
+    state: State<Arc<AppState>>,
+) -> ApiResult<QuestState, QuestStateError> {
+    transition_quest(&state, user_id, &id, QuestAction::Return).await
+}
+
+pub async fn approve_quest(
+    Path(id): Path<String>,
+    Moderator(user_id): Moderator,
+    state: State<Arc<AppState>>,
+) -> ApiResult<QuestState, QuestStateError> {
+    transition_quest(&state, user_id, &id, QuestAction::Approve).await
+}
+
+pub async fn lock_quest(
+    Path(id): Path<String>,
+    Moderator(user_id): Moderator,
+    state: State<Arc<AppState>>,
+) -> ApiResult<QuestState, QuestStateError> {
+    transition_quest(&state, user_id, &id, QuestAction::Lock).await
+}
+
+/// Quest id from the route path
+pub fn parse_quest_id(id: &str) -> Result<Uuid, ApiError> {
+    Uuid::from_str(id).map_err(|_| ApiError::bad_request("provided bad quest id"))
+}
+
+/// Quest, that the user owns or moderates, and whether user is a moderator
+pub async fn accessible_quest(
+    state: &AppState,
+    user: &AuthUser,
+    quest_id: Uuid,
+) -> Result<(QuestInfo, bool), ApiError> {
+    let quest_info = state
+        .database
+        .get_quest(quest_id)
+        .await
+        .ok_or_else(|| ApiError::not_found("there are no such quest"))?;
+    let is_moderator = user.is_moderator(&state.database).await?;
+    if quest_info.owner != user.0 && !is_moderator {
+        return Err(ApiError::forbidden("you do now own this quest"));
+    }
+    Ok((quest_info, is_moderator))
+}
+
+/// Quest, that can be edited by the user in its current state
+pub async fn editable_quest(
+    state: &AppState,
+    user: &AuthUser,
+    quest_id: Uuid,
+) -> Result<QuestInfo, ApiError> {
+    let (quest_info, is_moderator) = accessible_quest(state, user, quest_id).await?;
+    let can_edit = (quest_info.owner == user.0 && quest_info.state.author_can_edit())
+        || (is_moderator && quest_info.state.moderator_can_edit());
+    if !can_edit {
+        return Err(ApiError::forbidden(format!(
+            "not updatable in {:?} state",
+            quest_info.state
+        )));
+    }
+    Ok(quest_info)
+}
+
+/// Applies the action, returning new [`QuestState`] on success.
+///
+/// Moderator role must be checked by the caller, ownership is checked here.
+pub async fn transition_quest(
+    state: &AppState,
+    user_uuid: UserId,
+    id: &str,
+    action: QuestAction,
+) -> ApiResult<QuestState, QuestStateError> {
+    let quest_uuid = Uuid::from_str(id).map_err(|_| ApiError::bad_request("bad quest id"))?;
+
+    let quest_info = state
+        .database
+        .get_quest(quest_uuid)
+        .await
+        .ok_or_else(|| ApiError::not_found("there are no such quest"))?;
+
+    if !action.by_moderator() && quest_info.owner != user_uuid {
+        return Err(ApiError::forbidden("you do now own this quest"));
+    }
+
+    let new_state = quest_info.state.apply(action).ok_or_else(|| {
+        ApiError::conflict(format!(
+            "cannot {action:?} quest in {:?} state",
+            quest_info.state
+        ))
+    })?;
+
+    // broken page should never reach players
+    let publishing = new_state.is_published() && !quest_info.state.is_published();
+    let parsed_pages = if publishing {
+        let sources = state
+            .database
+            .get_quest_pages(quest_uuid)
+            .await
+            .ok_or(ApiError::Internal)?;
+        Some(parse_quest_pages(sources).map_err(ApiError::Publish)?)
+    } else {
+        None
+    };
+
+    state
+        .database
+        .set_quest_state(&quest_info, new_state, parsed_pages.as_deref())
+        .await
+        .ok_or(ApiError::Internal)?;
+
+    if publishing {
+        // warm up the cache, so first players don't wait for pages to be loaded
+        state.quests_cache.invalidate(quest_uuid).await;
+        let _ = state
+            .quests_cache
+            .get_or_load(&state.database, quest_uuid)
+            .await;
+    }
+
+    if new_state == QuestState::Locked {
+        // no receivers just means nobody is playing
+        let _ = state.locked_quests.send(quest_uuid);
+        state.quests_cache.invalidate(quest_uuid).await;
+    }
+
+    Ok(Json(ApiResponse::Response(new_state)))
+}
@@ -1,134 +1,75 @@
-use crate::{ApiResponse, AppState};
+use crate::auth::AuthUser;
+use crate::database::PageSaveError;
+use crate::error::{ApiError, ApiResult};
+use crate::route::quest_state::editable_quest;
+use crate::AppState;
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
 use axum::Json;
-use axum_extra::headers::Cookie;
-use axum_extra::TypedHeader;
+use common::{ApiResponse, QuestPageSource};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 use uuid::Uuid;
 
-pub async fn update_quest_page(
-    state: State<Arc<AppState>>,
-    Path(id): Path<String>,
-    Path(page): Path<String>,
-    TypedHeader(session): TypedHeader<Cookie>,
-    Json((source, duration)): Json<(String, Option<Duration>)>,
-) -> (StatusCode, Json<ApiResponse<()>>) {
-    let quest_id = match Uuid::from_str(id.as_str()) {
-        Ok(uuid) => uuid,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::Error(String::from("provided bad quest id"))),
-            );
-        }
-    };
+const MAX_PAGE_TIME_LIMIT: Duration = Duration::from_secs(24 * 60 * 60);
 
-    let session_value = match session.get("session") {
-        None => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::Error(String::from("login required"))),
-            );
-        }
-        Some(value) => value,
-    };
+pub fn check_time_limit(time_limit: Option<Duration>) -> Result<(), ApiError> {
+    if time_limit.is_some_and(|limit| limit.as_secs() == 0 || limit > MAX_PAGE_TIME_LIMIT) {
+        return Err(ApiError::bad_request(
+            "time limit must be from 1 second to 24 hours",
+        ));
+    }
+    Ok(())
+}
 
-    let session_uuid = match Uuid::from_str(session_value) {
-        Ok(uuid) => uuid,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::Error(String::from("internal server error, contact administrator with description of this situation"))),
-            );
-        }
-    };
+pub fn page_save_error(err: PageSaveError) -> ApiError {
+    match err {
+        PageSaveError::Outdated => ApiError::conflict("page was saved by someone else"),
+        PageSaveError::Failed => ApiError::Internal,
+    }
+}
 
-    let user_id = match state.session_cache.get(&session_uuid).await {
-        None => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::Error(String::from("login required"))),
-            );
-        }
-        Some(user_id) => user_id,
-    };
+pub async fn update_quest_page(
+    state: State<Arc<AppState>>,
+    Path((id, page)): Path<(String, String)>,
+    user: AuthUser,
+    Json(QuestPageSource {
+        source,
+        time_limit,
+        revision,
+    }): Json<QuestPageSource>,
+) -> ApiResult<u32> {
+    let quest_id =
+        Uuid::from_str(id.as_str()).map_err(|_| ApiError::bad_request("provided bad quest id"))?;
 
-    let mut quest_info = if let Some(quest_info) = state.database.get_quest(quest_id).await {
-        if quest_info.owner != user_id {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::Error(String::from(
-                    "you do now own this quest",
-                ))),
-            );
-        }
-        if quest_info.published {
-            return (
-                StatusCode::FORBIDDEN,
-                Json(ApiResponse::Error(String::from(
-                    "not updatable after publish",
-                ))),
-            );
-        }
-        quest_info
-    } else {
-        return (
-            StatusCode::NOT_FOUND,
-            Json(ApiResponse::Error(String::from("there are no such quest"))),
-        );
-    };
+    let quest_info = editable_quest(&state, &user, quest_id).await?;
 
-    let quest_page = match page.parse::<u32>() {
-        Ok(quest_page) => quest_page,
-        Err(_) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ApiResponse::Error(String::from("provided bad page number"))),
-            );
-        }
-    };
+    check_time_limit(time_limit)?;
 
-    if quest_info.pages > quest_page {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ApiResponse::Error(String::from(
-                "provided page number exceeding current possible page number",
-            ))),
-        );
-    }
+    let quest_page = page
+        .parse::<u32>()
+        .map_err(|_| ApiError::bad_request("provided bad page number"))?;
 
-    if quest_info.pages == quest_page {
-        quest_info.pages += 1;
-        if let None = state.database.update_quest_pages(&quest_info).await {
-            return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ApiResponse::Error(String::from(
-                        "internal server error, contact administrator with description of this situation",
-                    ))),
-                );
-        }
+    if quest_page > quest_info.pages {
+        return Err(ApiError::bad_request(
+            "provided page number exceeding current possible page number",
+        ));
     }
 
-    if let None = state
+    let new_revision = state
         .database
         .update_quest_page(
-            quest_info.id,
+            &quest_info,
             quest_page,
-            source,
-            duration.map(|x| x.as_secs() as u32),
+            QuestPageSource {
+                source,
+                time_limit,
+                revision,
+            },
+            user.0,
         )
         .await
-    {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiResponse::Error(String::from(
-                "internal server error, contact administrator with description of this situation",
-            ))),
-        );
-    }
+        .map_err(page_save_error)?;
 
-    (StatusCode::OK, Json(ApiResponse::Response(())))
+    Ok(Json(ApiResponse::Response(new_revision)))
 }
@@ -1,128 +1,261 @@
-use crate::{ApiResponse, AppState};
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use crate::quests_cache::ParsedQuest;
+use crate::AppState;
 use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
 use axum::extract::{ConnectInfo, Path, State};
-use axum::http::StatusCode;
-use axum::response::IntoResponse;
-use axum::Json;
-use axum_extra::headers::Cookie;
-use axum_extra::TypedHeader;
-use common::{QuestInfo, WsClientMessage, WsServerMessage};
+use axum::response::Response;
+use common::{
+    ask_quest_page, grade_answer, page_questions, Answer, QuestInfo, ShuffleSeed, Timestamp,
+    WsClientMessage, WsServerMessage,
+};
+use diesel::internal::derives::multiconnection::chrono::{TimeDelta, Utc};
 use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::{interval, MissedTickBehavior};
 use uuid::Uuid;
 
+// answers are still accepted for a moment after the time is up, to make up for latency
+const TIME_LIMIT_GRACE: TimeDelta = TimeDelta::seconds(2);
+const COUNTDOWN_PERIOD: Duration = Duration::from_secs(1);
+
 pub async fn ws_quest_participation_handler(
-    ws: WebSocketUpgrade,
-    TypedHeader(session): TypedHeader<Cookie>,
+    AuthUser(user_uuid): AuthUser,
     Path(id): Path<String>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     state: State<Arc<AppState>>,
-) -> impl IntoResponse {
-    let quest_uuid = match Uuid::from_str(id.as_str()) {
-        Ok(uuid) => uuid,
-        Err(_) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ApiResponse::<()>::Error(String::from("bad quest id"))),
-            )
-                .into_response();
-        }
-    };
+    ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let quest_uuid =
+        Uuid::from_str(id.as_str()).map_err(|_| ApiError::bad_request("bad quest id"))?;
 
-    let session_value = match session.get("session") {
-        None => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::<()>::Error(String::from("login required"))),
-            )
-                .into_response();
-        }
-        Some(value) => value,
-    };
+    let quest_info = state
+        .database
+        .get_quest(quest_uuid)
+        .await
+        .ok_or_else(|| ApiError::not_found("there are no such quest"))?;
+    if !quest_info.state.is_published() {
+        return Err(ApiError::forbidden(
+            "cannot participate in unpublished quest",
+        ));
+    }
 
-    let session_uuid = match Uuid::from_str(session_value) {
-        Ok(uuid) => uuid,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse::<()>::Error(String::from("internal server error, contact administrator with description of this situation"))),
-            ).into_response();
-        }
+    let started_at = match state
+        .database
+        .get_user_quest_history_questdata(user_uuid.0, quest_uuid)
+        .await
+    {
+        Some((started_at, None, _)) => started_at,
+        Some((_, Some(_), _)) => return Err(ApiError::forbidden("you finished quest")),
+        None => return Err(ApiError::forbidden("not joined to quest")),
     };
 
-    let user_uuid = match state.session_cache.get(&session_uuid).await {
-        None => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                Json(ApiResponse::<()>::Error(String::from("login required"))),
-            )
-                .into_response();
-        }
-        Some(user_id) => user_id,
-    };
+    let quest_deadline = quest_info.deadline(started_at);
+    if quest_deadline.is_some_and(|deadline| Utc::now().naive_utc() >= deadline + TIME_LIMIT_GRACE)
+    {
+        // nobody was connected, when time ran out
+        state
+            .database
+            .set_user_finished_quest(&user_uuid.0, &quest_uuid)
+            .await
+            .ok_or(ApiError::Internal)?;
+        return Err(ApiError::forbidden("time for the quest is up"));
+    }
 
-    let quest_info = if let Some(quest_info) = state.database.get_quest(quest_uuid).await {
-        if !quest_info.published {
-            return (
-                StatusCode::FORBIDDEN,
-                Json(ApiResponse::<()>::Error(String::from(
-                    "cannot participate in unpublished quest",
-                ))),
-            )
-                .into_response();
-        }
-        quest_info
-    } else {
-        return (
-            StatusCode::NOT_FOUND,
-            Json(ApiResponse::<()>::Error(String::from(
-                "there are no such quest",
-            ))),
+    // subscribe before upgrade, so lock issued in between is not missed
+    let locked_quests = state.locked_quests.subscribe();
+    Ok(ws.on_upgrade(move |socket| {
+        handle_socket(
+            socket,
+            addr,
+            state,
+            locked_quests,
+            user_uuid.0,
+            quest_info,
+            quest_deadline,
         )
-            .into_response();
-    };
+    }))
+}
 
+/// Moment the page has to be submitted by, if it has a time limit and was already served
+fn page_deadline(
+    parsed_quest: &ParsedQuest,
+    page: u32,
+    served_at: Option<Timestamp>,
+) -> Option<Timestamp> {
+    let time_limit = parsed_quest.page(page)?.time_limit?;
+    Some(served_at? + TimeDelta::from_std(time_limit).ok()?)
+}
+
+/// Marks page completed, finishing quest after the last one
+async fn complete_page(
+    state: &AppState,
+    user_id: Uuid,
+    quest_info: &QuestInfo,
+    page: u32,
+) -> Option<()> {
+    state
+        .database
+        .update_user_last_completed_page(&user_id, &quest_info.id.0, page + 1)
+        .await?;
+    if page + 1 == quest_info.pages {
+        state
+            .database
+            .set_user_finished_quest(&user_id, &quest_info.id.0)
+            .await?;
+    }
+    Some(())
+}
+
+/// Finishes quest, which time is up, with pages submitted so far
+async fn finish_timed_out(
+    state: &AppState,
+    user_id: Uuid,
+    quest_info: &QuestInfo,
+) -> WsServerMessage {
     match state
         .database
-        .is_user_finished_quest(user_uuid.0, quest_uuid)
+        .set_user_finished_quest(&user_id, &quest_info.id.0)
         .await
     {
-        Some(is_finished) => {
-            if is_finished {
-                return (
-                    StatusCode::FORBIDDEN,
-                    Json(ApiResponse::<()>::Error(String::from("you finished quest"))),
-                )
-                    .into_response();
-            }
-        }
-        None => {
-            return (
-                StatusCode::FORBIDDEN,
-                Json(ApiResponse::<()>::Error(String::from(
-                    "not joined to quest",
-                ))),
-            )
-                .into_response();
-        }
+        Some(()) => WsServerMessage::QuestTimeout,
+        None => WsServerMessage::RequestBail,
+    }
+}
+
+async fn submit_page(
+    state: &AppState,
+    user_id: Uuid,
+    seed: ShuffleSeed,
+    quest_info: &QuestInfo,
+    parsed_quest: &ParsedQuest,
+    page: u32,
+    answers: &[Answer],
+) -> Option<Result<Box<[bool]>, String>> {
+    let parsed_page = parsed_quest.page(page)?;
+
+    let questions = page_questions(&parsed_page.page).collect::<Vec<_>>();
+    if questions.len() != answers.len() {
+        return Some(Err(format!(
+            "expected {} answers, got {}",
+            questions.len(),
+            answers.len()
+        )));
+    }
+
+    let graded_answers = match questions
+        .into_iter()
+        .zip(answers)
+        .map(|((question, scoring), answer)| {
+            grade_answer(question, seed, scoring, answer).map(|grade| (answer, grade))
+        })
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(graded_answers) => graded_answers,
+        Err(err) => return Some(Err(err.to_string())),
+    };
+
+    state
+        .database
+        .save_page_answers(&user_id, &quest_info.id.0, page, &graded_answers)
+        .await?;
+    if page + 1 == quest_info.pages {
+        state
+            .database
+            .set_user_finished_quest(&user_id, &quest_info.id.0)
+            .await?;
     }
 
-    ws.on_upgrade(move |socket| handle_socket(socket, addr, state, user_uuid.0, quest_info))
+    let correctness = graded_answers
+        .iter()
+        .map(|(_, grade)| grade.correct)
+        .collect();
+    Some(Ok(correctness))
 }
 
 async fn handle_socket(
     mut socket: WebSocket,
     _who: SocketAddr,
     state: State<Arc<AppState>>,
+    mut locked_quests: broadcast::Receiver<Uuid>,
     user_id: Uuid,
     quest_info: QuestInfo,
+    quest_deadline: Option<Timestamp>,
 ) {
     //forbid user multiple ws conn
 
+    let Some(seed) = state
+        .database
+        .get_shuffle_seed(user_id, quest_info.id.0)
+        .await
+    else {
+        let _ = send_message(&mut socket, &WsServerMessage::RequestBail).await;
+        return;
+    };
+
+    // page that is answered now, if it has time limit
+    let mut deadline = None::<(u32, Timestamp)>;
+    let mut countdown = interval(COUNTDOWN_PERIOD);
+    countdown.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
     loop {
         // receive, react
-        let client_msg = if let Some(msg) = socket.recv().await {
+        let msg = tokio::select! {
+            msg = socket.recv() => msg,
+            locked = locked_quests.recv() => {
+                let bail = match locked {
+                    Ok(locked_id) => locked_id == quest_info.id.0,
+                    // missed some locks, have to check by hand
+                    Err(broadcast::error::RecvError::Lagged(_)) => state
+                        .database
+                        .get_quest(quest_info.id.0)
+                        .await
+                        .is_none_or(|info| !info.state.is_published()),
+                    Err(broadcast::error::RecvError::Closed) => true,
+                };
+                if bail {
+                    let _ = send_message(&mut socket, &WsServerMessage::RequestBail).await;
+                    break;
+                }
+                continue;
+            }
+            _ = countdown.tick(), if deadline.is_some() || quest_deadline.is_some() => {
+                let now = Utc::now().naive_utc();
+                if let Some(quest_deadline) = quest_deadline {
+                    let to_send = if now < quest_deadline + TIME_LIMIT_GRACE {
+                        let time_left = (quest_deadline - now).to_std().unwrap_or_default();
+                        WsServerMessage::QuestTimeLeft(time_left)
+                    } else {
+                        finish_timed_out(&state, user_id, &quest_info).await
+                    };
+                    let last = !matches!(to_send, WsServerMessage::QuestTimeLeft(_));
+                    if send_message(&mut socket, &to_send).await.is_none() || last {
+                        break;
+                    }
+                }
+                let Some((page, page_deadline)) = deadline else {
+                    continue;
+                };
+                let to_send = if now < page_deadline + TIME_LIMIT_GRACE {
+                    let time_left = (page_deadline - now).to_std().unwrap_or_default();
+                    WsServerMessage::TimeLeft(page, time_left)
+                } else if complete_page(&state, user_id, &quest_info, page).await.is_some() {
+                    deadline = None;
+                    WsServerMessage::PageTimeout(page)
+                } else {
+                    WsServerMessage::RequestBail
+                };
+                let bail = to_send == WsServerMessage::RequestBail;
+                if send_message(&mut socket, &to_send).await.is_none() || bail {
+                    break;
+                }
+                continue;
+            }
+        };
+        let client_msg = if let Some(msg) = msg {
             match msg {
                 Ok(Message::Text(data)) => {
                     match serde_json::from_str::<WsClientMessage>(data.as_str()) {
@@ -144,39 +277,107 @@ async fn handle_socket(
             break; // stream closed
         };
 
+        // only page right after the completed ones can be requested or submitted
+        let (Some((mut completed_pages, served_at)), Some(parsed_quest)) = (
+            state
+                .database
+                .get_user_quest_progress(user_id, quest_info.id.0)
+                .await,
+            state
+                .quests_cache
+                .get_or_load(&state.database, quest_info.id.0)
+                .await,
+        ) else {
+            let _ = send_message(&mut socket, &WsServerMessage::RequestBail).await;
+            break;
+        };
+
+        let now = Utc::now().naive_utc();
+        if quest_deadline.is_some_and(|quest_deadline| now >= quest_deadline + TIME_LIMIT_GRACE) {
+            let to_send = finish_timed_out(&state, user_id, &quest_info).await;
+            let _ = send_message(&mut socket, &to_send).await;
+            break;
+        }
+
+        // time could run out while player was disconnected
+        if page_deadline(&parsed_quest, completed_pages, served_at)
+            .is_some_and(|page_deadline| now >= page_deadline + TIME_LIMIT_GRACE)
+        {
+            let to_send = if complete_page(&state, user_id, &quest_info, completed_pages)
+                .await
+                .is_some()
+            {
+                WsServerMessage::PageTimeout(completed_pages)
+            } else {
+                WsServerMessage::RequestBail
+            };
+            let bail = to_send == WsServerMessage::RequestBail;
+            if send_message(&mut socket, &to_send).await.is_none() || bail {
+                break;
+            }
+            completed_pages += 1;
+            deadline = None;
+        }
+
         let to_send = match client_msg {
             WsClientMessage::RequestPage(page) => {
-                if page >= quest_info.pages {
-                    break;
-                } // there are no such page at all
-
-                // check if page == completed_pages
-                let last_not_completed_page = state
-                    .database
-                    .get_user_quest_last_completed_page(user_id, quest_info.id.0)
-                    .await
-                    .unwrap();
-                if last_not_completed_page != page {
-                    WsServerMessage::ResponsePage(Err(last_not_completed_page))
-                } else {
-                    // потрібна сторінка, взяти її з кешу
-
-                    {
-                        let f = state.quests_cache.lock().await.entry(quest_info.id.0);
+                if page != completed_pages || page >= quest_info.pages {
+                    WsServerMessage::ResponsePage(Err(completed_pages))
+                } else if let Some(parsed_page) = parsed_quest.page(page) {
+                    if parsed_page.time_limit.is_some() {
+                        let served_at = state
+                            .database
+                            .set_user_page_served(&user_id, &quest_info.id.0, page)
+                            .await;
+                        deadline = page_deadline(&parsed_quest, page, served_at)
+                            .map(|page_deadline| (page, page_deadline));
                     }
-
-                    //todo
+                    WsServerMessage::ResponsePage(Ok(ask_quest_page(&parsed_page.page, seed)))
+                } else {
                     WsServerMessage::RequestBail
                 }
             }
-            WsClientMessage::RequestSubmit(page, answer) => WsServerMessage::RequestBail,
-        };
-        let to_send = match serde_json::to_string(&to_send) {
-            Ok(res) => res,
-            Err(_) => {
-                break;
+            WsClientMessage::RequestSubmit(page, answers) => {
+                let limited = parsed_quest
+                    .page(page)
+                    .is_some_and(|parsed_page| parsed_page.time_limit.is_some());
+                if page != completed_pages || page >= quest_info.pages {
+                    WsServerMessage::ResponseSubmit(Err(format!(
+                        "only page {completed_pages} can be submitted"
+                    )))
+                } else if limited && served_at.is_none() {
+                    WsServerMessage::ResponseSubmit(Err(String::from(
+                        "page with time limit has to be requested first",
+                    )))
+                } else if let Some(result) = submit_page(
+                    &state,
+                    user_id,
+                    seed,
+                    &quest_info,
+                    &parsed_quest,
+                    page,
+                    &answers,
+                )
+                .await
+                {
+                    if result.is_ok() {
+                        deadline = None;
+                    }
+                    WsServerMessage::ResponseSubmit(result)
+                } else {
+                    WsServerMessage::RequestBail
+                }
             }
         };
-        let _ = socket.send(Message::Text(to_send.into())).await;
+
+        let bail = to_send == WsServerMessage::RequestBail;
+        if send_message(&mut socket, &to_send).await.is_none() || bail {
+            break;
+        }
     }
 }
+
+async fn send_message(socket: &mut WebSocket, message: &WsServerMessage) -> Option<()> {
+    let message = serde_json::to_string(message).ok()?;
+    socket.send(Message::Text(message.into())).await.ok()
+}
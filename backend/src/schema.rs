@@ -16,7 +16,10 @@ diesel::table! {
         title -> Nullable<Text>,
         description -> Nullable<Text>,
         pages -> Int4,
-        published -> Bool,
+        state -> Int2,
+        time_limit_seconds -> Nullable<Int4>,
+        opens_at -> Nullable<Timestamp>,
+        closes_at -> Nullable<Timestamp>,
     }
 }
 
@@ -29,6 +32,21 @@ diesel::table! {
         completed_pages -> Int4,
         rate -> Nullable<Int4>,
         comment -> Nullable<Text>,
+        page_served_at -> Nullable<Timestamp>,
+        score -> Int4,
+        max_score -> Int4,
+        shuffle_seed -> Int8,
+    }
+}
+
+diesel::table! {
+    quests_answers (user_id, quest_id, page, question) {
+        user_id -> Uuid,
+        quest_id -> Uuid,
+        page -> Int4,
+        question -> Int4,
+        answer -> Jsonb,
+        score -> Int4,
     }
 }
 
@@ -38,6 +56,30 @@ diesel::table! {
         page -> Int4,
         source -> Text,
         time_limit_seconds -> Nullable<Int4>,
+        parsed -> Nullable<Jsonb>,
+        revision -> Int4,
+        page_id -> Uuid,
+        parsed_format -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    quests_revisions (id) {
+        id -> Int4,
+        quest_id -> Uuid,
+        author -> Uuid,
+        created_at -> Timestamp,
+        page -> Nullable<Int4>,
+        page_id -> Nullable<Uuid>,
+        content -> Jsonb,
+    }
+}
+
+diesel::table! {
+    sessions (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        expires_at -> Timestamp,
     }
 }
 
@@ -50,13 +92,20 @@ diesel::table! {
         email -> Varchar,
         #[max_length = 255]
         password_hash -> Varchar,
+        role -> Int2,
+        banned -> Bool,
     }
 }
 
+diesel::joinable!(sessions -> users (user_id));
+
 diesel::allow_tables_to_appear_in_same_query!(
     avatars,
     quests,
+    quests_answers,
     quests_applied,
     quests_pages,
+    quests_revisions,
+    sessions,
     users,
 );
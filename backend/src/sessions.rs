@@ -0,0 +1,148 @@
+use crate::config::ConfigSession;
+use crate::database::Database;
+use axum::http::HeaderValue;
+use common::{Timestamp, UserId};
+use diesel::internal::derives::multiconnection::chrono::{TimeDelta, Utc};
+use moka::future::Cache;
+use std::time::Duration;
+use uuid::Uuid;
+
+const MAX_CACHED_SESSIONS: u64 = 100_000;
+// only bounds memory, expiration is checked on every access
+const CACHE_TIME_TO_IDLE: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Clone, Copy)]
+pub struct Session {
+    pub user_id: UserId,
+    pub expires_at: Timestamp,
+}
+
+/// Sessions of logged in users.
+///
+/// Stored in the database so they survive restarts, the cache only saves lookups of active ones.
+pub struct Sessions {
+    cache: Cache<Uuid, Session>,
+    lifetime: TimeDelta,
+    refresh_after: TimeDelta,
+    /// cookie attributes, except for Max-Age
+    cookie_attributes: String,
+}
+
+impl Sessions {
+    pub fn new(config: &ConfigSession) -> Self {
+        let cache = Cache::builder()
+            .max_capacity(MAX_CACHED_SESSIONS)
+            .time_to_idle(CACHE_TIME_TO_IDLE)
+            // to log out all devices of a user
+            .support_invalidation_closures()
+            .build();
+
+        let mut cookie_attributes = format!("Path=/; HttpOnly; SameSite={:?}", config.same_site);
+        if config.secure {
+            cookie_attributes.push_str("; Secure");
+        }
+
+        Self {
+            cache,
+            lifetime: TimeDelta::seconds(config.lifetime_secs as i64),
+            refresh_after: TimeDelta::seconds(config.refresh_after_secs as i64),
+            cookie_attributes,
+        }
+    }
+
+    /// Starts new session, returning its id
+    pub async fn create(&self, database: &Database, user_id: UserId) -> Option<Uuid> {
+        let session_id = Uuid::new_v4();
+        let session = Session {
+            user_id,
+            expires_at: Utc::now().naive_utc() + self.lifetime,
+        };
+        database
+            .insert_session(session_id, user_id.0, session.expires_at)
+            .await?;
+        self.cache.insert(session_id, session).await;
+        Some(session_id)
+    }
+
+    /// Not expired session, loaded from the database on a cache miss
+    pub async fn get(&self, database: &Database, session_id: Uuid) -> Option<Session> {
+        let session = self
+            .cache
+            .optionally_get_with(session_id, async {
+                let (user_id, expires_at) = database.get_session(session_id).await?;
+                Some(Session {
+                    user_id: UserId(user_id),
+                    expires_at,
+                })
+            })
+            .await?;
+        if session.expires_at <= Utc::now().naive_utc() {
+            self.cache.invalidate(&session_id).await;
+            return None;
+        }
+        Some(session)
+    }
+
+    /// Prolongs session, if it was not refreshed for a while.
+    ///
+    /// True if session was prolonged, so its cookie has to be re-issued.
+    pub async fn refresh(&self, database: &Database, session_id: Uuid) -> bool {
+        let Some(session) = self.get(database, session_id).await else {
+            return false;
+        };
+        let now = Utc::now().naive_utc();
+        if session.expires_at - now > self.lifetime - self.refresh_after {
+            return false;
+        }
+
+        let expires_at = now + self.lifetime;
+        if database
+            .prolong_session(session_id, expires_at)
+            .await
+            .is_none()
+        {
+            return false;
+        }
+        self.cache
+            .insert(
+                session_id,
+                Session {
+                    expires_at,
+                    ..session
+                },
+            )
+            .await;
+        true
+    }
+
+    pub async fn revoke(&self, database: &Database, session_id: Uuid) -> Option<()> {
+        database.delete_session(session_id).await?;
+        self.cache.invalidate(&session_id).await;
+        Some(())
+    }
+
+    /// Revokes every session of the user
+    pub async fn revoke_user(&self, database: &Database, user_id: UserId) -> Option<()> {
+        database.delete_user_sessions(user_id.0).await?;
+        self.cache
+            .invalidate_entries_if(move |_, session| session.user_id == user_id)
+            .ok()?;
+        Some(())
+    }
+
+    /// Set-Cookie value for the session
+    pub fn cookie(&self, session_id: Uuid) -> HeaderValue {
+        let cookie = format!(
+            "session={session_id}; Max-Age={}; {}",
+            self.lifetime.num_seconds(),
+            self.cookie_attributes
+        );
+        HeaderValue::from_str(&cookie).expect("cookie is always visible ascii")
+    }
+
+    /// Set-Cookie value, that removes session cookie
+    pub fn removal_cookie(&self) -> HeaderValue {
+        let cookie = format!("session=; Max-Age=0; {}", self.cookie_attributes);
+        HeaderValue::from_str(&cookie).expect("cookie is always visible ascii")
+    }
+}
@@ -0,0 +1,225 @@
+//! Contract between backend and its clients: response envelope, request bodies and routes
+
+use crate::{
+    AdminUsersPage, DiffLine, LoginRequest, LoginResponse, PublishError, QuestHistoryPage, QuestId,
+    QuestInfo, QuestRevisionsPage, QuestState, RegisterRequest, RegisterResponse, UserId, UserInfo,
+    UserOwnedQuestsPage, UserRole, WsClientMessage, WsServerMessage,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Body of every json response
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiResponse<T, E = String> {
+    #[serde(rename = "error")]
+    Error(E),
+    #[serde(untagged)]
+    Response(T),
+}
+
+/// Page as it is edited by the author, GET and POST /api/quests/:id/page/:page
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QuestPageSource {
+    pub source: String,
+    /// time to answer the page, counted from when it is sent to the player
+    pub time_limit: Option<Duration>,
+    /// revision the source is based on, every save makes a new one
+    #[serde(default)]
+    pub revision: u32,
+}
+
+/// POST /api/quests/:id/update_rate_comment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateCommentRequest {
+    pub rate: u32,
+    pub comment: String,
+}
+
+/// Average rate of all quests of the owner
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnerRateRecord {
+    pub owner: UserId,
+    pub rate: f64,
+}
+
+/// Error of quest state change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum QuestStateError {
+    /// publishing quest, that can not be played
+    Unplayable(PublishError),
+    Other(String),
+}
+
+/// Body is a multipart form with a single file, not json
+#[derive(Debug, Clone, Copy)]
+pub struct MultipartBody;
+
+/// Response is a file with its own content type, not [`ApiResponse`]
+#[derive(Debug, Clone, Copy)]
+pub struct FileBody;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+/// Route of the api
+pub trait Endpoint {
+    const METHOD: Method;
+    /// `{name}` segments are parameters
+    const PATH: &'static str;
+    /// `()` when there is no body
+    type Request;
+    /// sent as [`ApiResponse::Response`]
+    type Response;
+    /// sent as [`ApiResponse::Error`]
+    type Error;
+
+    /// Path with parameters filled in order
+    fn path(params: &[&str]) -> String {
+        let mut params = params.iter();
+        Self::PATH
+            .split('/')
+            .map(|segment| {
+                if segment.starts_with('{') && segment.ends_with('}') {
+                    *params.next().expect("not enough path parameters")
+                } else {
+                    segment
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+macro_rules! endpoints {
+    ($(
+        $(#[$meta:meta])*
+        $name:ident: $method:ident $path:literal, $request:ty => $response:ty $(, $error:ty)?;
+    )*) => {
+        $(
+            $(#[$meta])*
+            pub struct $name;
+
+            impl Endpoint for $name {
+                const METHOD: Method = Method::$method;
+                const PATH: &'static str = $path;
+                type Request = $request;
+                type Response = $response;
+                type Error = endpoints!(@error $($error)?);
+            }
+        )*
+    };
+    (@error) => { String };
+    (@error $error:ty) => { $error };
+}
+
+/// Every route of the api
+pub mod endpoints {
+    use super::*;
+
+    endpoints! {
+        Login: Post "/api/login", LoginRequest => LoginResponse;
+        /// Conflict, if name or email is already in use
+        Register: Post "/api/register", RegisterRequest => RegisterResponse;
+        /// Logs out current device
+        Logout: Post "/api/logout", () => ();
+        /// Logs out every device of the user
+        LogoutAll: Post "/api/logout_all", () => ();
+        /// User is looked up by name, email or id
+        GetUserInfo: Get "/api/get_user/{username_or_email}", () => UserInfo;
+        UpdateAvatar: Post "/api/update_avatar", MultipartBody => ();
+        GetAvatar: Get "/api/get_avatar/{user_id}", () => FileBody;
+
+        /// Creates quest with empty title, description and no pages
+        CreateQuest: Post "/api/quests/create", () => QuestId;
+        GetQuestInfo: Get "/api/quests/{id}/info", () => QuestInfo;
+        UpdateQuestInfo: Post "/api/quests/{id}/info", QuestInfo => ();
+        GetQuestPage: Get "/api/quests/{id}/page/{page}", () => QuestPageSource;
+        /// Updates page, or appends it when `page` is equal to current page count.
+        /// Conflict, if `revision` is not the current one; new revision is returned
+        UpdateQuestPage: Post "/api/quests/{id}/page/{page}", QuestPageSource => u32;
+        /// Inserts page before `page`, or appends it when `page` is equal to current page count
+        InsertQuestPage: Post "/api/quests/{id}/page/{page}/insert", QuestPageSource => ();
+        /// Removes page, the following pages move one page closer
+        DeleteQuestPage: Post "/api/quests/{id}/page/{page}/delete", () => ();
+        /// Body lists every page number once, in the new order
+        ReorderQuestPages: Post "/api/quests/{id}/pages/order", Vec<u32> => ();
+        GetOwnedQuests: Get "/api/owned_quests/page/{page}", () => UserOwnedQuestsPage;
+        /// Every save of quest info and pages is kept; for its author and moderators
+        GetQuestRevisions: Get "/api/quests/{id}/revisions/page/{page}", () => QuestRevisionsPage;
+        /// Lines of `revision` are removed, lines of `other` are added
+        DiffQuestRevisions: Get "/api/quests/{id}/revisions/{revision}/diff/{other}", () => Vec<DiffLine>;
+        /// Saves content of the revision again, page is appended if there are fewer pages now
+        RestoreQuestRevision: Post "/api/quests/{id}/revisions/{revision}/restore", () => ();
+
+        PublishQuest: Post "/api/quests/{id}/publish", () => QuestState, QuestStateError;
+        SubmitQuest: Post "/api/quests/{id}/submit", () => QuestState, QuestStateError;
+        ReturnQuest: Post "/api/quests/{id}/return", () => QuestState, QuestStateError;
+        ApproveQuest: Post "/api/quests/{id}/approve", () => QuestState, QuestStateError;
+        LockQuest: Post "/api/quests/{id}/lock", () => QuestState, QuestStateError;
+
+        JoinQuest: Post "/api/quests/join/{id}", () => ();
+        GetAppliedQuests: Get "/api/applied_quests/{page}", () => QuestHistoryPage;
+        /// Scores of everyone who joined the quest, best first; for its author and moderators
+        GetQuestResults: Get "/api/quests/{id}/results/{page}", () => QuestHistoryPage;
+        UpdateRateComment: Post "/api/quests/{id}/update_rate_comment", RateCommentRequest => ();
+        GetOwnersRate: Get "/api/quests/owner_rate", () => Vec<OwnerRateRecord>;
+        /// Upgraded to websocket, messages are sent instead of request and response bodies
+        QuestParticipation: Get "/api/ws/quest/{id}", WsClientMessage => WsServerMessage;
+
+        GetUsers: Get "/api/admin/users/{page}", () => AdminUsersPage;
+        SetUserRole: Post "/api/admin/users/{id}/role", UserRole => ();
+        BanUser: Post "/api/admin/users/{id}/ban", () => ();
+        UnbanUser: Post "/api/admin/users/{id}/unban", () => ();
+    }
+}
+
+#[cfg(test)]
+mod api_tests {
+    use super::endpoints::*;
+    use super::*;
+
+    #[test]
+    fn endpoint_path() {
+        assert_eq!(Login::path(&[]), "/api/login");
+        assert_eq!(
+            UpdateQuestPage::path(&["qid", "3"]),
+            "/api/quests/qid/page/3"
+        );
+        assert_eq!(GetOwnedQuests::path(&["0"]), "/api/owned_quests/page/0");
+        assert_eq!(
+            DeleteQuestPage::path(&["qid", "3"]),
+            "/api/quests/qid/page/3/delete"
+        );
+        assert_eq!(
+            DiffQuestRevisions::path(&["qid", "4", "7"]),
+            "/api/quests/qid/revisions/4/diff/7"
+        );
+    }
+
+    #[test]
+    fn api_response_format() {
+        let response = ApiResponse::<u32>::Response(5);
+        assert_eq!(serde_json::to_string(&response).unwrap(), "5");
+        let error = ApiResponse::<u32>::Error(String::from("oops"));
+        let json = serde_json::to_string(&error).unwrap();
+        assert_eq!(json, r#"{"error":"oops"}"#);
+        assert_eq!(
+            serde_json::from_str::<ApiResponse<u32>>(&json).unwrap(),
+            error
+        );
+
+        let unplayable = ApiResponse::<QuestState, QuestStateError>::Error(
+            QuestStateError::Unplayable(PublishError::NoPages),
+        );
+        let json = serde_json::to_string(&unplayable).unwrap();
+        assert!(matches!(
+            serde_json::from_str::<ApiResponse<QuestState, QuestStateError>>(&json).unwrap(),
+            ApiResponse::Error(QuestStateError::Unplayable(PublishError::NoPages))
+        ));
+    }
+}
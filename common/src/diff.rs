@@ -0,0 +1,102 @@
+//! Line diff of page sources, shown when saving conflicts and when comparing revisions.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DiffLine {
+    /// present in both sources
+    Same(String),
+    /// present only in the old source
+    Removed(String),
+    /// present only in the new source
+    Added(String),
+}
+
+/// Lines of both sources, in order of their longest common subsequence.
+/// Removed lines go before the added ones, where a block of lines was changed
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+    // common[i][j] is the length of common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            diff.push(DiffLine::Same(old[i].to_owned()));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            diff.push(DiffLine::Removed(old[i].to_owned()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j].to_owned()));
+            j += 1;
+        }
+    }
+    diff.extend(
+        old[i..]
+            .iter()
+            .map(|&line| DiffLine::Removed(line.to_owned())),
+    );
+    diff.extend(
+        new[j..]
+            .iter()
+            .map(|&line| DiffLine::Added(line.to_owned())),
+    );
+    diff
+}
+
+#[cfg(test)]
+mod diff_tests {
+    use super::{diff_lines, DiffLine};
+    use proptest::prelude::*;
+
+    #[test]
+    fn changed_line() {
+        use DiffLine::*;
+
+        assert_eq!(
+            diff_lines("a\nb\nc", "a\nB\nc\nd"),
+            [
+                Same("a".into()),
+                Removed("b".into()),
+                Added("B".into()),
+                Same("c".into()),
+                Added("d".into()),
+            ]
+        );
+        assert_eq!(diff_lines("", "a"), [Added("a".into())]);
+        assert_eq!(diff_lines("a", ""), [Removed("a".into())]);
+    }
+
+    proptest! {
+        #[test]
+        fn both_sources_restored(
+            old in prop::collection::vec("[abc]", 0..12),
+            new in prop::collection::vec("[abc]", 0..12),
+        ) {
+            let diff = diff_lines(&old.join("\n"), &new.join("\n"));
+            let restored_old = diff.iter().filter_map(|line| match line {
+                DiffLine::Same(line) | DiffLine::Removed(line) => Some(line.clone()),
+                DiffLine::Added(_) => None,
+            });
+            let restored_new = diff.iter().filter_map(|line| match line {
+                DiffLine::Same(line) | DiffLine::Added(line) => Some(line.clone()),
+                DiffLine::Removed(_) => None,
+            });
+            prop_assert_eq!(restored_old.collect::<Vec<_>>(), old);
+            prop_assert_eq!(restored_new.collect::<Vec<_>>(), new);
+        }
+    }
+}
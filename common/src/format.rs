@@ -0,0 +1,1532 @@
+//! Quest page source format, parsed by [`check_quest_page`] and written by
+//! [`serialize_quest_page`].
+//!
+//! Parser descends the grammar below, rules are parsed by `parse_` functions named after them.
+//!
+//! Source is split into lines, everything inside a line is matched after trimming surrounding
+//! whitespace, unless noted otherwise. Empty lines inside questions are skipped, lines with only
+//! whitespace are empty too.
+//!
+//! ```text
+//! page            = { text | question } ;
+//! text            = text-line, { text-line } ;
+//! text-line       = ? any line, that is not a question-open ? ;
+//! (* text with blanks outside of inline code and fenced code blocks is a cloze question *)
+//! blank           = "{{", blank-answer, { "|", blank-answer }, "}}" ;
+//! (* without "`", does not end with "}" *)
+//! blank-answer    = item - ( "|" | "}}" ) ;
+//! question        = question-open, question-body, question-close ;
+//! question-open   = "<question", { whitespace, attribute }, ">" ;
+//! attribute       = "points=", points | "penalty=", points | "partial" ;
+//! points          = uint ; (* up to 1000 *)
+//! question-close  = "</question>" ;
+//! question-body   = choice | multiple-choice | ordering | matching | opened | image ;
+//!
+//! (* lines of choices are not trimmed, exactly one of them is "+" *)
+//! choice          = choice-line, { choice-line } ;
+//! choice-line     = ( "+" | "-" ), item ;
+//! (* at least one of them is "[x]" *)
+//! multiple-choice = multiple-line, { multiple-line } ;
+//! multiple-line   = ( "[x]" | "[X]" | "[ ]" ), item ;
+//!
+//! ordering        = "<order>", item, item, { item }, "</order>" ;
+//! matching        = "<match>", pair, pair, { pair }, "</match>" ;
+//! pair            = item - "=", "=", item ;
+//!
+//! opened          = "<opened>", answer, { answer }, "</opened>" ;
+//! answer          = "=", number, [ ( "+-" | "±" ), number ]
+//!                 | "/", regex, "/"
+//!                 | "~", uint, whitespace, text-answer
+//!                 | text-answer ;
+//! text-answer     = [ "\" ], item ;
+//!
+//! image           = "<img", whitespace, 'src="', url, '"', [ whitespace ], "/>", region, { region } ;
+//! region          = ( "rect", 4 * coordinate
+//!                   | "rect", 4 * pixels
+//!                   | "circle", 3 * coordinate
+//!                   | "polygon", 3 * ( coordinate, coordinate ), { coordinate, coordinate } ),
+//!                   [ "--", comment ] ;
+//! (* percents of image size, separated by whitespace or commas *)
+//! coordinate      = uint, [ ".", digit, [ digit ] ], [ "%" ] ;
+//! (* pixels of the image at its natural size *)
+//! pixels          = uint, "px" ;
+//!
+//! (* non-empty, without surrounding whitespace *)
+//! item            = ? rest of the line ? ;
+//! ```
+//!
+//! Cloze question is worth a point for every blank, it can have up to 1000 of them.
+//! Images also accept the older form of four lines with a number each: left, top, width and height
+//! in pixels, same as `rect LEFTpx TOPpx WIDTHpx HEIGHTpx`.
+
+use crate::{
+    Diagnostic, ImageRegion, Number, OpenedAnswer, PageCheck, PageParseError, PageWarning,
+    QuestPageElement, Question, Scoring, SourceError, SourceSpan, SourceWarning, IMAGE_SCALE,
+    MAX_POINTS,
+};
+use std::borrow::Borrow;
+use std::collections::BTreeSet;
+
+/// Parses page source, collecting all errors and warnings, for the editor to point at
+pub fn check_quest_page(source: impl Borrow<str>) -> PageCheck {
+    let mut page = PageChecker::default();
+
+    // unlike `str::lines`, keeps the last line, when it is empty, so text ending with one reads back
+    let mut lines = source
+        .borrow()
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .enumerate();
+    while let Some((no, line)) = lines.next() {
+        let Some(scoring) = parse_question_open(line) else {
+            // leading empty lines of a text are skipped
+            if !page.text.is_empty() || !line.is_empty() {
+                page.text.push((no, line));
+            }
+            continue;
+        };
+        page.flush_text();
+
+        let tag = SourceSpan::line(no, line);
+        let mut block = Vec::new();
+        let mut closing = None;
+        for (no, line) in lines.by_ref() {
+            if line.trim() == "</question>" {
+                closing = Some(SourceSpan::line(no, line));
+                break;
+            }
+            block.push((no, line));
+        }
+        let Some(closing) = closing else {
+            // unclosed question takes the rest of the source
+            let end = block
+                .last()
+                .map_or(tag, |&(no, line)| SourceSpan::line(no, line));
+            let question = page.next_question(tag.to(end));
+            page.error(PageParseError::UnclosedQuestionTag, tag, Some(question));
+            break;
+        };
+        let question = page.next_question(tag.to(closing));
+
+        let scoring = scoring.unwrap_or_else(|kind| {
+            page.error(kind, tag, Some(question));
+            Scoring::default()
+        });
+        let (mut read, mut exhausted) = (0, false);
+        let parsed = parse_question(std::iter::from_fn(|| {
+            let line = block.get(read).map(|&(_, line)| line);
+            if line.is_some() {
+                read += 1;
+            } else {
+                exhausted = true;
+            }
+            line
+        }));
+        match parsed {
+            Ok(question) => page
+                .elements
+                .push(QuestPageElement::Question(question, scoring)),
+            Err(kind) => {
+                // point at the line, that broke the question, or at the whole question,
+                // if it was found broken only after reading it all
+                let span = match block.get(read.max(1) - 1) {
+                    Some(&(no, line)) if !exhausted => SourceSpan::line(no, line),
+                    _ => tag.to(closing),
+                };
+                page.error(kind, span, Some(question));
+            }
+        }
+    }
+    page.flush_text();
+
+    let page_result = if page.errors.is_empty() {
+        Ok(page.elements.into_boxed_slice())
+    } else {
+        Err(page.errors.into_boxed_slice())
+    };
+    PageCheck {
+        page: page_result,
+        warnings: page.warnings.into_boxed_slice(),
+        questions: page.questions.into_boxed_slice(),
+    }
+}
+
+#[derive(Default)]
+struct PageChecker<'s> {
+    elements: Vec<QuestPageElement>,
+    errors: Vec<SourceError>,
+    warnings: Vec<SourceWarning>,
+    /// lines of the text since the last question, with their numbers
+    text: Vec<(usize, &'s str)>,
+    /// spans of questions found so far
+    questions: Vec<SourceSpan>,
+}
+
+impl PageChecker<'_> {
+    fn next_question(&mut self, span: SourceSpan) -> u32 {
+        self.questions.push(span);
+        self.questions.len() as u32 - 1
+    }
+
+    fn error(&mut self, kind: PageParseError, span: SourceSpan, question: Option<u32>) {
+        self.errors.push(Diagnostic {
+            kind,
+            span,
+            question,
+        });
+    }
+
+    fn warning(&mut self, kind: PageWarning, span: SourceSpan) {
+        self.warnings.push(Diagnostic {
+            kind,
+            span,
+            question: None,
+        });
+    }
+
+    fn flush_text(&mut self) {
+        let lines = std::mem::take(&mut self.text);
+        let (Some(&(first_no, first)), Some(&(last_no, last))) = (lines.first(), lines.last())
+        else {
+            return;
+        };
+
+        for &(no, line) in &lines {
+            let trimmed = line.trim();
+            if trimmed.starts_with("<img") {
+                self.warning(PageWarning::UnusedImage, SourceSpan::line(no, line));
+            } else if trimmed.starts_with("</") && trimmed.ends_with('>') {
+                self.warning(PageWarning::StrayClosingTag, SourceSpan::line(no, line));
+            }
+        }
+
+        let text = lines
+            .iter()
+            .map(|&(_, line)| line)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let whole = SourceSpan::line(first_no, first).to(SourceSpan::line(last_no, last));
+        if text.trim().is_empty() {
+            self.warning(PageWarning::EmptyText, whole);
+        }
+        match parse_text(&text) {
+            Ok(element) => {
+                if let QuestPageElement::Question(..) = element {
+                    self.next_question(whole);
+                }
+                self.elements.push(element);
+            }
+            Err((kind, offset)) => {
+                let before = &text[..offset];
+                let (no, line) = lines[before.matches('\n').count()];
+                let column = before
+                    .rsplit('\n')
+                    .next()
+                    .unwrap_or_default()
+                    .chars()
+                    .count();
+                let span = SourceSpan {
+                    column: column as u32 + 1,
+                    ..SourceSpan::line(no, line)
+                };
+                let question = self.next_question(whole);
+                self.error(kind, span, Some(question));
+            }
+        }
+    }
+}
+
+/// `text`, that becomes a cloze question if it has `{{answer|another answer}}` blanks outside
+/// of code. Fails with byte offset of the broken blank
+fn parse_text(text: &str) -> Result<QuestPageElement, (PageParseError, usize)> {
+    let mut parts = Vec::new();
+    let mut blanks = Vec::new();
+    let (mut part_start, mut offset) = (0, 0);
+    while let Some(rest) = text.get(offset..).filter(|rest| !rest.is_empty()) {
+        let line_start = offset == 0 || text[..offset].ends_with('\n');
+        if let Some(code) = code_len(rest, line_start) {
+            offset += code;
+            continue;
+        }
+        let Some(blank) = rest.strip_prefix("{{") else {
+            offset += rest.chars().next().map_or(1, char::len_utf8);
+            continue;
+        };
+        let broken = |kind| (kind, offset);
+        let (blank, _) = blank
+            .split_once("}}")
+            .ok_or(broken(PageParseError::BadBlankFormat))?;
+        let accepted = parse_blank(blank).ok_or(broken(PageParseError::BadBlankFormat))?;
+        if blanks.len() == MAX_POINTS as usize {
+            return Err(broken(PageParseError::TooManyPoints));
+        }
+        parts.push(text[part_start..offset].to_owned());
+        blanks.push(accepted);
+        offset += "{{".len() + blank.len() + "}}".len();
+        part_start = offset;
+    }
+
+    if blanks.is_empty() {
+        return Ok(QuestPageElement::Text(text.into()));
+    }
+    parts.push(text[part_start..].to_owned());
+    // every blank is a point on its own
+    let scoring = Scoring {
+        points: blanks.len() as u32,
+        penalty: 0,
+        partial: true,
+    };
+    Ok(QuestPageElement::Question(
+        Question::Cloze {
+            text: parts.into_boxed_slice(),
+            blanks: blanks.into_boxed_slice(),
+        },
+        scoring,
+    ))
+}
+
+/// `blank` between `{{` and `}}`, its accepted answers
+fn parse_blank(blank: &str) -> Option<Box<[String]>> {
+    blank
+        .split('|')
+        .map(|answer| {
+            let answer = parse_item(answer)?;
+            // `}` at the end would be read back as a part of the closing `}}`,
+            // backticks could start code along with the text around the blank
+            (!answer.ends_with('}') && !answer.contains('`')).then(|| answer.to_owned())
+        })
+        .collect()
+}
+
+/// Length of the code at the start of `rest`, blanks are not looked for in it.
+///
+/// Fenced code block starts a line with three or more backticks or tildes and lasts up to the
+/// same fence, or to the end of the text. Inline code is closed by as many backticks as opened it,
+/// unclosed ones are skipped on their own.
+fn code_len(rest: &str, line_start: bool) -> Option<usize> {
+    let run = |s: &str, c: char| s.len() - s.trim_start_matches(c).len();
+
+    let indent = run(rest, ' ');
+    if line_start && indent <= 3 {
+        for fence in ['`', '~'] {
+            let width = run(&rest[indent..], fence);
+            if width < 3 {
+                continue;
+            }
+            let mut end = rest.find('\n').map_or(rest.len(), |end| end + 1);
+            while end < rest.len() {
+                let line = &rest[end..];
+                let line_len = line.find('\n').map_or(line.len(), |len| len + 1);
+                end += line_len;
+                let closing = line[..line_len].trim();
+                if closing.len() >= width && closing.chars().all(|c| c == fence) {
+                    break;
+                }
+            }
+            return Some(end);
+        }
+    }
+
+    let width = run(rest, '`');
+    if width == 0 {
+        return None;
+    }
+    let mut end = width;
+    while let Some(start) = rest[end..].find('`') {
+        let closing = run(&rest[end + start..], '`');
+        end += start + closing;
+        if closing == width {
+            return Some(end);
+        }
+    }
+    Some(width)
+}
+
+/// `question-open` line with its scoring, None if the line is not one
+fn parse_question_open(line: &str) -> Option<Result<Scoring, PageParseError>> {
+    let attributes = line.trim().strip_prefix("<question")?.strip_suffix('>')?;
+    if !attributes.is_empty() && !attributes.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let mut scoring = Scoring::default();
+    let parsed = attributes
+        .split_whitespace()
+        .try_for_each(|attribute| parse_attribute(attribute, &mut scoring));
+    Some(parsed.map(|()| scoring))
+}
+
+/// `attribute`, set in the scoring
+fn parse_attribute(attribute: &str, scoring: &mut Scoring) -> Result<(), PageParseError> {
+    match attribute.split_once('=') {
+        Some(("points", value)) => scoring.points = parse_points(value)?,
+        Some(("penalty", value)) => scoring.penalty = parse_points(value)?,
+        None if attribute == "partial" => scoring.partial = true,
+        _ => return Err(PageParseError::BadQuestionAttributes),
+    }
+    Ok(())
+}
+
+/// `points`, up to [`MAX_POINTS`]
+fn parse_points(value: &str) -> Result<u32, PageParseError> {
+    match value.parse::<u32>() {
+        Ok(points) if points <= MAX_POINTS => Ok(points),
+        // too large even for u32
+        _ if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+            Err(PageParseError::TooManyPoints)
+        }
+        _ => Err(PageParseError::BadQuestionAttributes),
+    }
+}
+
+/// `question-body`, lines between the question tags, untrimmed, empty ones are skipped
+///
+/// Kind of the question is told by its first line. Lines are read one at a time and the
+/// question is rejected at the line, that broke it, so the caller can point at it.
+pub(crate) fn parse_question<'l>(
+    lines: impl IntoIterator<Item = &'l str>,
+) -> Result<Question, PageParseError> {
+    let mut lines = lines
+        .into_iter()
+        .filter(|line| !line.trim().is_empty())
+        .peekable();
+    let Some(&first_line) = lines.peek() else {
+        return Err(PageParseError::EmptyQuestionTag);
+    };
+    let tag = first_line.trim();
+    if first_line.starts_with(['+', '-']) {
+        parse_choice(lines)
+    } else if first_line.starts_with('[') {
+        parse_multiple_choice(lines)
+    } else if tag == "<order>" {
+        parse_ordering(lines)
+    } else if tag == "<match>" {
+        parse_matching(lines)
+    } else if tag == "<opened>" {
+        parse_opened(lines)
+    } else if tag.starts_with("<img") {
+        parse_image(lines)
+    } else {
+        Err(PageParseError::UnknownQuestionType)
+    }
+}
+
+/// `item`, None if it is empty
+fn parse_item(s: &str) -> Option<&str> {
+    let item = s.trim();
+    (!item.is_empty()).then_some(item)
+}
+
+fn has_duplicates(items: &[&str]) -> bool {
+    items.iter().collect::<BTreeSet<_>>().len() != items.len()
+}
+
+/// `choice`
+fn parse_choice<'l>(lines: impl Iterator<Item = &'l str>) -> Result<Question, PageParseError> {
+    let mut variants = Vec::new();
+    let mut correct = None;
+    for (line, no) in lines.zip(0u32..) {
+        let (is_correct, variant) =
+            parse_choice_line(line).ok_or(PageParseError::BadChoiceFormat)?;
+        if is_correct {
+            if correct.is_some() {
+                return Err(PageParseError::MultipleCorrect);
+            }
+            correct = Some(no);
+        }
+        if variants.contains(&variant) {
+            return Err(PageParseError::IdenticalChoices);
+        }
+        variants.push(variant);
+    }
+
+    let correct = correct.ok_or(PageParseError::NoCorrectChoice)?;
+    Ok(Question::Choice {
+        variants: variants.into_iter().map(str::to_owned).collect(),
+        correct,
+    })
+}
+
+/// `choice-line`, whether it is the correct one and its variant
+fn parse_choice_line(line: &str) -> Option<(bool, &str)> {
+    let (is_correct, variant) = match line.strip_prefix('+') {
+        Some(variant) => (true, variant),
+        None => (false, line.strip_prefix('-')?),
+    };
+    Some((is_correct, parse_item(variant)?))
+}
+
+/// `multiple-choice`
+fn parse_multiple_choice<'l>(
+    lines: impl Iterator<Item = &'l str>,
+) -> Result<Question, PageParseError> {
+    let mut variants = Vec::new();
+    let mut correct = Vec::new();
+    for (line, no) in lines.zip(0u32..) {
+        let (checked, variant) =
+            parse_multiple_line(line).ok_or(PageParseError::BadMultipleChoiceFormat)?;
+        if variants.contains(&variant) {
+            return Err(PageParseError::IdenticalChoices);
+        }
+        if checked {
+            correct.push(no);
+        }
+        variants.push(variant);
+    }
+
+    if correct.is_empty() {
+        return Err(PageParseError::NoCorrectChoice);
+    }
+    Ok(Question::MultipleChoice {
+        variants: variants.into_iter().map(str::to_owned).collect(),
+        correct: correct.into_boxed_slice(),
+    })
+}
+
+/// `multiple-line`, whether it is checked and its variant
+fn parse_multiple_line(line: &str) -> Option<(bool, &str)> {
+    let (checked, variant) = match line.strip_prefix("[ ]") {
+        Some(variant) => (false, variant),
+        None => (
+            true,
+            line.strip_prefix("[x]")
+                .or_else(|| line.strip_prefix("[X]"))?,
+        ),
+    };
+    Some((checked, parse_item(variant)?))
+}
+
+/// Items between the opening tag and `closing_tag`, that must end the question
+fn parse_block_items<'l>(
+    mut lines: impl Iterator<Item = &'l str>,
+    closing_tag: &str,
+) -> Option<Vec<&'l str>> {
+    lines.next()?;
+    let mut items = Vec::new();
+    loop {
+        let line = lines.next()?.trim();
+        if line == closing_tag {
+            break;
+        }
+        items.push(line);
+    }
+    lines.next().is_none().then_some(items)
+}
+
+/// `ordering`
+fn parse_ordering<'l>(lines: impl Iterator<Item = &'l str>) -> Result<Question, PageParseError> {
+    let items = parse_block_items(lines, "</order>").ok_or(PageParseError::BadOrderingFormat)?;
+    if items.len() < 2 {
+        return Err(PageParseError::BadOrderingFormat);
+    }
+    if has_duplicates(&items) {
+        return Err(PageParseError::IdenticalChoices);
+    }
+    Ok(Question::Ordering {
+        items: items.into_iter().map(str::to_owned).collect(),
+    })
+}
+
+/// `matching`
+fn parse_matching<'l>(lines: impl Iterator<Item = &'l str>) -> Result<Question, PageParseError> {
+    let pairs = parse_block_items(lines, "</match>")
+        .ok_or(PageParseError::BadMatchingFormat)?
+        .into_iter()
+        .map(parse_pair)
+        .collect::<Option<Vec<_>>>()
+        .ok_or(PageParseError::BadMatchingFormat)?;
+    if pairs.len() < 2 {
+        return Err(PageParseError::BadMatchingFormat);
+    }
+    let (left, right): (Vec<_>, Vec<_>) = pairs.iter().copied().unzip();
+    if has_duplicates(&left) || has_duplicates(&right) {
+        return Err(PageParseError::IdenticalChoices);
+    }
+    Ok(Question::Matching {
+        pairs: pairs
+            .into_iter()
+            .map(|(left, right)| (left.to_owned(), right.to_owned()))
+            .collect(),
+    })
+}
+
+/// `pair`
+fn parse_pair(line: &str) -> Option<(&str, &str)> {
+    let (left, right) = line.split_once('=')?;
+    Some((parse_item(left)?, parse_item(right)?))
+}
+
+/// `opened`
+fn parse_opened<'l>(mut lines: impl Iterator<Item = &'l str>) -> Result<Question, PageParseError> {
+    lines.next();
+    let mut answers = Vec::new();
+    let mut closed = false;
+    for line in lines.by_ref() {
+        if line.trim() == "</opened>" {
+            closed = true;
+            break;
+        }
+        answers.push(parse_answer(line)?);
+    }
+
+    if !closed || answers.is_empty() || lines.next().is_some() {
+        return Err(PageParseError::BadOpenedFormat);
+    }
+    Ok(Question::Opened(answers.into_boxed_slice()))
+}
+
+/// `answer` line of `<opened>` block:
+/// - `= 3.14 +- 0.01` is a number with optional tolerance
+/// - `/(the )?eiffel tower/` is a regex
+/// - `~2 Eiffel tower` is a text with up to 2 typos forgiven
+/// - anything else is a text, `\` at the start escapes the markers above
+fn parse_answer(line: &str) -> Result<OpenedAnswer, PageParseError> {
+    let line = line.trim();
+    if let Some(number) = line.strip_prefix('=') {
+        let (value, tolerance) = number
+            .split_once("+-")
+            .or_else(|| number.split_once('±'))
+            .unwrap_or((number, "0"));
+        let parse = |s: &str| {
+            s.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .ok_or(PageParseError::BadOpenedNumber)
+        };
+        let (value, tolerance) = (parse(value)?, parse(tolerance)?);
+        if tolerance < 0.0 {
+            return Err(PageParseError::BadOpenedNumber);
+        }
+        return Ok(OpenedAnswer::Number {
+            value: Number(value),
+            tolerance: Number(tolerance),
+        });
+    }
+
+    if let Some(pattern) = line
+        .strip_prefix('/')
+        .and_then(|s| s.strip_suffix('/'))
+        .filter(|pattern| !pattern.is_empty())
+    {
+        OpenedAnswer::build_regex(pattern).ok_or(PageParseError::BadOpenedRegex)?;
+        return Ok(OpenedAnswer::Regex(pattern.to_owned()));
+    }
+
+    let (text, typos) = line
+        .strip_prefix('~')
+        .and_then(|s| s.split_once(char::is_whitespace))
+        .and_then(|(typos, text)| Some((text, typos.parse::<u32>().ok()?)))
+        .unwrap_or((line, 0));
+    let text = parse_text_answer(text).ok_or(PageParseError::BadOpenedFormat)?;
+    Ok(OpenedAnswer::Text {
+        text: text.to_owned(),
+        typos,
+    })
+}
+
+/// `text-answer`, without the escaping `\`
+fn parse_text_answer(s: &str) -> Option<&str> {
+    parse_item(s.strip_prefix('\\').unwrap_or(s))
+}
+
+/// `image`
+fn parse_image<'l>(mut lines: impl Iterator<Item = &'l str>) -> Result<Question, PageParseError> {
+    let src = lines
+        .next()
+        .and_then(parse_image_tag)
+        .ok_or(PageParseError::BadImageFormat)?;
+    let lines = lines.collect::<Vec<_>>();
+    // four lines with a number each, rectangle in the format used before other regions were added
+    let legacy = lines.len() == 4
+        && lines
+            .iter()
+            .all(|line| line.trim_start().starts_with(|c: char| c.is_ascii_digit()));
+    let regions = if legacy {
+        vec![parse_legacy_rectangle(&lines).ok_or(PageParseError::BadImageFormat)?]
+    } else {
+        lines
+            .into_iter()
+            .map(|line| parse_region(line).ok_or(PageParseError::BadImageFormat))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    if regions.is_empty() {
+        return Err(PageParseError::BadImageFormat);
+    }
+
+    Ok(Question::Image {
+        src: src.to_owned(),
+        regions: regions.into_boxed_slice(),
+    })
+}
+
+/// Url of the `<img src="..." />` line
+fn parse_image_tag(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix("<img")?
+        .trim_start()
+        .strip_prefix("src=\"")?
+        .strip_suffix("/>")?
+        .trim_end()
+        .strip_suffix('"')
+}
+
+/// Left, top, width and height in pixels, anything after the number on the line is ignored
+fn parse_legacy_rectangle(lines: &[&str]) -> Option<ImageRegion> {
+    fn parse_starting_u32(mut s: &str) -> Option<u32> {
+        if let Some((stripped_end, _)) = s.split_once(' ') {
+            s = stripped_end;
+        }
+        s.parse::<u32>().ok()
+    }
+
+    let &[left, top, width, height] = lines else {
+        return None;
+    };
+    Some(ImageRegion::PixelRectangle {
+        left: parse_starting_u32(left)?,
+        top: parse_starting_u32(top)?,
+        width: parse_starting_u32(width)?,
+        height: parse_starting_u32(height)?,
+    })
+}
+
+/// `region` line, like `circle 50 50 10 -- comment` or `rect 32px 23px 7px 5px`
+fn parse_region(line: &str) -> Option<ImageRegion> {
+    let line = line.split_once("--").map_or(line, |(region, _)| region);
+    let mut words = line
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|word| !word.is_empty());
+    let kind = words.next()?;
+    let words = words.collect::<Vec<_>>();
+    if let Some(pixels) = words
+        .iter()
+        .map(|word| parse_pixels(word))
+        .collect::<Option<Vec<_>>>()
+    {
+        return match (kind, pixels.as_slice()) {
+            ("rect", &[left, top, width, height]) => Some(ImageRegion::PixelRectangle {
+                left,
+                top,
+                width,
+                height,
+            }),
+            _ => None,
+        };
+    }
+    let values = words
+        .into_iter()
+        .map(parse_coordinate)
+        .collect::<Option<Vec<_>>>()?;
+    match (kind, values.as_slice()) {
+        ("rect", &[left, top, width, height]) => Some(ImageRegion::Rectangle {
+            left,
+            top,
+            width,
+            height,
+        }),
+        ("circle", &[x, y, radius]) => Some(ImageRegion::Circle { x, y, radius }),
+        ("polygon", points) if points.len() >= 6 && points.len() % 2 == 0 => Some(
+            ImageRegion::Polygon(points.chunks_exact(2).map(|p| (p[0], p[1])).collect()),
+        ),
+        _ => None,
+    }
+}
+
+/// `coordinate`, like `12.5` or `40%`, in [`IMAGE_SCALE`] units
+fn parse_coordinate(s: &str) -> Option<u32> {
+    let s = s.strip_suffix('%').unwrap_or(s);
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if fraction.len() > 2 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let fraction = format!("{fraction:0<2}").parse::<u32>().ok()?;
+    let value = whole.parse::<u32>().ok()?.checked_mul(100)? + fraction;
+    (value <= IMAGE_SCALE).then_some(value)
+}
+
+/// `pixels`, like `32px`
+fn parse_pixels(s: &str) -> Option<u32> {
+    s.strip_suffix("px")?.parse::<u32>().ok()
+}
+
+/// Source of the page, that parses back into the same page.
+///
+/// It holds for every page produced by the parser. Pages built by hand also need texts, that
+/// do not start with an empty line and have no question tags or `{{` outside of code in them, blank
+/// answers, that are parsed as such, and no text or cloze question right after another one, as those
+/// would be read as a single text.
+pub fn serialize_quest_page(page: &[QuestPageElement]) -> String {
+    let mut lines = Vec::new();
+    for element in page {
+        match element {
+            QuestPageElement::Text(text) => lines.push(text.to_string()),
+            // cloze question is written inline, so its scoring always is the default one
+            QuestPageElement::Question(Question::Cloze { text, blanks }, _) => {
+                let mut cloze = String::new();
+                for (part, blank) in text.iter().zip(blanks.iter()) {
+                    cloze.push_str(part);
+                    cloze.push_str(&format!("{{{{{}}}}}", blank.join("|")));
+                }
+                cloze.push_str(text.last().map_or("", String::as_str));
+                lines.push(cloze);
+            }
+            QuestPageElement::Question(question, scoring) => {
+                lines.push(question_tag(scoring));
+                serialize_question(question, &mut lines);
+                lines.push("</question>".to_owned());
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+fn question_tag(scoring: &Scoring) -> String {
+    let default = Scoring::default();
+    let mut tag = String::from("<question");
+    if scoring.points != default.points {
+        tag.push_str(&format!(" points={}", scoring.points));
+    }
+    if scoring.penalty != default.penalty {
+        tag.push_str(&format!(" penalty={}", scoring.penalty));
+    }
+    if scoring.partial {
+        tag.push_str(" partial");
+    }
+    tag.push('>');
+    tag
+}
+
+fn serialize_question(question: &Question, lines: &mut Vec<String>) {
+    match question {
+        Question::Choice { variants, correct } => {
+            for (variant, no) in variants.iter().zip(0u32..) {
+                let mark = if no == *correct { '+' } else { '-' };
+                lines.push(format!("{mark} {variant}"));
+            }
+        }
+        Question::MultipleChoice { variants, correct } => {
+            for (variant, no) in variants.iter().zip(0u32..) {
+                let mark = if correct.contains(&no) { "[x]" } else { "[ ]" };
+                lines.push(format!("{mark} {variant}"));
+            }
+        }
+        Question::Ordering { items } => {
+            lines.push("<order>".to_owned());
+            lines.extend(items.iter().cloned());
+            lines.push("</order>".to_owned());
+        }
+        Question::Matching { pairs } => {
+            lines.push("<match>".to_owned());
+            lines.extend(
+                pairs
+                    .iter()
+                    .map(|(left, right)| format!("{left} = {right}")),
+            );
+            lines.push("</match>".to_owned());
+        }
+        Question::Opened(answers) => {
+            lines.push("<opened>".to_owned());
+            lines.extend(answers.iter().map(opened_answer_line));
+            lines.push("</opened>".to_owned());
+        }
+        Question::Image { src, regions } => {
+            lines.push(format!("<img src=\"{src}\" />"));
+            lines.extend(regions.iter().map(image_region_line));
+        }
+        Question::Cloze { .. } => unreachable!("cloze questions are written inline"),
+    }
+}
+
+fn opened_answer_line(answer: &OpenedAnswer) -> String {
+    match answer {
+        OpenedAnswer::Text { text, typos } => {
+            let escaped = if text.starts_with(['=', '/', '~', '\\']) || text == "</opened>" {
+                format!("\\{text}")
+            } else {
+                text.clone()
+            };
+            if *typos == 0 {
+                escaped
+            } else {
+                format!("~{typos} {escaped}")
+            }
+        }
+        OpenedAnswer::Regex(pattern) => format!("/{pattern}/"),
+        OpenedAnswer::Number { value, tolerance } => {
+            // positive zero is what the parser puts, when there is no tolerance
+            if tolerance.0.to_bits() == 0f64.to_bits() {
+                format!("= {}", value.0)
+            } else {
+                format!("= {} +- {}", value.0, tolerance.0)
+            }
+        }
+    }
+}
+
+fn image_region_line(region: &ImageRegion) -> String {
+    fn coordinate(value: u32) -> String {
+        let percents = format!("{}.{:02}", value / 100, value % 100);
+        percents
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_owned()
+    }
+
+    match region {
+        &ImageRegion::PixelRectangle {
+            left,
+            top,
+            width,
+            height,
+        } => format!("rect {left}px {top}px {width}px {height}px"),
+        &ImageRegion::Rectangle {
+            left,
+            top,
+            width,
+            height,
+        } => format!(
+            "rect {} {} {} {}",
+            coordinate(left),
+            coordinate(top),
+            coordinate(width),
+            coordinate(height)
+        ),
+        &ImageRegion::Circle { x, y, radius } => format!(
+            "circle {} {} {}",
+            coordinate(x),
+            coordinate(y),
+            coordinate(radius)
+        ),
+        ImageRegion::Polygon(points) => {
+            let points = points
+                .iter()
+                .map(|&(x, y)| format!("{} {}", coordinate(x), coordinate(y)))
+                .collect::<Vec<_>>();
+            format!("polygon {}", points.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod round_trip_tests {
+    use super::serialize_quest_page;
+    use crate::{
+        parse_quest_page, ImageRegion, Number, OpenedAnswer, PageParseError, QuestPageElement,
+        Question, Scoring, IMAGE_SCALE,
+    };
+    use proptest::prelude::*;
+    use proptest::sample::Index;
+
+    fn item() -> impl Strategy<Value = String> {
+        "[a-zA-Z0-9]([a-zA-Z0-9 ,.?!']{0,8}[a-zA-Z0-9.?!])?"
+    }
+
+    fn items(min: usize) -> impl Strategy<Value = Vec<String>> {
+        prop::collection::btree_set(item(), min..6)
+            .prop_map(|items| items.into_iter().collect::<Vec<_>>())
+            .prop_shuffle()
+    }
+
+    fn text() -> impl Strategy<Value = String> {
+        let line = prop_oneof![
+            "[a-zA-Z0-9 .,!?#*_()\\[\\]{}-]{1,20}"
+                .prop_filter("`{{` starts a blank", |line| !line.contains("{{")),
+            // blanks are not looked for in code
+            "[a-z ]{0,5}`\\{\\{[a-z ]{1,5}\\}\\}`[a-z ]{0,5}",
+        ];
+        let code_block = "```[a-z]{0,4}\n[a-z{}()\";, ]{1,20}\n```".prop_map(|block| vec![block]);
+        let lines = prop_oneof![prop::collection::vec(line, 1..4), code_block];
+        // trailing empty lines belong to the text
+        (lines, 0..3usize).prop_map(|(lines, empty)| lines.join("\n") + &"\n".repeat(empty))
+    }
+
+    /// Accepted answer of a blank, braces inside it are kept
+    fn blank_answer() -> impl Strategy<Value = String> {
+        "([a-zA-Z0-9{}][a-zA-Z0-9 {}.]{0,6})?[a-zA-Z0-9{.]"
+            .prop_filter("`}}` closes the blank", |answer| !answer.contains("}}"))
+    }
+
+    fn scoring() -> impl Strategy<Value = Scoring> {
+        (0..5u32, 0..3u32, any::<bool>()).prop_map(|(points, penalty, partial)| Scoring {
+            points,
+            penalty,
+            partial,
+        })
+    }
+
+    fn finite() -> impl Strategy<Value = f64> {
+        any::<f64>().prop_filter("parser accepts finite numbers only", |n| n.is_finite())
+    }
+
+    fn opened_answer() -> impl Strategy<Value = OpenedAnswer> {
+        prop_oneof![
+            (
+                prop::sample::select(vec!["", "=", "/", "~1 ", "\\", "~"]),
+                item(),
+                0..3u32
+            )
+                .prop_map(|(prefix, text, typos)| OpenedAnswer::Text {
+                    text: format!("{prefix}{text}"),
+                    typos,
+                }),
+            "[a-z]{1,5}(\\|[a-z]{1,3})?( [a-z]+)?".prop_map(OpenedAnswer::Regex),
+            (
+                finite(),
+                prop_oneof![Just(0.0), Just(-0.0), finite().prop_map(f64::abs)]
+            )
+                .prop_map(|(value, tolerance)| OpenedAnswer::Number {
+                    value: Number(value),
+                    tolerance: Number(tolerance),
+                }),
+        ]
+    }
+
+    fn image_region() -> impl Strategy<Value = ImageRegion> {
+        let coordinate = || 0..=IMAGE_SCALE;
+        let pixels = || 0..100_000u32;
+        prop_oneof![
+            (pixels(), pixels(), pixels(), pixels()).prop_map(|(left, top, width, height)| {
+                ImageRegion::PixelRectangle {
+                    left,
+                    top,
+                    width,
+                    height,
+                }
+            }),
+            (coordinate(), coordinate(), coordinate(), coordinate()).prop_map(
+                |(left, top, width, height)| ImageRegion::Rectangle {
+                    left,
+                    top,
+                    width,
+                    height,
+                }
+            ),
+            (coordinate(), coordinate(), coordinate())
+                .prop_map(|(x, y, radius)| ImageRegion::Circle { x, y, radius }),
+            prop::collection::vec((coordinate(), coordinate()), 3..6)
+                .prop_map(|points| ImageRegion::Polygon(points.into_boxed_slice())),
+        ]
+    }
+
+    fn question() -> impl Strategy<Value = Question> {
+        prop_oneof![
+            (items(1), any::<Index>()).prop_map(|(variants, correct)| Question::Choice {
+                correct: correct.index(variants.len()) as u32,
+                variants: variants.into_boxed_slice(),
+            }),
+            items(1)
+                .prop_flat_map(|variants| {
+                    let count = variants.len();
+                    (
+                        Just(variants),
+                        prop::collection::btree_set(0..count as u32, 1..=count),
+                    )
+                })
+                .prop_map(|(variants, correct)| Question::MultipleChoice {
+                    variants: variants.into_boxed_slice(),
+                    correct: correct.into_iter().collect(),
+                }),
+            items(2).prop_map(|items| Question::Ordering {
+                items: items.into_boxed_slice(),
+            }),
+            items(2)
+                .prop_flat_map(|left| {
+                    let count = left.len();
+                    (
+                        Just(left),
+                        items(count).prop_map(move |right| right[..count].to_vec()),
+                    )
+                })
+                .prop_map(|(left, right)| Question::Matching {
+                    pairs: left.into_iter().zip(right).collect(),
+                }),
+            prop::collection::vec(opened_answer(), 1..4)
+                .prop_map(|answers| Question::Opened(answers.into_boxed_slice())),
+            (
+                "[a-z]{1,8}\\.png",
+                prop::collection::vec(image_region(), 1..4)
+            )
+                .prop_map(|(src, regions)| Question::Image {
+                    src,
+                    regions: regions.into_boxed_slice(),
+                }),
+        ]
+    }
+
+    fn cloze() -> impl Strategy<Value = QuestPageElement> {
+        let part = || {
+            "[a-zA-Z0-9 .,!?{}]{0,10}".prop_filter("`{{` starts a blank", |part| {
+                !part.contains("{{") && !part.ends_with('{')
+            })
+        };
+        let blank = prop::collection::vec(blank_answer(), 1..3).prop_map(Vec::into_boxed_slice);
+        prop::collection::vec((part(), blank), 1..4)
+            .prop_flat_map(move |blanks| (Just(blanks), part(), 0..3usize))
+            .prop_map(|(blanks, last, empty)| {
+                let (mut text, blanks): (Vec<_>, Vec<_>) = blanks.into_iter().unzip();
+                // trailing empty lines belong to the text
+                text.push(last + &"\n".repeat(empty));
+                let scoring = Scoring {
+                    points: blanks.len() as u32,
+                    penalty: 0,
+                    partial: true,
+                };
+                QuestPageElement::Question(
+                    Question::Cloze {
+                        text: text.into_boxed_slice(),
+                        blanks: blanks.into_boxed_slice(),
+                    },
+                    scoring,
+                )
+            })
+    }
+
+    fn is_inline(element: &QuestPageElement) -> bool {
+        matches!(
+            element,
+            QuestPageElement::Text(_) | QuestPageElement::Question(Question::Cloze { .. }, _)
+        )
+    }
+
+    fn page() -> impl Strategy<Value = Vec<QuestPageElement>> {
+        let element = prop_oneof![
+            text().prop_map(|text| QuestPageElement::Text(text.into_boxed_str())),
+            cloze(),
+            (question(), scoring())
+                .prop_map(|(question, scoring)| QuestPageElement::Question(question, scoring)),
+        ];
+        prop::collection::vec(element, 0..6).prop_map(|elements| {
+            // consecutive texts are read as one
+            let mut page = Vec::<QuestPageElement>::new();
+            for element in elements {
+                if !(is_inline(&element) && page.last().is_some_and(is_inline)) {
+                    page.push(element);
+                }
+            }
+            page
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn page_round_trip(page in page()) {
+            let source = serialize_quest_page(&page);
+            let parsed = parse_quest_page(&*source);
+            prop_assert_eq!(parsed.as_deref(), Ok(&page[..]), "source:\n{}", source);
+        }
+
+        /// Any source, that parses, parses the same after it is written back
+        #[test]
+        fn source_round_trip(source in source()) {
+            if let Ok(page) = parse_quest_page(&*source) {
+                let written = serialize_quest_page(&page);
+                let parsed = parse_quest_page(&*written);
+                prop_assert_eq!(parsed.as_deref(), Ok(&page[..]), "source:\n{}\nwritten:\n{}", source, written);
+            }
+        }
+    }
+
+    /// Source from pieces, that are special somewhere in the format
+    fn source() -> impl Strategy<Value = String> {
+        let pieces = vec![
+            "a",
+            " ",
+            "  ",
+            "\n",
+            "\n\n",
+            "\r\n",
+            "{",
+            "}",
+            "{{",
+            "}}",
+            "|",
+            "\\",
+            "`",
+            "```",
+            "~",
+            "=",
+            "+ ",
+            "- ",
+            "[x] ",
+            "[ ] ",
+            "<question>",
+            "<question points=2>",
+            "</question>",
+            "<order>",
+            "</order>",
+            "<match>",
+            "</match>",
+            "<opened>",
+            "</opened>",
+            "/a/",
+            "= 1",
+            "<img src=\"a.png\" />",
+            "rect 1 2 3 4",
+            "5",
+        ];
+        prop::collection::vec(prop::sample::select(pieces), 0..40)
+            .prop_map(|pieces| pieces.concat())
+    }
+
+    #[test]
+    fn trailing_lines_and_braces() {
+        for source in [
+            "foo\n\n",
+            "foo\n",
+            "<question>\n+ a\n</question>\ntext\n\n",
+            "a {{x}}} b",
+        ] {
+            let page = parse_quest_page(source).unwrap();
+            assert_eq!(
+                parse_quest_page(serialize_quest_page(&page)),
+                Ok(page),
+                "{source:?}"
+            );
+        }
+        assert_eq!(
+            parse_quest_page("foo\n\n").unwrap()[..],
+            [QuestPageElement::Text("foo\n\n".into())]
+        );
+        // would be read back as `{{x}}` and `} b`
+        assert_eq!(
+            parse_quest_page("a {{x} }} b").unwrap_err()[0].kind,
+            PageParseError::BadBlankFormat
+        );
+    }
+
+    #[test]
+    fn readable_source() {
+        let source = "\
+Capital of Ukraine is {{Kyiv|Kiev}}.
+<question points=2 partial>
+[x] Kyiv
+[ ] Lviv
+[x] Kharkiv
+</question>
+<question>
+<opened>
+~1 Kyiv
+\\/not a regex
+= 9.81 +- 0.01
+</opened>
+</question>
+<question penalty=1>
+<img src=\"map.png\" />
+rect 10 20.5 5 5
+polygon 0 0, 20 0, 0 20
+</question>";
+        let page = parse_quest_page(source).unwrap();
+        assert_eq!(serialize_quest_page(&page), source);
+    }
+}
+
+#[cfg(test)]
+mod grammar_tests {
+    use crate::{check_quest_page, QuestPageElement, Question, Scoring};
+    use proptest::prelude::*;
+
+    /// Question kind and count of its variants, items, pairs, answers or regions
+    type Expected = (&'static str, usize, Scoring);
+
+    fn kind(question: &Question) -> (&'static str, usize) {
+        match question {
+            Question::Choice { variants, .. } => ("choice", variants.len()),
+            Question::MultipleChoice { variants, .. } => ("multiple-choice", variants.len()),
+            Question::Ordering { items } => ("ordering", items.len()),
+            Question::Matching { pairs } => ("matching", pairs.len()),
+            Question::Opened(answers) => ("opened", answers.len()),
+            Question::Image { regions, .. } => ("image", regions.len()),
+            Question::Cloze { blanks, .. } => ("cloze", blanks.len()),
+        }
+    }
+
+    fn ws() -> impl Strategy<Value = String> {
+        "[ \t]{0,2}"
+    }
+
+    fn padded(line: impl Strategy<Value = String>) -> impl Strategy<Value = String> {
+        (ws(), line, ws()).prop_map(|(before, line, after)| format!("{before}{line}{after}"))
+    }
+
+    /// Lines with empty ones in between, where blocks skip them
+    fn block(lines: Vec<String>) -> impl Strategy<Value = String> {
+        prop::collection::vec(prop::sample::select(vec!["", "\n", "  \n"]), lines.len()).prop_map(
+            move |gaps| {
+                let lines = lines
+                    .iter()
+                    .zip(gaps)
+                    .map(|(line, gap)| format!("{gap}{line}"));
+                lines.collect::<Vec<_>>().join("\n")
+            },
+        )
+    }
+
+    fn items(min: usize) -> impl Strategy<Value = Vec<String>> {
+        prop::collection::btree_set("[a-zA-Z0-9]([a-zA-Z0-9 ,.?!']{0,8}[a-zA-Z0-9.?!])?", min..6)
+            .prop_map(|items| items.into_iter().collect::<Vec<_>>())
+            .prop_shuffle()
+    }
+
+    fn choice() -> impl Strategy<Value = (String, usize)> {
+        items(1)
+            .prop_flat_map(|variants| {
+                let count = variants.len();
+                (Just(variants), 0..count, prop::collection::vec(ws(), count))
+            })
+            .prop_map(|(variants, correct, spaces)| {
+                let lines =
+                    variants
+                        .iter()
+                        .zip(spaces)
+                        .enumerate()
+                        .map(|(no, (variant, space))| {
+                            let mark = if no == correct { '+' } else { '-' };
+                            format!("{mark}{space}{variant}{space}")
+                        });
+                lines.collect::<Vec<_>>()
+            })
+            .prop_flat_map(|lines| {
+                let count = lines.len();
+                (block(lines), Just(count))
+            })
+    }
+
+    fn multiple_choice() -> impl Strategy<Value = (String, usize)> {
+        items(1)
+            .prop_flat_map(|variants| {
+                let count = variants.len();
+                let marks = prop::sample::select(vec!["[x]", "[X]", "[ ]"]);
+                (
+                    Just(variants),
+                    prop::collection::vec(marks, count),
+                    0..count,
+                )
+            })
+            .prop_map(|(variants, mut marks, checked)| {
+                marks[checked] = "[x]";
+                let lines = variants
+                    .iter()
+                    .zip(marks)
+                    .map(|(variant, mark)| format!("{mark} {variant}"));
+                lines.collect::<Vec<_>>()
+            })
+            .prop_flat_map(|lines| {
+                let count = lines.len();
+                (block(lines), Just(count))
+            })
+    }
+
+    fn tagged(
+        open: &'static str,
+        lines: impl Strategy<Value = Vec<String>>,
+        close: &'static str,
+    ) -> impl Strategy<Value = (String, usize)> {
+        (
+            padded(Just(open.to_owned())),
+            lines,
+            padded(Just(close.to_owned())),
+        )
+            .prop_flat_map(|(open, lines, close)| {
+                let count = lines.len();
+                (Just(open), block(lines), Just(close), Just(count))
+            })
+            .prop_map(|(open, lines, close, count)| (format!("{open}\n{lines}\n{close}"), count))
+    }
+
+    fn ordering() -> impl Strategy<Value = (String, usize)> {
+        let lines = items(2)
+            .prop_flat_map(|items| {
+                let count = items.len();
+                (Just(items), prop::collection::vec(ws(), count))
+            })
+            .prop_map(|(items, spaces)| {
+                let lines = items
+                    .iter()
+                    .zip(spaces)
+                    .map(|(item, space)| format!("{space}{item}"));
+                lines.collect()
+            });
+        tagged("<order>", lines, "</order>")
+    }
+
+    fn matching() -> impl Strategy<Value = (String, usize)> {
+        let lines = (items(2), items(5)).prop_map(|(left, right)| {
+            let pairs = left.iter().zip(right.iter().cycle());
+            pairs
+                .enumerate()
+                .map(|(no, (left, right))| {
+                    format!(
+                        "{left}{}={}{right}{no}",
+                        " ".repeat(no % 2),
+                        " ".repeat(no % 3)
+                    )
+                })
+                .collect()
+        });
+        tagged("<match>", lines, "</match>")
+    }
+
+    fn answer() -> impl Strategy<Value = String> {
+        let number = "-?[0-9]{1,4}(\\.[0-9]{1,3})?";
+        let tolerance = "[0-9]{1,2}(\\.[0-9])?";
+        let text = "[a-zA-Z0-9]([a-zA-Z0-9 ,.?!']{0,8}[a-zA-Z0-9.?!])?";
+        padded(prop_oneof![
+            (number, ws()).prop_map(|(value, space)| format!("={space}{value}")),
+            (
+                number,
+                prop::sample::select(vec!["+-", "±", " +- ", " ± "]),
+                tolerance
+            )
+                .prop_map(|(value, sign, tolerance)| format!("= {value}{sign}{tolerance}")),
+            "[a-z]{1,5}(\\|[a-z]{1,3})?( [a-z]+)?".prop_map(|regex| format!("/{regex}/")),
+            ("[0-9]", text).prop_map(|(typos, text)| format!("~{typos} {text}")),
+            ("[=/~\\\\]?", text).prop_map(|(marker, text)| format!("\\{marker}{text}")),
+            text.prop_map(String::from),
+        ])
+    }
+
+    fn opened() -> impl Strategy<Value = (String, usize)> {
+        tagged(
+            "<opened>",
+            prop::collection::vec(answer(), 1..5),
+            "</opened>",
+        )
+    }
+
+    fn region() -> impl Strategy<Value = String> {
+        let coordinate = "(100|[1-9]?[0-9](\\.[0-9]{1,2})?)%?";
+        let separator = prop::sample::select(vec![" ", "  ", ", ", ",", "\t"]);
+        let coordinates = move |count: usize| {
+            prop::collection::vec((coordinate, separator.clone()), count).prop_map(|values| {
+                let values = values
+                    .into_iter()
+                    .map(|(value, separator)| format!("{separator}{value}"));
+                values.collect::<String>()
+            })
+        };
+        let region = prop_oneof![
+            coordinates(4).prop_map(|values| format!("rect{values}")),
+            coordinates(3).prop_map(|values| format!("circle{values}")),
+            (3..6usize)
+                .prop_flat_map(move |points| coordinates(points * 2))
+                .prop_map(|values| format!("polygon{values}")),
+            prop::collection::vec("[0-9]{1,5}", 4)
+                .prop_map(|pixels| format!("rect {}px", pixels.join("px "))),
+        ];
+        (region, "( -- [a-z -]{0,10})?").prop_map(|(region, comment)| format!("{region}{comment}"))
+    }
+
+    fn image() -> impl Strategy<Value = (String, usize)> {
+        let tag = ("[a-z]{1,8}\\.png", prop::sample::select(vec!["", " "]))
+            .prop_map(|(src, space)| format!("<img src=\"{src}\"{space}/>"));
+        let regions = prop::collection::vec(region(), 1..4).prop_flat_map(|regions| {
+            let count = regions.len();
+            (block(regions), Just(count))
+        });
+        // four lines with a number each, as images were written before regions
+        let legacy = prop::collection::vec("[0-9]{1,4}( [a-z-]{0,8})?", 4)
+            .prop_map(|lines| (lines.join("\n"), 1));
+        (padded(tag), prop_oneof![regions, legacy])
+            .prop_map(|(tag, (regions, count))| (format!("{tag}\n{regions}"), count))
+    }
+
+    fn question() -> impl Strategy<Value = (String, Expected)> {
+        let body = prop_oneof![
+            choice().prop_map(|(source, count)| (source, "choice", count)),
+            multiple_choice().prop_map(|(source, count)| (source, "multiple-choice", count)),
+            ordering().prop_map(|(source, count)| (source, "ordering", count)),
+            matching().prop_map(|(source, count)| (source, "matching", count)),
+            opened().prop_map(|(source, count)| (source, "opened", count)),
+            image().prop_map(|(source, count)| (source, "image", count)),
+        ];
+        let attributes = (
+            prop::option::of(0..10u32),
+            prop::option::of(0..10u32),
+            any::<bool>(),
+        )
+            .prop_flat_map(|(points, penalty, partial)| {
+                let mut attributes = Vec::new();
+                attributes.extend(points.map(|points| format!("points={points}")));
+                attributes.extend(penalty.map(|penalty| format!("penalty={penalty}")));
+                if partial {
+                    attributes.push("partial".to_owned());
+                }
+                let scoring = Scoring {
+                    points: points.unwrap_or(Scoring::default().points),
+                    penalty: penalty.unwrap_or_default(),
+                    partial,
+                };
+                (Just(attributes).prop_shuffle(), Just(scoring))
+            });
+        (
+            padded(Just(String::new())),
+            attributes,
+            body,
+            padded(Just("</question>".to_owned())),
+        )
+            .prop_map(
+                |(space, (attributes, scoring), (body, kind, count), close)| {
+                    let attributes = attributes
+                        .iter()
+                        .map(|attribute| format!(" {attribute}"))
+                        .collect::<String>();
+                    let source = format!("{space}<question{attributes}>\n{body}\n{close}");
+                    (source, (kind, count, scoring))
+                },
+            )
+    }
+
+    fn text() -> impl Strategy<Value = String> {
+        let blank = prop::collection::vec("[a-z]([a-z ]{0,5}[a-z])?", 1..3)
+            .prop_map(|answers| format!("{{{{{}}}}}", answers.join("|")));
+        let code = prop::option::of("`[a-z ]{0,3}\\{\\{[a-z]{0,3}\\}?\\}?`");
+        let line = (
+            "[a-zA-Z0-9 .,!?#*_()-]{0,20}",
+            code,
+            prop::option::of(blank),
+        )
+            .prop_map(|(text, code, blank)| {
+                format!(
+                    "{text}{}{}",
+                    code.unwrap_or_default(),
+                    blank.unwrap_or_default()
+                )
+            });
+        (prop::collection::vec(line, 1..4), 0..3usize)
+            .prop_map(|(lines, empty)| lines.join("\n") + &"\n".repeat(empty))
+    }
+
+    fn page() -> impl Strategy<Value = (String, Vec<Expected>)> {
+        let element = prop_oneof![
+            text().prop_map(|text| (text, None)),
+            question().prop_map(|(source, expected)| (source, Some(expected))),
+        ];
+        prop::collection::vec(element, 0..6).prop_map(|elements| {
+            let (sources, expected): (Vec<_>, Vec<_>) = elements.into_iter().unzip();
+            (sources.join("\n"), expected.into_iter().flatten().collect())
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn grammar_conforming_page((source, expected) in page()) {
+            let check = check_quest_page(&*source);
+            let page = check.page.map_err(|errors| format!("{errors:?}\nsource:\n{source}"));
+            let questions = page
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|element| match element {
+                    QuestPageElement::Question(Question::Cloze { .. }, _) => None,
+                    QuestPageElement::Question(question, scoring) => {
+                        let (kind, count) = kind(question);
+                        Some((kind, count, *scoring))
+                    }
+                    QuestPageElement::Text(_) => None,
+                })
+                .collect::<Vec<_>>();
+            prop_assert!(page.is_ok(), "{}", page.unwrap_err());
+            prop_assert_eq!(questions, expected, "source:\n{}", source);
+        }
+    }
+}
@@ -1,8 +1,17 @@
 use derive_more::{Display, From, FromStr, Into, TryFrom};
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
 use uuid::Uuid;
 
+mod api;
+mod diff;
+mod format;
 mod questions;
+pub use api::*;
+pub use diff::*;
+pub use format::*;
 pub use questions::*;
 
 #[derive(
@@ -34,12 +43,46 @@ pub struct LoginResponse {
     pub id: UserId,
 }
 
+/// Roles are ordered, each role has all permissions of the previous ones
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    TryFrom,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+#[try_from(repr)]
+#[repr(u8)]
+pub enum UserRole {
+    #[default]
+    User = 0,
+    /// Can review, edit and lock quests
+    Moderator = 1,
+    /// Can manage users and moderators
+    Admin = 2,
+}
+
+impl From<UserRole> for u8 {
+    fn from(value: UserRole) -> Self {
+        value as u8
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserInfo {
     pub id: UserId,
     pub name: String,
     pub email: String, // probably will be changed for some sort of enum representing identity (?)
     pub avatar_url: Option<Uuid>,
+    #[serde(default)]
+    pub role: UserRole,
 }
 
 impl UserInfo {
@@ -49,10 +92,32 @@ impl UserInfo {
             name: name.into(),
             email: email.into(),
             avatar_url: None,
+            role: UserRole::User,
         }
     }
 }
 
+/// Provided by server for admin user lists
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminUserRecord {
+    pub id: UserId,
+    pub name: String,
+    pub email: String,
+    pub role: UserRole,
+    pub banned: bool,
+}
+
+pub const ADMIN_USERS_PAGE_SIZE: usize = 20;
+
+/// Provided by server for admin user lists
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminUsersPage {
+    /// max len is limited in the constant above
+    pub data: Box<[AdminUserRecord]>,
+    pub page: u32,
+    pub total_pages: u32,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, From)]
 #[serde(transparent)]
 pub struct SessionKey(Uuid);
@@ -77,6 +142,12 @@ pub struct QuestHistoryRecord {
     pub started_at: Timestamp,
     pub finished_at: Option<Timestamp>,
     pub completed_pages: u32,
+    /// sum of scores of submitted answers
+    #[serde(default)]
+    pub score: i32,
+    /// score of the quest, when every answer is correct
+    #[serde(default)]
+    pub max_score: u32,
 }
 
 pub const QUEST_HISTORY_PAGE_SIZE: usize = 20;
@@ -90,7 +161,7 @@ pub struct QuestHistoryPage {
 }
 
 // TODO: multiple moderators
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, TryFrom)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, TryFrom, Hash, PartialEq, Eq)]
 #[try_from(repr)]
 #[repr(u8)]
 pub enum QuestState {
@@ -116,6 +187,91 @@ impl From<QuestState> for u8 {
     }
 }
 
+/// Requested change of a [`QuestState`]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, PartialEq, Eq)]
+pub enum QuestAction {
+    /// Author makes the quest public without a review
+    Publish,
+    /// Author asks moderator for a review
+    Submit,
+    /// Moderator returns the quest to the author, possibly with edits
+    Return,
+    /// Moderator reviews the quest, publishing it if it was not yet
+    Approve,
+    /// Moderator locks the quest down
+    Lock,
+}
+
+impl QuestAction {
+    pub fn by_moderator(self) -> bool {
+        match self {
+            QuestAction::Publish | QuestAction::Submit => false,
+            QuestAction::Return | QuestAction::Approve | QuestAction::Lock => true,
+        }
+    }
+}
+
+impl QuestState {
+    /// Quest can be joined and played by users
+    pub fn is_published(self) -> bool {
+        matches!(self, QuestState::Published | QuestState::PublishedReviewed)
+    }
+
+    pub fn author_can_edit(self) -> bool {
+        matches!(self, QuestState::Unpublished | QuestState::Returned)
+    }
+
+    pub fn moderator_can_edit(self) -> bool {
+        matches!(self, QuestState::Submitted | QuestState::Returned)
+    }
+
+    /// State after the action, if action is allowed in this state
+    pub fn apply(self, action: QuestAction) -> Option<QuestState> {
+        match (self, action) {
+            (QuestState::Unpublished | QuestState::Returned, QuestAction::Publish) => {
+                Some(QuestState::Published)
+            }
+            (QuestState::Unpublished | QuestState::Returned, QuestAction::Submit) => {
+                Some(QuestState::Submitted)
+            }
+            (QuestState::Submitted, QuestAction::Return) => Some(QuestState::Returned),
+            (QuestState::Submitted | QuestState::Published, QuestAction::Approve) => {
+                Some(QuestState::PublishedReviewed)
+            }
+            (QuestState::Locked, _) => None,
+            (_, QuestAction::Lock) => Some(QuestState::Locked),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod quest_state_tests {
+    use crate::{QuestAction, QuestState};
+
+    #[test]
+    fn transitions() {
+        use QuestAction::*;
+        use QuestState::*;
+
+        assert_eq!(Unpublished.apply(Publish), Some(Published));
+        assert_eq!(Unpublished.apply(Submit), Some(Submitted));
+        assert_eq!(Unpublished.apply(Approve), None);
+        assert_eq!(Submitted.apply(Return), Some(Returned));
+        assert_eq!(Submitted.apply(Approve), Some(PublishedReviewed));
+        assert_eq!(Submitted.apply(Publish), None);
+        assert_eq!(Returned.apply(Submit), Some(Submitted));
+        assert_eq!(Returned.apply(Publish), Some(Published));
+        assert_eq!(Published.apply(Approve), Some(PublishedReviewed));
+        assert_eq!(Published.apply(Return), None);
+        assert_eq!(PublishedReviewed.apply(Approve), None);
+        assert_eq!(PublishedReviewed.apply(Lock), Some(Locked));
+        assert_eq!(Unpublished.apply(Lock), Some(Locked));
+        assert_eq!(Locked.apply(Lock), None);
+        assert_eq!(Locked.apply(Publish), None);
+    }
+}
+
 /// Provided by server for quest lists
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserOwnedQuestRecord {
@@ -134,6 +290,61 @@ pub struct UserOwnedQuestsPage {
     pub total_pages: u32,
 }
 
+/// Saved version of quest info or of one of its pages
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum QuestRevisionContent {
+    Info(QuestInfo),
+    Page {
+        /// number of the page at the time it was saved
+        page: u32,
+        source: QuestPageSource,
+    },
+}
+
+impl QuestRevisionContent {
+    /// Content as lines of text, that are compared between revisions
+    pub fn text(&self) -> String {
+        fn optional(value: Option<impl ToString>) -> String {
+            value.map_or_else(|| String::from("none"), |value| value.to_string())
+        }
+        match self {
+            QuestRevisionContent::Info(info) => format!(
+                "title: {}\ntime limit, seconds: {}\nopens at: {}\ncloses at: {}\n{}",
+                info.title,
+                optional(info.time_limit.map(|limit| limit.as_secs())),
+                optional(info.opens_at),
+                optional(info.closes_at),
+                info.description,
+            ),
+            QuestRevisionContent::Page { source, .. } => format!(
+                "time limit, seconds: {}\n{}",
+                optional(source.time_limit.map(|limit| limit.as_secs())),
+                source.source,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestRevisionRecord {
+    pub id: u32,
+    pub author: UserId,
+    pub created_at: Timestamp,
+    /// page number at the time of the revision, none for quest info
+    pub page: Option<u32>,
+}
+
+pub const QUEST_REVISIONS_PAGE_SIZE: usize = 20;
+
+/// Revisions of the quest, the newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuestRevisionsPage {
+    /// max len is limited in the constant above
+    pub data: Box<[QuestRevisionRecord]>,
+    pub page: u32,
+    pub total_pages: u32,
+}
+
 /// /api/quests/:id/info
 #[derive(Debug, Serialize, Deserialize, Clone, Hash, PartialEq, Eq)]
 pub struct QuestInfo {
@@ -142,27 +353,393 @@ pub struct QuestInfo {
     pub title: String,
     pub description: String,
     pub pages: u32,
-    pub published: bool,
+    pub state: QuestState,
+    /// time to complete the quest, counted from joining it
+    #[serde(default)]
+    pub time_limit: Option<Duration>,
+    /// quest can not be joined before
+    #[serde(default)]
+    pub opens_at: Option<Timestamp>,
+    /// quest can not be joined or played after
+    #[serde(default)]
+    pub closes_at: Option<Timestamp>,
+}
+
+impl QuestInfo {
+    /// Whether quest can be joined at the moment
+    pub fn is_open_at(&self, now: Timestamp) -> bool {
+        self.opens_at.is_none_or(|opens_at| opens_at <= now)
+            && self.closes_at.is_none_or(|closes_at| now < closes_at)
+    }
+
+    /// Moment quest joined at `started_at` has to be finished by, whichever of time limit and
+    /// closing time comes first
+    pub fn deadline(&self, started_at: Timestamp) -> Option<Timestamp> {
+        let limit_end = self
+            .time_limit
+            .and_then(|limit| chrono::TimeDelta::from_std(limit).ok())
+            .and_then(|limit| started_at.checked_add_signed(limit));
+        match (limit_end, self.closes_at) {
+            (Some(limit_end), Some(closes_at)) => Some(limit_end.min(closes_at)),
+            (limit_end, closes_at) => limit_end.or(closes_at),
+        }
+    }
+}
+
+#[cfg(test)]
+mod quest_schedule_tests {
+    use crate::{QuestId, QuestInfo, QuestState, Timestamp, UserId};
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    fn at(hour: u32) -> Timestamp {
+        chrono::NaiveDate::from_ymd_opt(2025, 1, 1)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    fn quest(
+        time_limit: Option<Duration>,
+        opens_at: Option<Timestamp>,
+        closes_at: Option<Timestamp>,
+    ) -> QuestInfo {
+        QuestInfo {
+            id: QuestId(Uuid::nil()),
+            owner: UserId(Uuid::nil()),
+            title: String::new(),
+            description: String::new(),
+            pages: 1,
+            state: QuestState::Published,
+            time_limit,
+            opens_at,
+            closes_at,
+        }
+    }
+
+    #[test]
+    fn open_window() {
+        assert!(quest(None, None, None).is_open_at(at(5)));
+
+        let window = quest(None, Some(at(2)), Some(at(4)));
+        assert!(!window.is_open_at(at(1)));
+        assert!(window.is_open_at(at(2)));
+        assert!(window.is_open_at(at(3)));
+        assert!(!window.is_open_at(at(4)));
+    }
+
+    #[test]
+    fn deadline() {
+        let hour = Some(Duration::from_secs(60 * 60));
+        assert_eq!(quest(None, None, None).deadline(at(1)), None);
+        assert_eq!(quest(hour, None, None).deadline(at(1)), Some(at(2)));
+        assert_eq!(quest(None, None, Some(at(3))).deadline(at(1)), Some(at(3)));
+        assert_eq!(quest(hour, None, Some(at(3))).deadline(at(1)), Some(at(2)));
+        assert_eq!(
+            quest(hour, None, Some(at(3))).deadline(at(2) + chrono::TimeDelta::minutes(30)),
+            Some(at(3))
+        );
+    }
+}
+
+/// Image coordinates are measured in these units of its width and height, so the answer is the
+/// same whatever size the image is shown at: `(0, 0)` is top left corner, `(IMAGE_SCALE, IMAGE_SCALE)`
+/// is bottom right one
+pub const IMAGE_SCALE: u32 = 10_000;
+
+/// Part of image, that is accepted as correct answer, in [`IMAGE_SCALE`] units
+#[derive(Debug, Serialize, Deserialize, Clone, Hash, PartialEq, Eq)]
+pub enum ImageRegion {
+    /// in pixels of the image at its natural size, as in the format used before the other
+    /// regions were added, right and bottom edges are not a part of it
+    PixelRectangle {
+        left: u32,
+        top: u32,
+        width: u32,
+        height: u32,
+    },
+    Rectangle {
+        left: u32,
+        top: u32,
+        width: u32,
+        height: u32,
+    },
+    Circle {
+        x: u32,
+        y: u32,
+        radius: u32,
+    },
+    /// vertices in order, edges between them must not cross
+    Polygon(Box<[(u32, u32)]>),
+}
+
+impl ImageRegion {
+    /// Whether the point in pixels of the image with natural size `size` is inside
+    pub fn contains_pixel(&self, left: u32, top: u32, size: (u32, u32)) -> bool {
+        let scale = |value: u32, size: u32| {
+            u32::try_from(u64::from(value) * u64::from(IMAGE_SCALE) / u64::from(size)).ok()
+        };
+        match *self {
+            ImageRegion::PixelRectangle {
+                left: region_left,
+                top: region_top,
+                width,
+                height,
+            } => {
+                let (Some(dx), Some(dy)) =
+                    (left.checked_sub(region_left), top.checked_sub(region_top))
+                else {
+                    return false;
+                };
+                dx < width && dy < height
+            }
+            _ if size.0 == 0 || size.1 == 0 => false,
+            _ => match (scale(left, size.0), scale(top, size.1)) {
+                (Some(left), Some(top)) => self.contains(left, top),
+                _ => false,
+            },
+        }
+    }
+
+    /// Point is in [`IMAGE_SCALE`] units, right and bottom edges are not a part of the region,
+    /// so adjacent ones do not overlap. Pixel rectangles contain nothing, as they need image size,
+    /// see [`ImageRegion::contains_pixel`]
+    pub fn contains(&self, left: u32, top: u32) -> bool {
+        let (x, y) = (i64::from(left), i64::from(top));
+        match self {
+            ImageRegion::PixelRectangle { .. } => false,
+            &ImageRegion::Rectangle {
+                left,
+                top,
+                width,
+                height,
+            } => {
+                let (dx, dy) = (x - i64::from(left), y - i64::from(top));
+                (0..i64::from(width)).contains(&dx) && (0..i64::from(height)).contains(&dy)
+            }
+            &ImageRegion::Circle {
+                x: cx,
+                y: cy,
+                radius,
+            } => {
+                let (dx, dy) = (x - i64::from(cx), y - i64::from(cy));
+                dx * dx + dy * dy < i64::from(radius) * i64::from(radius)
+            }
+            ImageRegion::Polygon(points) => {
+                // even-odd rule: count edges crossed by a ray going left from the point
+                let mut inside = false;
+                for (i, &(x1, y1)) in points.iter().enumerate() {
+                    let (x2, y2) = points[(i + 1) % points.len()];
+                    let (x1, y1, x2, y2) =
+                        (i64::from(x1), i64::from(y1), i64::from(x2), i64::from(y2));
+                    if (y1 > y) == (y2 > y) {
+                        continue;
+                    }
+                    // sign of cross product tells on which side of the edge the point is
+                    let cross = (x2 - x1) * (y - y1) - (x - x1) * (y2 - y1);
+                    let crossed = if y2 > y1 { cross > 0 } else { cross < 0 };
+                    if crossed {
+                        inside = !inside;
+                    }
+                }
+                inside
+            }
+        }
+    }
 }
 
+#[cfg(test)]
+mod image_region_tests {
+    use super::ImageRegion;
+
+    #[test]
+    fn rectangle() {
+        let rect = ImageRegion::Rectangle {
+            left: 10,
+            top: 20,
+            width: 5,
+            height: 5,
+        };
+        assert!(rect.contains(10, 20));
+        assert!(rect.contains(14, 24));
+        assert!(!rect.contains(15, 22));
+        assert!(!rect.contains(12, 25));
+        assert!(!rect.contains(9, 22));
+        assert!(!rect.contains(0, 0));
+    }
+
+    #[test]
+    fn circle() {
+        let circle = ImageRegion::Circle {
+            x: 100,
+            y: 100,
+            radius: 10,
+        };
+        assert!(circle.contains(100, 100));
+        assert!(circle.contains(106, 107));
+        assert!(!circle.contains(108, 108));
+        assert!(!circle.contains(110, 100));
+        assert!(!circle.contains(0, 0));
+    }
+
+    #[test]
+    fn polygon() {
+        // square with a notch cut from the top
+        let polygon =
+            ImageRegion::Polygon(Box::new([(0, 0), (50, 50), (100, 0), (100, 100), (0, 100)]));
+        assert!(polygon.contains(50, 75));
+        assert!(polygon.contains(10, 20));
+        assert!(polygon.contains(90, 20));
+        assert!(!polygon.contains(50, 20));
+        assert!(!polygon.contains(150, 50));
+        assert!(!polygon.contains(50, 150));
+
+        let triangle = ImageRegion::Polygon(Box::new([(0, 0), (100, 0), (0, 100)]));
+        assert!(triangle.contains(20, 20));
+        assert!(!triangle.contains(60, 60));
+    }
+    #[test]
+    fn pixels() {
+        let legacy = ImageRegion::PixelRectangle {
+            left: 32,
+            top: 23,
+            width: 7,
+            height: 5,
+        };
+        let size = (640, 480);
+        assert!(legacy.contains_pixel(32, 23, size));
+        assert!(legacy.contains_pixel(38, 27, size));
+        // far edges are outside, like with other regions
+        assert!(!legacy.contains_pixel(39, 28, size));
+        assert!(!legacy.contains_pixel(39, 25, size));
+        assert!(!legacy.contains_pixel(35, 28, size));
+        assert!(!legacy.contains_pixel(31, 25, size));
+
+        // the left half, whatever size the image is
+        let rect = ImageRegion::Rectangle {
+            left: 0,
+            top: 0,
+            width: 5000,
+            height: 10000,
+        };
+        assert!(rect.contains_pixel(319, 479, size));
+        assert!(!rect.contains_pixel(320, 0, size));
+        assert!(rect.contains_pixel(49, 0, (100, 100)));
+        assert!(!rect.contains_pixel(0, 0, (0, 0)));
+    }
+}
+
+/// Number, that is compared and hashed by its bits, so questions can stay `Eq` and `Hash`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct Number(pub f64);
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Number {}
+
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+/// Lowercased, with whitespace collapsed and trailing punctuation dropped,
+/// so `The  Eiffel tower.` is the same answer as `the eiffel tower`
+pub fn normalize_answer(answer: &str) -> String {
+    answer
+        .trim()
+        .trim_end_matches(['.', ',', '!', '?', ';', ':'])
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// One of accepted answers of opened question
 #[derive(Debug, Serialize, Deserialize, Clone, Hash, PartialEq, Eq)]
-pub struct ImageRectangle {
-    pub left: u32,
-    pub top: u32,
-    pub width: u32,
-    pub height: u32,
+pub enum OpenedAnswer {
+    /// compared after [`normalize_answer`], up to `typos` edits (Levenshtein distance) are forgiven
+    Text { text: String, typos: u32 },
+    /// pattern, that whole answer has to match, case-insensitively
+    Regex(String),
+    /// answer is read as a number, that can differ by `tolerance`
+    Number { value: Number, tolerance: Number },
+}
+
+impl OpenedAnswer {
+    /// None if pattern is not a valid regex
+    pub fn build_regex(pattern: &str) -> Option<Regex> {
+        RegexBuilder::new(&format!("^(?:{pattern})$"))
+            .case_insensitive(true)
+            .build()
+            .ok()
+    }
+
+    pub fn matches(&self, answer: &str) -> bool {
+        match self {
+            OpenedAnswer::Text { text, typos } => {
+                let distance =
+                    strsim::levenshtein(&normalize_answer(text), &normalize_answer(answer));
+                distance <= *typos as usize
+            }
+            OpenedAnswer::Regex(pattern) => {
+                Self::build_regex(pattern).is_some_and(|regex| regex.is_match(answer.trim()))
+            }
+            OpenedAnswer::Number { value, tolerance } => normalize_answer(answer)
+                .replace(',', ".")
+                .parse::<f64>()
+                // decimal fractions are not exact in binary, so 9.82 may be a bit more than 0.01 from 9.81
+                .is_ok_and(|answer| (answer - value.0).abs() <= tolerance.0 + 1e-9),
+        }
+    }
 }
 
-impl ImageRectangle {
-    fn contains(&self, left: u32, top: u32) -> bool {
-        let Some(dx) = left.checked_sub(self.left) else {
-            return false;
+#[cfg(test)]
+mod opened_answer_tests {
+    use super::{Number, OpenedAnswer};
+
+    #[test]
+    fn text() {
+        let exact = OpenedAnswer::Text {
+            text: "Eiffel tower".to_string(),
+            typos: 0,
         };
-        let Some(dy) = top.checked_sub(self.top) else {
-            return false;
+        assert!(exact.matches("eiffel tower"));
+        assert!(exact.matches("  Eiffel   Tower. "));
+        assert!(!exact.matches("Eifel tower"));
+
+        let typos = OpenedAnswer::Text {
+            text: "Eiffel tower".to_string(),
+            typos: 1,
         };
+        assert!(typos.matches("Eifel tower"));
+        assert!(!typos.matches("Eifel towr"));
+    }
+
+    #[test]
+    fn regex() {
+        let regex = OpenedAnswer::Regex("(the )?eiffel tower".to_string());
+        assert!(regex.matches("The Eiffel Tower"));
+        assert!(regex.matches(" eiffel tower "));
+        assert!(!regex.matches("eiffel tower in Paris"));
+        assert!(!OpenedAnswer::Regex("(".to_string()).matches("("));
+    }
 
-        dx <= self.width && dy <= self.height
+    #[test]
+    fn number() {
+        let number = OpenedAnswer::Number {
+            value: Number(9.81),
+            tolerance: Number(0.01),
+        };
+        assert!(number.matches("9.81"));
+        assert!(number.matches("9,82"));
+        assert!(number.matches("9.80."));
+        assert!(!number.matches("9.9"));
+        assert!(!number.matches("pi"));
     }
 }
 
@@ -170,7 +747,8 @@ impl ImageRectangle {
 /// (parsed from source)
 #[derive(Debug, Serialize, Deserialize, Clone, Hash, PartialEq, Eq)] // (for DB)
 pub enum Question {
-    Opened(String),
+    /// answer matching any of these is correct
+    Opened(Box<[OpenedAnswer]>),
     Choice {
         variants: Box<[String]>,
         correct: u32,
@@ -181,13 +759,70 @@ pub enum Question {
     },
     Image {
         src: String,
-        correct_bounds: ImageRectangle,
+        /// answer in any of them is correct
+        regions: Box<[ImageRegion]>,
+    },
+    /// items in the correct order
+    Ordering { items: Box<[String]> },
+    /// left and right items, that belong together
+    Matching { pairs: Box<[(String, String)]> },
+    /// text with blanks in between, `text` has one more part than `blanks`
+    Cloze {
+        text: Box<[String]>,
+        /// accepted answers of each blank, compared after [`normalize_answer`]
+        blanks: Box<[Box<[String]>]>,
     },
 }
 
-impl From<&Question> for AskQuestion {
-    fn from(value: &Question) -> Self {
-        match value {
+/// Secret of a participation, that shuffles items of ordering and matching questions.
+///
+/// Kept on the server and never sent to the player, so shown order can not be traced back
+/// to the source one, which is the answer.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, From, Into)]
+pub struct ShuffleSeed(pub u64);
+
+/// Order, in which items of ordering or matching question are shown to the participation with
+/// `seed`: `i`-th shown item is `order[i]`-th one in the source. Differs from the source order,
+/// if it can
+pub fn shown_order<T: AsRef<str>>(seed: ShuffleSeed, items: &[T]) -> Box<[u32]> {
+    // FNV-1a of the items, stable between builds, so pages shown before an update are graded right,
+    // and questions of the same participation are shuffled differently
+    let mut state = seed.0;
+    for byte in items
+        .iter()
+        .flat_map(|item| item.as_ref().bytes().chain([0]))
+    {
+        state = (state ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+    }
+
+    // Fisher-Yates shuffle with splitmix64, each swap depends on every bit of the seed;
+    // source order is shuffled again, so every other order is equally likely
+    let mut order = (0..items.len() as u32).collect::<Vec<_>>();
+    loop {
+        for i in (1..order.len()).rev() {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut random = state;
+            random = (random ^ (random >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            random = (random ^ (random >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            random ^= random >> 31;
+            order.swap(i, (random % (i as u64 + 1)) as usize);
+        }
+        if order.len() < 2
+            || order
+                .iter()
+                .zip(0..)
+                .any(|(&shown, source)| shown != source)
+        {
+            break;
+        }
+    }
+    order.into_boxed_slice()
+}
+
+impl AskQuestion {
+    /// Question as it is shown to the participation with `seed`, without correct answers
+    pub fn new(question: &Question, seed: ShuffleSeed) -> Self {
+        match question {
             Question::Opened(_) => AskQuestion::Opened,
             Question::Choice {
                 variants,
@@ -201,10 +836,24 @@ impl From<&Question> for AskQuestion {
             } => AskQuestion::MultipleChoice {
                 variants: variants.clone(),
             },
-            Question::Image {
-                src,
-                correct_bounds: _,
-            } => AskQuestion::Image { src: src.clone() },
+            Question::Image { src, regions: _ } => AskQuestion::Image { src: src.clone() },
+            Question::Ordering { items } => AskQuestion::Ordering {
+                items: shown_order(seed, items)
+                    .iter()
+                    .map(|&i| items[i as usize].clone())
+                    .collect(),
+            },
+            Question::Matching { pairs } => {
+                let right = pairs.iter().map(|(_, right)| right).collect::<Vec<_>>();
+                AskQuestion::Matching {
+                    left: pairs.iter().map(|(left, _)| left.clone()).collect(),
+                    right: shown_order(seed, &right)
+                        .iter()
+                        .map(|&i| right[i as usize].clone())
+                        .collect(),
+                }
+            }
+            Question::Cloze { text, blanks: _ } => AskQuestion::Cloze { text: text.clone() },
         }
     }
 }
@@ -213,9 +862,65 @@ impl From<&Question> for AskQuestion {
 #[derive(Debug, Serialize, Deserialize, Clone, Hash, PartialEq, Eq)]
 pub enum AskQuestion {
     Opened,
-    Choice { variants: Box<[String]> },
-    MultipleChoice { variants: Box<[String]> },
-    Image { src: String },
+    Choice {
+        variants: Box<[String]>,
+    },
+    MultipleChoice {
+        variants: Box<[String]>,
+    },
+    Image {
+        src: String,
+    },
+    /// items are shuffled
+    Ordering {
+        items: Box<[String]>,
+    },
+    /// right items are shuffled
+    Matching {
+        left: Box<[String]>,
+        right: Box<[String]>,
+    },
+    /// blanks go between parts of the text
+    Cloze {
+        text: Box<[String]>,
+    },
+}
+
+/// Most points or penalty of a question, so scores of a quest can be summed up safely
+pub const MAX_POINTS: u32 = 1000;
+
+/// Points of a question, declared as `<question points=2 penalty=1 partial>`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Scoring {
+    /// awarded for correct answer, 1 by default, up to [`MAX_POINTS`] when parsed
+    pub points: u32,
+    /// taken for wrong answer, 0 by default, up to [`MAX_POINTS`] when parsed
+    pub penalty: u32,
+    /// answer that is partially right gets a part of points, for multiple choice, ordering,
+    /// matching and blanks
+    pub partial: bool,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Self {
+            points: 1,
+            penalty: 0,
+            partial: false,
+        }
+    }
+}
+
+/// Checked answer
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Grade {
+    pub correct: bool,
+    /// negative, when penalty is taken
+    pub score: i32,
+}
+
+fn image_scale() -> u32 {
+    IMAGE_SCALE
 }
 
 /// server <- client
@@ -224,25 +929,81 @@ pub enum Answer {
     Opened(String),
     Choice(u32),
     MultipleChoice(Box<[u32]>),
-    Image { left: u32, top: u32 },
+    /// point on the image, in pixels of the image at its natural size `width` x `height`
+    Image {
+        left: u32,
+        top: u32,
+        /// answers saved before the size was sent are in [`IMAGE_SCALE`] units
+        #[serde(default = "image_scale")]
+        width: u32,
+        #[serde(default = "image_scale")]
+        height: u32,
+    },
+    /// indices of shown items, in the order player put them
+    Ordering(Box<[u32]>),
+    /// for each left item, index of shown right item it is connected to
+    Matching(Box<[u32]>),
+    /// text put in each blank
+    Cloze(Box<[String]>),
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Hash, PartialEq, Eq)]
 pub enum QuestPageElement {
     Text(Box<str>),
-    Question(Question),
+    Question(Question, Scoring),
 }
 
 pub type QuestPage = Box<[QuestPageElement]>;
 
+/// Version of [`QuestPage`] serialization, saved along with parsed pages. Increased on every change,
+/// after which saved pages deserialize differently or not at all, so they are parsed again
+pub const QUEST_PAGE_FORMAT: u32 = 1;
+
 #[derive(Debug, Serialize, Deserialize, Clone, Hash, PartialEq, Eq)]
 pub enum AskQuestPageElement {
     Text(Box<str>),
-    Question(AskQuestion),
+    /// scoring is shown, so player knows what the question is worth
+    Question(AskQuestion, Scoring),
 }
 
 pub type AskQuestPage = Box<[AskQuestPageElement]>;
 
+impl AskQuestPageElement {
+    pub fn new(element: &QuestPageElement, seed: ShuffleSeed) -> Self {
+        match element {
+            QuestPageElement::Text(text) => AskQuestPageElement::Text(text.clone()),
+            QuestPageElement::Question(question, scoring) => {
+                AskQuestPageElement::Question(AskQuestion::new(question, seed), *scoring)
+            }
+        }
+    }
+}
+
+/// Strips correct answers from the page, so it can be sent to the player of the participation
+/// with `seed`
+pub fn ask_quest_page(page: &[QuestPageElement], seed: ShuffleSeed) -> AskQuestPage {
+    page.iter()
+        .map(|element| AskQuestPageElement::new(element, seed))
+        .collect()
+}
+
+/// Questions of the page, in the order answers are expected to be submitted
+pub fn page_questions(page: &[QuestPageElement]) -> impl Iterator<Item = (&Question, &Scoring)> {
+    page.iter().filter_map(|element| match element {
+        QuestPageElement::Question(question, scoring) => Some((question, scoring)),
+        QuestPageElement::Text(_) => None,
+    })
+}
+
+/// Score of the page, if every question is answered correctly, saturates at `u32::MAX`
+pub fn page_max_score(page: &[QuestPageElement]) -> u32 {
+    page_questions(page).fold(0, |score, (_, scoring)| {
+        score.saturating_add(scoring.points)
+    })
+}
+
+/// Routes with their bodies are listed in [`endpoints`], notes on them are below
+///
 /// POST /api/quests/create
 /// - returns [`QuestId`]
 /// - title, desc, source, etc. are empty
@@ -254,13 +1015,31 @@ pub type AskQuestPage = Box<[AskQuestPageElement]>;
 /// - accepts [`QuestInfo`]
 ///
 /// GET /api/quests/:id/page/:page
-/// - return [`String`] (source) //possibly return also time limit
+/// - return [`QuestPageSource`] (source and time limit)
 ///
 /// POST /api/quests/:id/page/:page
-/// - accepts [`String`] (source), [`Option<Duration>`] (time limit for page)
-/// - server saves source (and [`Vec<Question>`], after quest is published)
+/// - accepts [`QuestPageSource`]
+/// - server saves source and time limit
 /// - check for OK
 ///
+/// POST /api/quests/:id/publish
+/// - every page is parsed, quest is refused with [`PublishError`] if it can not be played
+/// - server saves parsed [`QuestPage`]s, quest can not be edited after that
+///
+/// GET /api/admin/users/:page
+/// - returns [`AdminUsersPage`], admins only
+///
+/// POST /api/admin/users/:id/role
+/// - accepts [`UserRole`], only users and moderators can be promoted or demoted
+///
+/// POST /api/admin/users/:id/ban, /api/admin/users/:id/unban
+/// - banned user is logged out everywhere and can not log in
+///
+/// POST /api/quests/:id/submit, /return, /approve, /lock
+/// - changes [`QuestState`] according to [`QuestAction`], returns new [`QuestState`]
+/// - return, approve and lock are available to moderators only
+/// - approving a submitted quest publishes it, same as above
+///
 /// // below is not final
 /// POST /api/quests/:id/answer/:page
 /// - accepts [`Vec<Answer>`]
@@ -275,15 +1054,32 @@ pub type AskQuestPage = Box<[AskQuestPageElement]>;
 /// POST /api/quests/qid/page/0 "lalalal, question ;)" -- updates page 0
 /// POST /api/quests/qid/info "Title; description" -- update title/description
 /// GET /api/quests/qid/info -- returns [`QuestInfo`] with pages=2
+#[allow(unused_imports)]
 mod doc {
     use super::*;
 }
 
-// websocket is opened to POST /api/ws/quest/:id
+// websocket is opened to GET /api/ws/quest/:id
 //
 // ~ flow ~
 //
 // *opened connection* (new / continue)
+// -> RequestPage(completed_pages)
+// <- ResponsePage(Ok(page))
+// -> RequestSubmit(completed_pages, answers)
+// <- ResponseSubmit(Ok(correctness of each answer))
+// ... repeat for every page; after the last page is submitted, quest is finished
+//
+// ~ time limits ~
+//
+// time of a page with limit starts, when it is sent for the first time (reconnect doesn't reset it)
+// <- TimeLeft(page, remaining) is pushed every second, until page is submitted
+// <- PageTimeout(page) once time is up, page is submitted without answers
+// submit of the page after that is refused, next page has to be requested
+//
+// quest with time limit or closing time works the same way, but for the whole quest:
+// <- QuestTimeLeft(remaining) is pushed every second
+// <- QuestTimeout once time is up, quest is finished and connection is closed
 //
 
 #[derive(Debug, Serialize, Deserialize, Clone, Hash, PartialEq, Eq)]
@@ -291,11 +1087,19 @@ pub enum WsServerMessage {
     // ok - quest page to answer
     // err - only pages up to err are available
     ResponsePage(Result<AskQuestPage, u32>),
-    // ok - answers were submitted
-    // err - only pages up to err are available
-    ResponseSubmit(Result<(), String>),
+    // ok - answers were submitted, contains correctness of each answer
+    // err - answers were not accepted, contains reason
+    ResponseSubmit(Result<Box<[bool]>, String>),
     // inform client about bail
     RequestBail,
+    // pushed while page with time limit is answered, contains remaining time
+    TimeLeft(u32, Duration),
+    // time of the page is up, it was submitted without answers
+    PageTimeout(u32),
+    // pushed while quest with time limit or closing time is played, contains remaining time
+    QuestTimeLeft(Duration),
+    // time of the quest is up, it is finished with pages submitted so far
+    QuestTimeout,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Hash, PartialEq, Eq)]
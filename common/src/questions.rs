@@ -1,8 +1,11 @@
-use std::{borrow::Borrow, collections::HashMap};
+use std::{borrow::Borrow, collections::BTreeSet};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{Answer, ImageRectangle, QuestPage, QuestPageElement, Question};
+use crate::{
+    check_quest_page, normalize_answer, shown_order, Answer, Grade, QuestPage, Question, Scoring,
+    ShuffleSeed, MAX_POINTS,
+};
 
 #[derive(
     Debug,
@@ -26,181 +29,263 @@ pub enum PageParseError {
     BadChoiceFormat,
     #[error("choice question defines identical choices")]
     IdenticalChoices,
-    #[error("choice question has multiple correct answers")]
+    #[error(
+        "choice question has multiple correct answers, use [x] and [ ] lines for multiple choice"
+    )]
     MultipleCorrect,
+    #[error("multiple choice question variant lines must start with [x] or [ ]")]
+    BadMultipleChoiceFormat,
     #[error("choice question has no correct answer")]
     NoCorrectChoice,
-    #[error("opened question must contain three lines: <opened>, correct answer, and </opened>")]
+    #[error("opened question must contain <opened>, accepted answers one per line, and </opened>")]
     BadOpenedFormat,
+    #[error("opened question number must look like `= 3.14` or `= 3.14 +- 0.01`")]
+    BadOpenedNumber,
+    #[error("opened question regex between slashes is not valid")]
+    BadOpenedRegex,
     #[error(
-        "image question must contain 5 lines: <img /> tag, left offset, top offset, width, height"
+        "image question must contain <img /> tag and regions: `rect LEFT TOP WIDTH HEIGHT`, \
+         `circle X Y RADIUS` or `polygon X1 Y1 X2 Y2 X3 Y3 ...`, in percents of image size, \
+         or `rect LEFTpx TOPpx WIDTHpx HEIGHTpx` in pixels"
     )]
     BadImageFormat,
+    #[error(
+        "ordering question must contain <order>, at least two items one per line, and </order>"
+    )]
+    BadOrderingFormat,
+    #[error(
+        "matching question must contain <match>, at least two `left = right` lines, and </match>"
+    )]
+    BadMatchingFormat,
+    #[error("blank must look like {{{{answer}}}} or {{{{answer|another answer}}}}")]
+    BadBlankFormat,
     #[error("Failed to recognize question type")]
     UnknownQuestionType,
+    #[error("<question> tag attributes must be points=N, penalty=N or partial")]
+    BadQuestionAttributes,
+    #[error("points and penalty of a question can not be more than {}", MAX_POINTS)]
+    TooManyPoints,
 }
 
-fn parse_question<'l>(
-    source: impl IntoIterator<Item = &'l str>,
-) -> Result<Question, PageParseError> {
-    let mut lines = source.into_iter();
-    let Some(first_line) = lines.next() else {
-        return Err(PageParseError::EmptyQuestionTag);
-    };
-    if first_line.starts_with(['+', '-']) {
-        let mut variants_map = HashMap::<String, u32>::new();
-        let mut correct = None::<u32>;
-
-        if first_line.starts_with('+') {
-            correct = Some(0);
-        }
-
-        #[allow(clippy::items_after_statements)]
-        fn parse_choice(s: &str) -> Option<&str> {
-            let trimmed = s.strip_prefix(['+', '-'])?.trim();
-            if trimmed.is_empty() {
-                return None;
-            }
-            Some(trimmed)
-        }
-
-        variants_map.insert(
-            parse_choice(first_line)
-                .ok_or(PageParseError::BadChoiceFormat)?
-                .to_owned(),
-            0,
-        );
-
-        for (line, no) in lines.zip(1u32..) {
-            if !line.starts_with(['+', '-']) {
-                return Err(PageParseError::BadChoiceFormat);
-            }
+/// Suspicious, but valid page source
+#[derive(
+    Debug,
+    thiserror::Error,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum PageWarning {
+    #[error("text has only whitespace, it is not shown")]
+    EmptyText,
+    #[error("image is not a part of any question, put it inside <question> to ask about it")]
+    UnusedImage,
+    #[error("closing tag has no opening one, it is shown as text")]
+    StrayClosingTag,
+}
 
-            if line.starts_with('+') {
-                if correct.is_some() {
-                    return Err(PageParseError::MultipleCorrect);
-                }
-                correct = Some(no);
-            }
+/// Place in page source, lines and columns are counted from 1 in characters, end is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct SourceSpan {
+    pub line: u32,
+    pub column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+}
 
-            let variant = parse_choice(line).ok_or(PageParseError::NoCorrectChoice)?;
-            if variants_map.contains_key(variant) {
-                return Err(PageParseError::IdenticalChoices);
-            }
-            variants_map.insert(variant.to_owned(), no);
+impl SourceSpan {
+    /// Line with number `no` counted from 0, without surrounding whitespace
+    pub(crate) fn line(no: usize, line: &str) -> Self {
+        let start = line.chars().take_while(|c| c.is_whitespace()).count();
+        let end = line.trim_end().chars().count().max(start);
+        Self {
+            line: no as u32 + 1,
+            column: start as u32 + 1,
+            end_line: no as u32 + 1,
+            end_column: end as u32 + 1,
         }
+    }
 
-        let correct = correct.ok_or(PageParseError::NoCorrectChoice)?;
-
-        let mut variants = vec![String::new(); variants_map.len()].into_boxed_slice();
-        for (variant, no) in variants_map {
-            variants[no as usize] = variant;
+    /// From start of this span to the end of the other one
+    pub(crate) fn to(self, end: Self) -> Self {
+        Self {
+            end_line: end.end_line,
+            end_column: end.end_column,
+            ..self
         }
-        return Ok(Question::Choice { variants, correct });
     }
+}
 
-    if first_line.trim() == "<opened>" {
-        let Some(correct) = lines.next() else {
-            return Err(PageParseError::BadOpenedFormat);
-        };
-
-        let Some(opened_end) = lines.next() else {
-            return Err(PageParseError::BadOpenedFormat);
-        };
-        if opened_end.trim() != "</opened>" {
-            return Err(PageParseError::BadOpenedFormat);
-        }
+impl std::fmt::Display for SourceSpan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
 
-        if lines.next().is_some() {
-            return Err(PageParseError::BadOpenedFormat);
-        }
+/// Error or warning, pointing at the page source
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[error("{span}: {kind}")]
+pub struct Diagnostic<K> {
+    pub kind: K,
+    pub span: SourceSpan,
+    /// question it is about, counted from 0 among questions of the page
+    pub question: Option<u32>,
+}
 
-        return Ok(Question::Opened(correct.trim().to_owned()));
-    }
+pub type SourceError = Diagnostic<PageParseError>;
+pub type SourceWarning = Diagnostic<PageWarning>;
 
-    if let Some(url) = first_line
-        .trim()
-        .strip_prefix("<img")
-        .and_then(|s| s.trim_start().strip_prefix("src=\""))
-        .and_then(|s| s.strip_suffix("/>"))
-        .and_then(|s| s.trim_end().strip_suffix("\""))
-    {
-        fn parse_starting_u32(mut s: &str) -> Option<u32> {
-            if let Some((stripped_end, _)) = s.split_once(' ') {
-                s = stripped_end;
-            }
-            s.parse::<u32>().ok()
-        }
-        let Some(left) = lines.next().and_then(parse_starting_u32) else {
-            return Err(PageParseError::BadImageFormat);
-        };
-        let Some(top) = lines.next().and_then(parse_starting_u32) else {
-            return Err(PageParseError::BadImageFormat);
-        };
-        let Some(width) = lines.next().and_then(parse_starting_u32) else {
-            return Err(PageParseError::BadImageFormat);
-        };
-        let Some(height) = lines.next().and_then(parse_starting_u32) else {
-            return Err(PageParseError::BadImageFormat);
-        };
+/// Page source checked for the editor
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PageCheck {
+    pub page: Result<QuestPage, Box<[SourceError]>>,
+    pub warnings: Box<[SourceWarning]>,
+    /// where every question is in the source, broken ones included, in the order of question indices
+    pub questions: Box<[SourceSpan]>,
+}
 
-        return Ok(Question::Image {
-            src: url.to_owned(),
-            correct_bounds: ImageRectangle {
-                left,
-                top,
-                width,
-                height,
-            },
-        });
-    }
+/// Parsed page, or all errors found in it
+pub fn parse_quest_page(source: impl Borrow<str>) -> Result<QuestPage, Box<[SourceError]>> {
+    check_quest_page(source).page
+}
 
-    Err(PageParseError::UnknownQuestionType)
+/// Page that failed to parse, reported while publishing a quest
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PageError {
+    pub page: u32,
+    pub errors: Box<[SourceError]>,
 }
 
-pub fn parse_quest_page(source: impl Borrow<str>) -> Result<QuestPage, PageParseError> {
-    let mut res = Vec::<QuestPageElement>::new();
-    let mut question_lines = Vec::<&str>::new();
-    let mut text = String::new();
-
-    let mut lines = <_ as Borrow<str>>::borrow(&source).lines();
-    while let Some(line) = lines.next() {
-        if line.trim() == "<question>" {
-            if !text.is_empty() {
-                res.push(QuestPageElement::Text(text.clone().into_boxed_str()));
-                text.clear();
-            }
-            question_lines.clear();
-            loop {
-                let Some(line) = lines.next() else {
-                    return Err(PageParseError::UnclosedQuestionTag);
-                };
-                if line.trim() == "</question>" {
-                    let question = parse_question(question_lines.drain(..))?;
-                    res.push(QuestPageElement::Question(question));
-                    break;
-                }
-                question_lines.push(line);
-            }
-        } else {
-            if !text.is_empty() {
-                text.push('\n');
-            }
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PublishError {
+    #[error("quest must have at least one page")]
+    NoPages,
+    #[error("quest must have at least one question")]
+    NoQuestions,
+    #[error("some pages failed to parse")]
+    BadPages(Box<[PageError]>),
+    #[error("quest is worth too many points for its scores to be counted")]
+    TooManyPoints,
+}
 
-            text.push_str(line);
+/// Parses all pages of the quest, checking it can be played.
+///
+/// Reports all broken pages at once.
+pub fn parse_quest_pages<S: Borrow<str>>(
+    sources: impl IntoIterator<Item = S>,
+) -> Result<Box<[QuestPage]>, PublishError> {
+    let mut pages = Vec::new();
+    let mut errors = Vec::new();
+    for (source, page) in sources.into_iter().zip(0u32..) {
+        match parse_quest_page(source) {
+            Ok(parsed) => pages.push(parsed),
+            Err(page_errors) => errors.push(PageError {
+                page,
+                errors: page_errors,
+            }),
         }
     }
-    if !text.is_empty() {
-        res.push(QuestPageElement::Text(text.into_boxed_str()));
+
+    if !errors.is_empty() {
+        return Err(PublishError::BadPages(errors.into_boxed_slice()));
+    }
+    if pages.is_empty() {
+        return Err(PublishError::NoPages);
+    }
+    if pages
+        .iter()
+        .all(|page| crate::page_questions(page).next().is_none())
+    {
+        return Err(PublishError::NoQuestions);
+    }
+    // any score of the player, from every penalty taken to every point awarded, is stored as i32
+    let fits = |worth: fn(&Scoring) -> u32| {
+        pages
+            .iter()
+            .flat_map(|page| crate::page_questions(page))
+            .try_fold(0i32, |sum, (_, scoring)| {
+                sum.checked_add(i32::try_from(worth(scoring)).ok()?)
+            })
+            .is_some()
+    };
+    if !fits(|scoring| scoring.points) || !fits(|scoring| scoring.penalty) {
+        return Err(PublishError::TooManyPoints);
     }
 
-    Ok(res.into_boxed_slice())
+    Ok(pages.into_boxed_slice())
 }
 
 #[cfg(test)]
 mod parse_tests {
-    use super::parse_question;
-    use crate::Question;
+    use crate::format::parse_question;
+    use crate::{Number, OpenedAnswer, PageParseError, Question};
+
+    #[test]
+    fn quest_pages_parse() {
+        use super::parse_quest_pages;
+        use crate::{Diagnostic, PageError, PageParseError, PublishError, SourceSpan};
+
+        assert_eq!(
+            parse_quest_pages(Vec::<&str>::new()),
+            Err(PublishError::NoPages)
+        );
+
+        assert_eq!(
+            parse_quest_pages(["just text", "more text"]),
+            Err(PublishError::NoQuestions)
+        );
+
+        assert_eq!(
+            parse_quest_pages([
+                "<question>\n+ a\n</question>",
+                "<question>\n- a\n</question>",
+                "<question>\n+ a\n",
+            ]),
+            Err(PublishError::BadPages(
+                vec![
+                    PageError {
+                        page: 1,
+                        errors: Box::new([Diagnostic {
+                            kind: PageParseError::NoCorrectChoice,
+                            span: SourceSpan {
+                                line: 1,
+                                column: 1,
+                                end_line: 3,
+                                end_column: 12
+                            },
+                            question: Some(0),
+                        }]),
+                    },
+                    PageError {
+                        page: 2,
+                        errors: Box::new([Diagnostic {
+                            kind: PageParseError::UnclosedQuestionTag,
+                            span: SourceSpan {
+                                line: 1,
+                                column: 1,
+                                end_line: 1,
+                                end_column: 11
+                            },
+                            question: Some(0),
+                        }]),
+                    },
+                ]
+                .into_boxed_slice()
+            ))
+        );
+
+        assert_eq!(
+            parse_quest_pages(["text", "<question>\n+ a\n- b\n</question>"])
+                .map(|pages| pages.len()),
+            Ok(2)
+        );
+    }
 
     #[test]
     fn question_parse() {
@@ -229,6 +314,73 @@ mod parse_tests {
             Err(crate::PageParseError::MultipleCorrect)
         );
 
+        assert_eq!(
+            parse_question(["[x] a", "[ ]  b ", "[X] c"]),
+            Ok(Question::MultipleChoice {
+                variants: ["a", "b", "c"].map(String::from).into(),
+                correct: [0, 2].into(),
+            })
+        );
+
+        assert_eq!(
+            parse_question(["<order>", "a", "", " b ", "</order>"]),
+            Ok(Question::Ordering {
+                items: ["a", "b"].map(String::from).into(),
+            })
+        );
+        for lines in [
+            &["<order>", "a", "</order>"][..],
+            &["<order>", "a", "b"],
+            &["<order>", "a", "b", "</order>", "c"],
+        ] {
+            assert_eq!(
+                parse_question(lines.iter().copied()),
+                Err(crate::PageParseError::BadOrderingFormat)
+            );
+        }
+        assert_eq!(
+            parse_question(["<order>", "a", "a", "</order>"]),
+            Err(crate::PageParseError::IdenticalChoices)
+        );
+
+        assert_eq!(
+            parse_question(["<match>", "a = 1", "b=2", "</match>"]),
+            Ok(Question::Matching {
+                pairs: [("a", "1"), ("b", "2")]
+                    .map(|(l, r)| (l.to_string(), r.to_string()))
+                    .into(),
+            })
+        );
+        for lines in [
+            &["<match>", "a = 1", "</match>"][..],
+            &["<match>", "a = 1", "b", "</match>"],
+            &["<match>", "a = 1", "b = ", "</match>"],
+        ] {
+            assert_eq!(
+                parse_question(lines.iter().copied()),
+                Err(crate::PageParseError::BadMatchingFormat)
+            );
+        }
+        assert_eq!(
+            parse_question(["<match>", "a = 1", "b = 1", "</match>"]),
+            Err(crate::PageParseError::IdenticalChoices)
+        );
+
+        assert_eq!(
+            parse_question(["[ ] a", "[ ] b"]),
+            Err(crate::PageParseError::NoCorrectChoice)
+        );
+
+        assert_eq!(
+            parse_question(["[x] a", "+ b"]),
+            Err(crate::PageParseError::BadMultipleChoiceFormat)
+        );
+
+        assert_eq!(
+            parse_question(["[x] a", "[ ] a"]),
+            Err(crate::PageParseError::IdenticalChoices)
+        );
+
         assert_eq!(
             parse_question(["+   a", "- b  "]),
             Ok(Question::Choice {
@@ -237,6 +389,26 @@ mod parse_tests {
             })
         );
 
+        // empty lines are skipped in every question
+        assert_eq!(
+            parse_question(["", "+ a", "  ", "", "- b", ""]),
+            Ok(Question::Choice {
+                variants: vec!["a".to_string(), "b".to_string()].into_boxed_slice(),
+                correct: 0,
+            })
+        );
+        assert_eq!(
+            parse_question(["[ ] a", "", "[x] b"]),
+            Ok(Question::MultipleChoice {
+                variants: ["a", "b"].map(String::from).into(),
+                correct: [1].into(),
+            })
+        );
+        assert_eq!(
+            parse_question(["", " "]),
+            Err(PageParseError::EmptyQuestionTag)
+        );
+
         assert_eq!(
             parse_question(["-a  ", "+  b  "]),
             Ok(Question::Choice {
@@ -247,7 +419,63 @@ mod parse_tests {
 
         assert_eq!(
             parse_question([" <opened>   ", " (correct answer) ", " </opened>    "]),
-            Ok(Question::Opened("(correct answer)".to_string()))
+            Ok(Question::Opened(Box::new([OpenedAnswer::Text {
+                text: "(correct answer)".to_string(),
+                typos: 0
+            }])))
+        );
+
+        assert_eq!(
+            parse_question([
+                "<opened>",
+                "Kyiv",
+                "~1 Kiev",
+                "\\= not a number",
+                "/ky[iy]v/",
+                "= 9.81 +- 0.01",
+                "= 42",
+                "</opened>"
+            ]),
+            Ok(Question::Opened(Box::new([
+                OpenedAnswer::Text {
+                    text: "Kyiv".to_string(),
+                    typos: 0
+                },
+                OpenedAnswer::Text {
+                    text: "Kiev".to_string(),
+                    typos: 1
+                },
+                OpenedAnswer::Text {
+                    text: "= not a number".to_string(),
+                    typos: 0
+                },
+                OpenedAnswer::Regex("ky[iy]v".to_string()),
+                OpenedAnswer::Number {
+                    value: Number(9.81),
+                    tolerance: Number(0.01)
+                },
+                OpenedAnswer::Number {
+                    value: Number(42.0),
+                    tolerance: Number(0.0)
+                },
+            ])))
+        );
+
+        assert_eq!(
+            parse_question(["<opened>", "/(/", "</opened>"]),
+            Err(PageParseError::BadOpenedRegex)
+        );
+        assert_eq!(
+            parse_question(["<opened>", "= three", "</opened>"]),
+            Err(PageParseError::BadOpenedNumber)
+        );
+        assert_eq!(
+            parse_question(["<opened>", "</opened>"]),
+            Err(PageParseError::BadOpenedFormat)
+        );
+        assert_eq!(
+            parse_question(["<opened>", "answer"]),
+            Err(PageParseError::BadOpenedFormat)
         );
 
         assert_eq!(
@@ -260,14 +488,62 @@ mod parse_tests {
             ]),
             Ok(Question::Image {
                 src: "some sort of url".to_string(),
-                correct_bounds: crate::ImageRectangle {
+                regions: Box::new([crate::ImageRegion::PixelRectangle {
                     left: 32,
                     top: 23,
                     width: 7,
                     height: 5
-                }
+                }])
             })
         );
+
+        assert_eq!(
+            parse_question([
+                "<img src=\"url\" />",
+                "rect 10 20.5 5% 5 -- the door",
+                "",
+                "circle 50, 50, 12.25",
+                "polygon 0 0, 10 0, 0 10",
+                "rect 320px 230px 70px 50px",
+            ]),
+            Ok(Question::Image {
+                src: "url".to_string(),
+                regions: Box::new([
+                    crate::ImageRegion::Rectangle {
+                        left: 1000,
+                        top: 2050,
+                        width: 500,
+                        height: 500
+                    },
+                    crate::ImageRegion::Circle {
+                        x: 5000,
+                        y: 5000,
+                        radius: 1225
+                    },
+                    crate::ImageRegion::Polygon(Box::new([(0, 0), (1000, 0), (0, 1000)])),
+                    crate::ImageRegion::PixelRectangle {
+                        left: 320,
+                        top: 230,
+                        width: 70,
+                        height: 50
+                    },
+                ])
+            })
+        );
+
+        for regions in [
+            &[][..],
+            &["rect 10 10 10"],
+            &["circle 101 0 1"],
+            &["polygon 0 0 10 10"],
+            &["circle 1.234 0 1"],
+            &["square 0 0 1"],
+            &["rect 10px 10px 10 10"],
+            &["circle 10px 10px 10px"],
+        ] {
+            let lines = ["<img src=\"url\" />"].iter().chain(regions).copied();
+            assert_eq!(parse_question(lines), Err(PageParseError::BadImageFormat));
+        }
     }
 }
 
@@ -287,12 +563,86 @@ mod parse_tests {
 #[error("Wrong answer type provided")]
 pub struct WrongQuestionType;
 
+/// How many of answered shown items are put where they belong, None if answer has wrong length
+fn parts_in_place(order: &[u32], answered: &[u32]) -> Option<usize> {
+    if order.len() != answered.len() {
+        return None;
+    }
+    let in_place = answered
+        .iter()
+        .zip(0u32..)
+        .filter(|&(&shown, source)| order.get(shown as usize) == Some(&source))
+        .count();
+    Some(in_place)
+}
+
+/// How many blanks are filled right, None if answer has wrong length
+fn blanks_filled(blanks: &[Box<[String]>], answered: &[String]) -> Option<usize> {
+    if blanks.len() != answered.len() {
+        return None;
+    }
+    let filled = blanks
+        .iter()
+        .zip(answered)
+        .filter(|(accepted, answered)| {
+            let answered = normalize_answer(answered);
+            accepted
+                .iter()
+                .any(|accepted| normalize_answer(accepted) == answered)
+        })
+        .count();
+    Some(filled)
+}
+
+/// Right and wrong parts of an answer, that can be partially right, and how many parts are there
+fn answer_parts(
+    question: &Question,
+    seed: ShuffleSeed,
+    answer: &Answer,
+) -> Option<(usize, usize, usize)> {
+    match (question, answer) {
+        (Question::MultipleChoice { correct, .. }, Answer::MultipleChoice(answered)) => {
+            let answered = chosen(answered);
+            let right = answered.iter().filter(|a| correct.contains(a)).count();
+            Some((right, answered.len() - right, correct.len()))
+        }
+        (Question::Ordering { items }, Answer::Ordering(answered)) => Some((
+            parts_in_place(&shown_order(seed, items), answered)?,
+            0,
+            items.len(),
+        )),
+        (Question::Matching { pairs }, Answer::Matching(answered)) => {
+            let right = pairs.iter().map(|(_, right)| right).collect::<Vec<_>>();
+            Some((
+                parts_in_place(&shown_order(seed, &right), answered)?,
+                0,
+                pairs.len(),
+            ))
+        }
+        (Question::Cloze { text: _, blanks }, Answer::Cloze(answered)) => {
+            Some((blanks_filled(blanks, answered)?, 0, blanks.len()))
+        }
+        _ => None,
+    }
+}
+
+/// Chosen variants, regardless of order and repeats
+fn chosen(variants: &[u32]) -> BTreeSet<u32> {
+    variants.iter().copied().collect()
+}
+
+/// Checks the answer of the participation with `seed`, that shuffled ordering and matching items
 pub fn check_answer(
     quetion: impl Borrow<Question>,
+    seed: ShuffleSeed,
     answer: impl Borrow<Answer>,
 ) -> Result<bool, WrongQuestionType> {
     match (quetion.borrow(), answer.borrow()) {
-        (Question::Opened(correct), Answer::Opened(answered)) if correct == answered => Ok(true),
+        (Question::Opened(accepted), Answer::Opened(answered))
+            if accepted.iter().any(|accepted| accepted.matches(answered)) =>
+        {
+            Ok(true)
+        }
         (
             Question::Choice {
                 variants: _,
@@ -306,14 +656,31 @@ pub fn check_answer(
                 correct,
             },
             Answer::MultipleChoice(answered),
-        ) if correct == answered => Ok(true),
+        ) if chosen(correct) == chosen(answered) => Ok(true),
         (
-            Question::Image {
-                src: _,
-                correct_bounds,
+            Question::Image { src: _, regions },
+            &Answer::Image {
+                left,
+                top,
+                width,
+                height,
             },
-            &Answer::Image { left, top },
-        ) if correct_bounds.contains(left, top) => todo!(),
+        ) if regions
+            .iter()
+            .any(|region| region.contains_pixel(left, top, (width, height))) =>
+        {
+            Ok(true)
+        }
+        (Question::Ordering { items }, Answer::Ordering(answered)) => {
+            Ok(parts_in_place(&shown_order(seed, items), answered) == Some(items.len()))
+        }
+        (Question::Matching { pairs }, Answer::Matching(answered)) => {
+            let right = pairs.iter().map(|(_, right)| right).collect::<Vec<_>>();
+            Ok(parts_in_place(&shown_order(seed, &right), answered) == Some(pairs.len()))
+        }
+        (Question::Cloze { text: _, blanks }, Answer::Cloze(answered)) => {
+            Ok(blanks_filled(blanks, answered) == Some(blanks.len()))
+        }
         (Question::Opened(..), Answer::Opened(..))
         | (Question::Choice { .. }, Answer::Choice(..))
         | (Question::MultipleChoice { .. }, Answer::MultipleChoice(..))
@@ -321,3 +688,531 @@ pub fn check_answer(
         _ => Err(WrongQuestionType),
     }
 }
+
+/// Checks the answer and scores it according to [`Scoring`]
+pub fn grade_answer(
+    question: impl Borrow<Question>,
+    seed: ShuffleSeed,
+    scoring: &Scoring,
+    answer: impl Borrow<Answer>,
+) -> Result<Grade, WrongQuestionType> {
+    let (question, answer) = (question.borrow(), answer.borrow());
+    if check_answer(question, seed, answer)? {
+        return Ok(Grade {
+            correct: true,
+            score: i32::try_from(scoring.points).unwrap_or(i32::MAX),
+        });
+    }
+
+    // each right part earns its share of points, each wrong one takes it back
+    if let (true, Some((right, wrong, total))) =
+        (scoring.partial, answer_parts(question, seed, answer))
+    {
+        let share = (right as i64 - wrong as i64) * i64::from(scoring.points) / total.max(1) as i64;
+        if share > 0 {
+            return Ok(Grade {
+                correct: false,
+                score: i32::try_from(share).unwrap_or(i32::MAX),
+            });
+        }
+    }
+
+    Ok(Grade {
+        correct: false,
+        score: i32::try_from(scoring.penalty).map_or(-i32::MAX, |penalty| -penalty),
+    })
+}
+
+#[cfg(test)]
+mod grade_tests {
+    use super::{check_answer, grade_answer, parse_quest_page, WrongQuestionType};
+    use crate::format::parse_question;
+    use crate::{Answer, Grade, PageParseError, QuestPageElement, Question, Scoring, ShuffleSeed};
+
+    const SEED: ShuffleSeed = ShuffleSeed(0x5eed);
+
+    #[test]
+    fn question_attributes() {
+        let page = parse_quest_page("<question points=3 penalty=1 partial>\n+ a\n</question>");
+        let Ok(page) = page else {
+            panic!("page must parse: {page:?}");
+        };
+        assert!(matches!(
+            &page[..],
+            [QuestPageElement::Question(
+                Question::Choice { .. },
+                Scoring {
+                    points: 3,
+                    penalty: 1,
+                    partial: true,
+                }
+            )]
+        ));
+
+        assert!(matches!(
+            &parse_quest_page("<question>\n+ a\n</question>").unwrap()[..],
+            [QuestPageElement::Question(_, scoring)] if *scoring == Scoring::default()
+        ));
+
+        assert_eq!(
+            parse_quest_page("<question points=many>\n+ a\n</question>").unwrap_err()[0].kind,
+            PageParseError::BadQuestionAttributes
+        );
+        assert_eq!(
+            parse_quest_page("<question bonus>\n+ a\n</question>").unwrap_err()[0].kind,
+            PageParseError::BadQuestionAttributes
+        );
+        assert!(parse_quest_page("<question points=1000 penalty=1000>\n+ a\n</question>").is_ok());
+        for tag in [
+            "<question points=1001>",
+            "<question penalty=1001>",
+            "<question points=99999999999>",
+        ] {
+            let source = format!("{tag}\n+ a\n</question>");
+            assert_eq!(
+                parse_quest_page(source).unwrap_err()[0].kind,
+                PageParseError::TooManyPoints
+            );
+        }
+        // not a question tag at all
+        assert!(matches!(
+            &parse_quest_page("<questions>").unwrap()[..],
+            [QuestPageElement::Text(_)]
+        ));
+    }
+
+    #[test]
+    fn scores_saturate() {
+        // scoring built by hand is not capped like a parsed one
+        let scoring = Scoring {
+            points: u32::MAX,
+            penalty: u32::MAX,
+            partial: false,
+        };
+        let question = parse_question(["+ a", "- b"]).unwrap();
+        let grade = |answer| grade_answer(&question, SEED, &scoring, Answer::Choice(answer));
+        assert_eq!(grade(0).map(|grade| grade.score), Ok(i32::MAX));
+        assert_eq!(grade(1).map(|grade| grade.score), Ok(-i32::MAX));
+
+        let page = [
+            QuestPageElement::Question(question.clone(), scoring),
+            QuestPageElement::Question(question.clone(), scoring),
+        ];
+        assert_eq!(crate::page_max_score(&page), u32::MAX);
+    }
+
+    #[test]
+    fn grading() {
+        let choice = Question::Choice {
+            variants: vec!["a".to_string(), "b".to_string()].into_boxed_slice(),
+            correct: 1,
+        };
+        let scoring = Scoring {
+            points: 2,
+            penalty: 1,
+            partial: false,
+        };
+        assert_eq!(
+            grade_answer(&choice, SEED, &scoring, Answer::Choice(1)),
+            Ok(Grade {
+                correct: true,
+                score: 2
+            })
+        );
+        assert_eq!(
+            grade_answer(&choice, SEED, &scoring, Answer::Choice(0)),
+            Ok(Grade {
+                correct: false,
+                score: -1
+            })
+        );
+
+        let multiple = Question::MultipleChoice {
+            variants: ["a", "b", "c", "d"].map(String::from).into(),
+            correct: [0, 1].into(),
+        };
+        let partial = Scoring {
+            points: 4,
+            penalty: 1,
+            partial: true,
+        };
+        let grade = |answer: &[u32], scoring: &Scoring| {
+            grade_answer(
+                &multiple,
+                SEED,
+                scoring,
+                Answer::MultipleChoice(answer.into()),
+            )
+            .unwrap()
+            .score
+        };
+        assert_eq!(grade(&[0, 1], &partial), 4);
+        assert_eq!(grade(&[0], &partial), 2);
+        assert_eq!(grade(&[0, 2], &partial), -1);
+        assert_eq!(grade(&[2], &partial), -1);
+        assert_eq!(grade(&[0], &Scoring::default()), 0);
+        assert_eq!(grade(&[1, 0], &Scoring::default()), 1);
+        assert_eq!(grade(&[1, 0, 1], &Scoring::default()), 1);
+        assert_eq!(grade(&[0, 0], &partial), 2);
+    }
+
+    #[test]
+    fn image_answer() {
+        let question = parse_question([
+            "<img src=\"map.png\" />",
+            "rect 0 0 50 50",
+            "circle 75 75 10",
+        ])
+        .unwrap();
+        let check = |question: &Question, left, top| {
+            let answer = Answer::Image {
+                left,
+                top,
+                width: 200,
+                height: 100,
+            };
+            grade_answer(question, SEED, &Scoring::default(), answer)
+                .unwrap()
+                .correct
+        };
+        assert!(check(&question, 0, 0));
+        assert!(check(&question, 99, 49));
+        assert!(!check(&question, 100, 20));
+        assert!(check(&question, 150, 84));
+        assert!(!check(&question, 180, 90));
+
+        // legacy rectangle is in pixels, far edges are outside
+        let legacy = parse_question(["<img src=\"map.png\" />", "32", "23", "7", "5"]).unwrap();
+        assert!(check(&legacy, 32, 23));
+        assert!(check(&legacy, 38, 27));
+        assert!(!check(&legacy, 39, 28));
+        assert!(!check(&legacy, 40, 28));
+
+        // answers saved without image size are in image scale units
+        let saved =
+            serde_json::from_str::<Answer>(r#"{"Image":{"left":4999,"top":4999}}"#).unwrap();
+        assert_eq!(
+            grade_answer(&question, SEED, &Scoring::default(), saved).map(|grade| grade.correct),
+            Ok(true)
+        );
+        assert_eq!(
+            grade_answer(&question, SEED, &Scoring::default(), Answer::Choice(0)),
+            Err(WrongQuestionType)
+        );
+    }
+
+    #[test]
+    fn ordering_and_matching() {
+        use crate::{shown_order, AskQuestion};
+
+        let ordering = parse_question(["<order>", "one", "two", "three", "</order>"]).unwrap();
+        let AskQuestion::Ordering { items } = AskQuestion::new(&ordering, SEED) else {
+            panic!("ordering is asked as {ordering:?}");
+        };
+        assert_ne!(&items[..], ["one", "two", "three"]);
+
+        // put shown items back in the source order
+        let order = shown_order(SEED, &["one", "two", "three"]);
+        let mut right = vec![0; order.len()];
+        for (shown, &source) in (0u32..).zip(order.iter()) {
+            right[source as usize] = shown;
+        }
+        let grade = |answer: &[u32], scoring: &Scoring| {
+            grade_answer(&ordering, SEED, scoring, Answer::Ordering(answer.into())).unwrap()
+        };
+        assert!(grade(&right, &Scoring::default()).correct);
+        let swapped = [right[1], right[0], right[2]];
+        assert!(!grade(&swapped, &Scoring::default()).correct);
+        let partial = Scoring {
+            points: 3,
+            penalty: 0,
+            partial: true,
+        };
+        assert_eq!(grade(&swapped, &partial).score, 1);
+        assert!(!grade(&right[..2], &Scoring::default()).correct);
+
+        let matching =
+            parse_question(["<match>", "Kyiv = Ukraine", "Paris = France", "</match>"]).unwrap();
+        let AskQuestion::Matching { left, right } = AskQuestion::new(&matching, SEED) else {
+            panic!("matching is asked as {matching:?}");
+        };
+        // two items are always swapped
+        assert_eq!(&left[..], ["Kyiv", "Paris"]);
+        assert_eq!(&right[..], ["France", "Ukraine"]);
+        assert_eq!(
+            check_answer(&matching, SEED, Answer::Matching([1, 0].into())),
+            Ok(true)
+        );
+        assert_eq!(
+            check_answer(&matching, SEED, Answer::Matching([0, 1].into())),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn shown_order_is_secret() {
+        use crate::AskQuestion;
+
+        // the same shown items come from every other source order with some seed,
+        // so the question as it is asked tells nothing about the answer
+        let shown = ["two", "three", "one"];
+        let sources = [
+            ["one", "two", "three"],
+            ["one", "three", "two"],
+            ["two", "one", "three"],
+            ["three", "one", "two"],
+            ["three", "two", "one"],
+        ];
+        for source in sources {
+            let ordering = Question::Ordering {
+                items: source.map(String::from).into(),
+            };
+            let seed = (0..10_000).map(ShuffleSeed).find(|&seed| {
+                AskQuestion::new(&ordering, seed)
+                    == AskQuestion::Ordering {
+                        items: shown.map(String::from).into(),
+                    }
+            });
+            assert!(seed.is_some(), "{source:?} is never shown as {shown:?}");
+        }
+
+        // and the answer for one participation is wrong for most of the others
+        let ordering = Question::Ordering {
+            items: sources[0].map(String::from).into(),
+        };
+        let answer = Answer::Ordering([2, 0, 1].into());
+        let right = (0..600)
+            .map(ShuffleSeed)
+            .filter(|&seed| check_answer(&ordering, seed, &answer) == Ok(true))
+            .count();
+        assert!((50..200).contains(&right), "right for {right} of 600 seeds");
+    }
+
+    #[test]
+    fn cloze() {
+        let page = parse_quest_page("Capital of {{Ukraine}} is {{ Kyiv | Kiev }}.").unwrap();
+        let [QuestPageElement::Question(cloze, scoring)] = &page[..] else {
+            panic!("text with blanks parsed as {page:?}");
+        };
+        assert_eq!(
+            cloze,
+            &Question::Cloze {
+                text: ["Capital of ", " is ", "."].map(String::from).into(),
+                blanks: [
+                    Box::new(["Ukraine".to_string()]) as Box<[_]>,
+                    Box::new(["Kyiv".to_string(), "Kiev".to_string()]),
+                ]
+                .into(),
+            }
+        );
+        let grade = |answer: [&str; 2]| {
+            grade_answer(
+                cloze,
+                SEED,
+                scoring,
+                Answer::Cloze(answer.map(String::from).into()),
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            grade(["ukraine", "Kiev"]),
+            Grade {
+                correct: true,
+                score: 2
+            }
+        );
+        assert_eq!(
+            grade(["Ukraine", "Lviv"]),
+            Grade {
+                correct: false,
+                score: 1
+            }
+        );
+
+        assert_eq!(
+            parse_quest_page("unclosed {{blank").unwrap_err()[0].kind,
+            PageParseError::BadBlankFormat
+        );
+        assert_eq!(
+            parse_quest_page("empty {{a||b}} alternative").unwrap_err()[0].kind,
+            PageParseError::BadBlankFormat
+        );
+        assert_eq!(
+            parse_quest_page("code {{`a`}} in answer").unwrap_err()[0].kind,
+            PageParseError::BadBlankFormat
+        );
+
+        let blanks = "{{a}}".repeat(crate::MAX_POINTS as usize);
+        assert!(parse_quest_page(&*blanks).is_ok());
+        assert_eq!(
+            parse_quest_page(blanks + "{{a}}").unwrap_err()[0].kind,
+            PageParseError::TooManyPoints
+        );
+    }
+
+    #[test]
+    fn code_is_not_cloze() {
+        for source in [
+            "```rust\nprintln!(\"{{}}\", x);\n```",
+            "  ~~~~\n{{ unclosed\n~~~~~",
+            "```\nunclosed fence takes the rest {{",
+            "Template `{{ name }}` here",
+            "Double ``{{ `a` }}`` backticks",
+        ] {
+            assert_eq!(
+                parse_quest_page(source).as_deref(),
+                Ok(&[QuestPageElement::Text(source.into())][..]),
+                "{source:?}"
+            );
+        }
+
+        // blanks are still found around the code
+        let page = parse_quest_page("```\n{{a}}\n```\n`x` {{b}} `{{c}}` and ` {{d}}").unwrap();
+        let [QuestPageElement::Question(Question::Cloze { text, blanks }, _)] = &page[..] else {
+            panic!("text with blanks parsed as {page:?}");
+        };
+        assert_eq!(
+            &text[..],
+            ["```\n{{a}}\n```\n`x` ", " `{{c}}` and ` ", ""].map(String::from)
+        );
+        assert_eq!(blanks.len(), 2);
+    }
+}
+
+#[cfg(test)]
+mod diagnostics_tests {
+    use crate::check_quest_page;
+    use crate::{PageParseError, PageWarning, SourceSpan};
+
+    #[test]
+    fn all_errors_located() {
+        let source = "\
+Intro
+<question>
++ a
++ b
+</question>
+Capital is {{Kyiv
+<question points=lots>
+- a
+- b
+</question>
+<question>
+<opened>
+</opened>
+</question>";
+        let errors = check_quest_page(source).page.unwrap_err();
+        let located = errors
+            .iter()
+            .map(|error| (error.kind, error.span, error.question))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            located,
+            [
+                (
+                    PageParseError::MultipleCorrect,
+                    SourceSpan {
+                        line: 4,
+                        column: 1,
+                        end_line: 4,
+                        end_column: 4
+                    },
+                    Some(0)
+                ),
+                (
+                    PageParseError::BadBlankFormat,
+                    SourceSpan {
+                        line: 6,
+                        column: 12,
+                        end_line: 6,
+                        end_column: 18
+                    },
+                    Some(1)
+                ),
+                (
+                    PageParseError::BadQuestionAttributes,
+                    SourceSpan {
+                        line: 7,
+                        column: 1,
+                        end_line: 7,
+                        end_column: 23
+                    },
+                    Some(2)
+                ),
+                (
+                    PageParseError::NoCorrectChoice,
+                    SourceSpan {
+                        line: 7,
+                        column: 1,
+                        end_line: 10,
+                        end_column: 12
+                    },
+                    Some(2)
+                ),
+                (
+                    PageParseError::BadOpenedFormat,
+                    SourceSpan {
+                        line: 13,
+                        column: 1,
+                        end_line: 13,
+                        end_column: 10
+                    },
+                    Some(3)
+                ),
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "line 4, column 1: choice question has multiple correct answers, \
+             use [x] and [ ] lines for multiple choice"
+        );
+    }
+
+    #[test]
+    fn warnings() {
+        let source = "\
+<img src=\"map.png\" />
+<question>
++ a
+</question>
+   
+<question>
++ a
+</question>
+</opened>";
+        let check = check_quest_page(source);
+        assert!(check.page.is_ok());
+        let warnings = check
+            .warnings
+            .iter()
+            .map(|warning| (warning.kind, warning.span.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            [
+                (PageWarning::UnusedImage, 1),
+                (PageWarning::EmptyText, 5),
+                (PageWarning::StrayClosingTag, 9),
+            ]
+        );
+    }
+    #[test]
+    fn question_spans() {
+        let source = "\
+Intro
+<question>
++ a
+- b
+</question>
+Capital is {{Kyiv}}
+<question>
+<opened>
+Kyiv";
+        let lines = check_quest_page(source)
+            .questions
+            .iter()
+            .map(|span| (span.line, span.end_line))
+            .collect::<Vec<_>>();
+        assert_eq!(lines, [(2, 5), (6, 6), (7, 9)]);
+    }
+}
@@ -7,17 +7,19 @@ use std::{
 
 use chrono::TimeDelta;
 use common::{
-    LoginRequest, QuestHistoryPage, QuestHistoryRecord, QuestId, QuestInfo, RegisterRequest,
-    Timestamp, UserId, UserInfo, UserOwnedQuestRecord, UserOwnedQuestsPage,
-    QUEST_HISTORY_PAGE_SIZE, USER_OWNED_QUESTS_PAGE_SIZE,
+    AskQuestPage, LoginRequest, QuestHistoryPage, QuestHistoryRecord, QuestId, QuestInfo,
+    QuestPageSource, QuestState, RegisterRequest, ShuffleSeed, Timestamp, UserId, UserInfo,
+    UserOwnedQuestRecord, UserOwnedQuestsPage, QUEST_HISTORY_PAGE_SIZE,
+    USER_OWNED_QUESTS_PAGE_SIZE,
 };
 use fastrand::Rng as FastRng;
 use leptos::logging;
+use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::{EntityKind, GeneralError};
 
-use super::{error::GameError, Api, LoginError, QuestPage, RegisterError};
+use super::{error::GameError, Api, LoginError, RegisterError};
 
 fn now() -> Timestamp {
     chrono::Utc::now().naive_local()
@@ -36,9 +38,13 @@ struct Data {
     name_or_emails: HashMap<String, UserId>,
     auth_user: Option<UserId>,
     quests: HashMap<QuestId, QuestInfo>,
-    quest_pages: HashMap<(QuestId, u32), String>,
+    quest_pages: HashMap<(QuestId, u32), QuestPageSource>,
     user_data: HashMap<UserId, (Vec<QuestHistoryRecord>, Vec<QuestId>)>,
     active_quest: Option<(QuestId, u32, Timestamp)>,
+    // score of the active quest so far
+    active_score: i32,
+    // the same for every participation of the dummy player
+    shuffle_seed: ShuffleSeed,
 }
 
 impl Data {
@@ -49,9 +55,11 @@ impl Data {
             name_or_emails: HashMap::new(),
             auth_user: None,
             quests: HashMap::<QuestId, QuestInfo>::new(),
-            quest_pages: HashMap::<(QuestId, u32), String>::new(),
+            quest_pages: HashMap::<(QuestId, u32), QuestPageSource>::new(),
             user_data: HashMap::new(),
             active_quest: None,
+            active_score: 0,
+            shuffle_seed: ShuffleSeed(42),
         }
     }
 
@@ -147,13 +155,16 @@ impl Data {
                 title: String::new(),
                 description: String::new(),
                 pages: 0,
-                published: false,
+                state: QuestState::Unpublished,
+                time_limit: None,
+                opens_at: None,
+                closes_at: None,
             },
         );
         quest_id
     }
 
-    fn get_quest_page(&self, quest_id: QuestId, page: u32) -> Option<String> {
+    fn get_quest_page(&self, quest_id: QuestId, page: u32) -> Option<QuestPageSource> {
         self.quest_pages.get(&(quest_id, page)).cloned()
     }
 
@@ -161,8 +172,8 @@ impl Data {
         &mut self,
         quest_id: QuestId,
         page: u32,
-        source: String,
-    ) -> Result<(), GeneralError> {
+        mut source: QuestPageSource,
+    ) -> Result<u32, GeneralError> {
         let info = self
             .quests
             .get_mut(&quest_id)
@@ -172,12 +183,60 @@ impl Data {
             return Err(GeneralError::UnknownEntity(EntityKind::QuestPage));
         }
 
+        match self.quest_pages.get(&(quest_id, page)) {
+            Some(current) if current.revision != source.revision => {
+                return Err(GeneralError::Outdated)
+            }
+            Some(_) => source.revision += 1,
+            None if source.revision != 0 => return Err(GeneralError::Outdated),
+            // 0 is the revision of a page, that is not saved yet
+            None => source.revision = 1,
+        }
+
         if page == info.pages {
             info.pages += 1;
         }
+        let revision = source.revision;
         self.quest_pages.insert((quest_id, page), source);
 
-        Ok(())
+        Ok(revision)
+    }
+
+    /// Pages of the quest, that the authorized user may edit
+    fn editable_quest_pages(
+        &self,
+        quest_id: QuestId,
+    ) -> Result<Vec<QuestPageSource>, GeneralError> {
+        let auth_user = self.require_auth()?;
+
+        let info = self
+            .quests
+            .get(&quest_id)
+            .ok_or(GeneralError::UnknownEntity(EntityKind::Quest))?;
+
+        if info.owner != auth_user {
+            return Err(GeneralError::Unauthorized);
+        }
+
+        Ok((0..info.pages)
+            .map(|page| self.get_quest_page(quest_id, page).unwrap_or_default())
+            .collect())
+    }
+
+    /// Replaces every page of the quest, sources saved before are outdated afterwards
+    fn replace_quest_pages(&mut self, quest_id: QuestId, pages: Vec<QuestPageSource>) {
+        let Some(info) = self.quests.get_mut(&quest_id) else {
+            return;
+        };
+        for page in 0..info.pages {
+            self.quest_pages.remove(&(quest_id, page));
+        }
+        info.pages = pages.len() as u32;
+        self.quest_pages
+            .extend((0..).zip(pages).map(|(page, mut source)| {
+                source.revision += 1;
+                ((quest_id, page), source)
+            }));
     }
 }
 
@@ -196,6 +255,35 @@ fn extract_page<const PAGE_SIZE: usize, I, T>(
     (data, page, u32::try_from(len.div_ceil(PAGE_SIZE)).unwrap())
 }
 
+/// Seeded dataset for [`DummyApi`], loaded from json
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Fixture {
+    pub users: Vec<FixtureUser>,
+    /// name of the user, that is logged in from the start
+    pub auth_user: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureUser {
+    pub name: String,
+    pub email: String,
+    pub pass: String,
+    #[serde(default)]
+    pub quests: Vec<FixtureQuest>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FixtureQuest {
+    pub title: String,
+    pub description: String,
+    /// unpublished, if not set
+    pub state: Option<QuestState>,
+    /// page sources
+    pub pages: Vec<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct DummyApi {
     data: Arc<Mutex<Data>>,
@@ -224,6 +312,8 @@ impl DummyApi {
                 started_at: data.timestamp(2..10),
                 finished_at: Some(data.timestamp(0..2)),
                 completed_pages: data.rng.u32(0..10),
+                score: data.rng.i32(-2..20),
+                max_score: 20,
             };
             data.user_data.entry(admin_id).or_default().0.push(record);
         }
@@ -237,6 +327,55 @@ impl DummyApi {
         }
     }
 
+    /// Api with nothing but the fixture data
+    pub fn with_fixture(fixture: Fixture) -> Self {
+        let mut data = Data::new();
+
+        for user in fixture.users {
+            let Some(user_id) = data.try_register(&user.name, user.email, user.pass) else {
+                logging::warn!(
+                    "Fixture user {} is skipped, name or email in use",
+                    user.name
+                );
+                continue;
+            };
+            for quest in user.quests {
+                let quest_id = data.create_quest(user_id);
+                for (source, page) in quest.pages.into_iter().zip(0..) {
+                    // pages are appended in order, so quest is always known
+                    let source = QuestPageSource {
+                        source,
+                        ..Default::default()
+                    };
+                    let _ = data.set_quest_page(quest_id, page, source);
+                }
+                if let Some(info) = data.quests.get_mut(&quest_id) {
+                    info.title = quest.title;
+                    info.description = quest.description;
+                    info.state = quest.state.unwrap_or(QuestState::Unpublished);
+                }
+            }
+        }
+
+        data.auth_user = fixture
+            .auth_user
+            .and_then(|name| data.name_or_emails.get(&name).copied());
+
+        Self {
+            data: Arc::new(Mutex::new(data)),
+        }
+    }
+
+    /// Fetches json fixture from the url
+    pub async fn load_fixture(url: &str) -> Result<Self, gloo_net::Error> {
+        let fixture = gloo_net::http::Request::get(url)
+            .send()
+            .await?
+            .json::<Fixture>()
+            .await?;
+        Ok(Self::with_fixture(fixture))
+    }
+
     fn lock_data(&self) -> Result<MutexGuard<'_, Data>, GeneralError> {
         self.data.lock().map_err(|_| GeneralError::Unknown)
     }
@@ -411,8 +550,8 @@ impl Api for DummyApi {
         &self,
         quest_id: QuestId,
         page: u32,
-        source: String,
-    ) -> Result<(), GeneralError> {
+        source: QuestPageSource,
+    ) -> Result<u32, GeneralError> {
         server_response().await;
         let mut data = self.lock_data()?;
         data.server_failure()?;
@@ -431,7 +570,11 @@ impl Api for DummyApi {
         data.set_quest_page(quest_id, page, source)
     }
 
-    async fn get_page_source(&self, quest_id: QuestId, page: u32) -> Result<String, GeneralError> {
+    async fn get_page_source(
+        &self,
+        quest_id: QuestId,
+        page: u32,
+    ) -> Result<QuestPageSource, GeneralError> {
         server_response().await;
         let mut data = self.lock_data()?;
         data.server_failure()?;
@@ -448,13 +591,65 @@ impl Api for DummyApi {
         }
 
         if info.pages == page {
-            data.set_quest_page(quest_id, page, String::new())?;
+            data.set_quest_page(quest_id, page, QuestPageSource::default())?;
         }
 
         data.get_quest_page(quest_id, page)
             .ok_or(GeneralError::UnknownEntity(EntityKind::QuestPage))
     }
 
+    async fn insert_page(
+        &self,
+        quest_id: QuestId,
+        page: u32,
+        source: QuestPageSource,
+    ) -> Result<(), GeneralError> {
+        server_response().await;
+        let mut data = self.lock_data()?;
+        data.server_failure()?;
+
+        let mut pages = data.editable_quest_pages(quest_id)?;
+        if page as usize > pages.len() {
+            return Err(GeneralError::UnknownEntity(EntityKind::QuestPage));
+        }
+        pages.insert(page as usize, source);
+        data.replace_quest_pages(quest_id, pages);
+        Ok(())
+    }
+
+    async fn delete_page(&self, quest_id: QuestId, page: u32) -> Result<(), GeneralError> {
+        server_response().await;
+        let mut data = self.lock_data()?;
+        data.server_failure()?;
+
+        let mut pages = data.editable_quest_pages(quest_id)?;
+        if page as usize >= pages.len() {
+            return Err(GeneralError::UnknownEntity(EntityKind::QuestPage));
+        }
+        pages.remove(page as usize);
+        data.replace_quest_pages(quest_id, pages);
+        Ok(())
+    }
+
+    async fn reorder_pages(&self, quest_id: QuestId, order: Vec<u32>) -> Result<(), GeneralError> {
+        server_response().await;
+        let mut data = self.lock_data()?;
+        data.server_failure()?;
+
+        let pages = data.editable_quest_pages(quest_id)?;
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        if !sorted.into_iter().eq(0..pages.len() as u32) {
+            return Err(GeneralError::Unknown);
+        }
+        let pages = order
+            .into_iter()
+            .map(|page| pages[page as usize].clone())
+            .collect();
+        data.replace_quest_pages(quest_id, pages);
+        Ok(())
+    }
+
     async fn start_quest(&self, quest_id: QuestId) -> Result<(), GameError> {
         server_response().await;
         let mut data = self.lock_data()?;
@@ -470,8 +665,15 @@ impl Api for DummyApi {
             };
         }
 
-        data.quests.contains_key(&quest_id);
+        let info = data
+            .quests
+            .get(&quest_id)
+            .ok_or(GeneralError::UnknownEntity(EntityKind::Quest))?;
+        if !info.is_open_at(now()) {
+            return Err(GameError::QuestClosed);
+        }
         data.active_quest = Some((quest_id, 0, now()));
+        data.active_score = 0;
         Ok(())
     }
 
@@ -479,7 +681,7 @@ impl Api for DummyApi {
         Ok(self.lock_data()?.active_quest)
     }
 
-    async fn quest_page(&self) -> Result<(QuestPage, u32), GameError> {
+    async fn quest_page(&self) -> Result<(AskQuestPage, u32), GameError> {
         server_response().await;
         let mut data = self.lock_data()?;
         data.server_failure()?;
@@ -494,14 +696,15 @@ impl Api for DummyApi {
         }
         */
 
-        let source = data
+        let QuestPageSource { source, .. } = data
             .get_quest_page(active_id, active_page)
             .ok_or(GeneralError::UnknownEntity(EntityKind::QuestPage))?;
 
+        let page = common::parse_quest_page(source)
+            .map_err(GeneralError::from)
+            .map_err(GameError::from)?;
         Ok((
-            common::parse_quest_page(source)
-                .map_err(GeneralError::from)
-                .map_err(GameError::from)?,
+            common::ask_quest_page(&page, data.shuffle_seed),
             active_page,
         ))
     }
@@ -515,8 +718,24 @@ impl Api for DummyApi {
             return Err(GameError::NoActiveQuest);
         };
 
+        let QuestPageSource { source, .. } = data
+            .get_quest_page(active_id, active_page)
+            .ok_or(GeneralError::UnknownEntity(EntityKind::QuestPage))?;
+        let page = common::parse_quest_page(source)
+            .map_err(GeneralError::from)
+            .map_err(GameError::from)?;
+        let seed = data.shuffle_seed;
+        let score = common::page_questions(&page)
+            .zip(answers.iter())
+            .filter_map(|((question, scoring), answer)| {
+                common::grade_answer(question, seed, scoring, answer).ok()
+            })
+            .map(|grade| grade.score)
+            .sum::<i32>();
+        data.active_score += score;
+
         logging::log!(
-            "quest:{active_id}, page:{active_page}, answers: {:?}",
+            "quest:{active_id}, page:{active_page}, answers: {:?}, score: {score}",
             answers
         );
         Ok(())
@@ -533,6 +752,14 @@ impl Api for DummyApi {
 
         let auth_user = data.require_auth()?;
 
+        let pages = data.quests.get(&active_id).map_or(0, |info| info.pages);
+        let max_score = (0..pages)
+            .filter_map(|page| data.get_quest_page(active_id, page))
+            .filter_map(|page| common::parse_quest_page(page.source).ok())
+            .map(|page| common::page_max_score(&page))
+            .sum();
+        let score = data.active_score;
+
         data.user_data
             .entry(auth_user)
             .or_default()
@@ -543,6 +770,8 @@ impl Api for DummyApi {
                 started_at,
                 finished_at: Some(now()),
                 completed_pages: active_page,
+                score,
+                max_score,
             });
 
         Ok(())
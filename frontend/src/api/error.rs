@@ -96,6 +96,12 @@ pub enum GameError {
     AlreadyActiveQuest,
     #[error("Page out of order")]
     PageOutOfOrder,
+    #[error("Time for the page is up")]
+    PageTimeout,
+    #[error("Time for the quest is up")]
+    QuestTimeout,
+    #[error("Quest is not open for playing now")]
+    QuestClosed,
     #[error(transparent)]
     General(GeneralError),
 }
@@ -105,7 +111,10 @@ impl ErrorAction for GameError {
         match self {
             GameError::NoActiveQuest
             | GameError::AlreadyActiveQuest
-            | GameError::PageOutOfOrder => false,
+            | GameError::PageOutOfOrder
+            | GameError::PageTimeout
+            | GameError::QuestTimeout
+            | GameError::QuestClosed => false,
             GameError::General(general_error) => general_error.should_logout(),
         }
     }
@@ -115,6 +124,7 @@ impl ErrorAction for GameError {
             GameError::NoActiveQuest
             | GameError::AlreadyActiveQuest
             | GameError::PageOutOfOrder => true,
+            GameError::PageTimeout | GameError::QuestTimeout | GameError::QuestClosed => false,
             GameError::General(general_error) => general_error.should_log(),
         }
     }
@@ -124,6 +134,21 @@ impl ErrorAction for GameError {
             GameError::NoActiveQuest
             | GameError::AlreadyActiveQuest
             | GameError::PageOutOfOrder => None,
+            GameError::PageTimeout => Some(ToastInfo::new(
+                "Time is up",
+                "page was submitted without answers",
+                ToastKind::Warn,
+            )),
+            GameError::QuestTimeout => Some(ToastInfo::new(
+                "Time is up",
+                "quest is finished with pages submitted so far",
+                ToastKind::Warn,
+            )),
+            GameError::QuestClosed => Some(ToastInfo::new(
+                "Quest is closed",
+                "quest is not open for playing now",
+                ToastKind::Info,
+            )),
             GameError::General(general_error) => general_error.toast_info(),
         }
     }
@@ -133,6 +158,7 @@ impl ErrorAction for GameError {
             GameError::NoActiveQuest
             | GameError::AlreadyActiveQuest
             | GameError::PageOutOfOrder => true,
+            GameError::PageTimeout | GameError::QuestTimeout | GameError::QuestClosed => false,
             GameError::General(general_error) => general_error.is_bug(),
         }
     }
@@ -1,8 +1,8 @@
-use std::future::Future;
+use std::{future::Future, str::FromStr};
 
 use common::{
-    Answer, Avatar, LoginRequest, QuestHistoryPage, QuestId, QuestInfo, QuestPage, RegisterRequest,
-    Timestamp, UserId, UserInfo, UserOwnedQuestsPage,
+    Answer, AskQuestPage, Avatar, LoginRequest, QuestHistoryPage, QuestId, QuestInfo,
+    QuestPageSource, RegisterRequest, Timestamp, UserId, UserInfo, UserOwnedQuestsPage,
 };
 use error::{GameError, LoginError, RegisterError};
 
@@ -10,10 +10,64 @@ use crate::GeneralError;
 
 pub mod dummy;
 
-// pub mod real;
+pub mod real;
 
 pub mod error;
 
+/// Implementation of [`Api`] the app is started with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKind {
+    Dummy,
+    Real,
+}
+
+impl FromStr for ApiKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dummy" => Ok(Self::Dummy),
+            "real" => Ok(Self::Real),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ApiKind {
+    /// Picked by the first of: `?api=` query parameter, `FRONTEND_API` env var at build time,
+    /// `dummy-api` feature. Real api otherwise.
+    pub fn select() -> Self {
+        let from_query = query_param("api").and_then(|api| {
+            let kind = api.parse().ok();
+            if kind.is_none() {
+                leptos::logging::warn!("Unknown api={api} in query, expected dummy or real");
+            }
+            kind
+        });
+        from_query
+            .or_else(|| option_env!("FRONTEND_API")?.parse().ok())
+            .unwrap_or(if cfg!(feature = "dummy-api") {
+                Self::Dummy
+            } else {
+                Self::Real
+            })
+    }
+}
+
+/// Url of json [`dummy::Fixture`]: `?fixture=` query parameter or `FRONTEND_FIXTURE` env var at
+/// build time
+pub fn fixture_url() -> Option<String> {
+    query_param("fixture").or_else(|| option_env!("FRONTEND_FIXTURE").map(String::from))
+}
+
+/// Parameter of the page url, the app is started at
+fn query_param(name: &str) -> Option<String> {
+    let search = gloo_utils::window().location().search().ok()?;
+    web_sys::UrlSearchParams::new_with_str(&search)
+        .ok()?
+        .get(name)
+}
+
 pub fn static_get_user_info(
     api: &impl Api,
     user_id: UserId,
@@ -82,18 +136,40 @@ pub trait Api: Clone + Send + Sync + 'static {
         quest_info: QuestInfo,
     ) -> impl Future<Output = Result<(), GeneralError>> + Send + Sync;
 
+    /// New revision of the page, [`GeneralError::Outdated`] if `source` is based on an old one
     fn set_page_source(
         &self,
         quest_id: QuestId,
         page: u32,
-        source: String,
-    ) -> impl Future<Output = Result<(), GeneralError>> + Send + Sync;
+        source: QuestPageSource,
+    ) -> impl Future<Output = Result<u32, GeneralError>> + Send + Sync;
 
     fn get_page_source(
         &self,
         quest_id: QuestId,
         page: u32,
-    ) -> impl Future<Output = Result<String, GeneralError>> + Send + Sync;
+    ) -> impl Future<Output = Result<QuestPageSource, GeneralError>> + Send + Sync;
+
+    /// Inserts page before `page`, or appends it when `page` is the page count
+    fn insert_page(
+        &self,
+        quest_id: QuestId,
+        page: u32,
+        source: QuestPageSource,
+    ) -> impl Future<Output = Result<(), GeneralError>> + Send + Sync;
+
+    fn delete_page(
+        &self,
+        quest_id: QuestId,
+        page: u32,
+    ) -> impl Future<Output = Result<(), GeneralError>> + Send + Sync;
+
+    /// `order[i]` is the current number of the page, that becomes i-th
+    fn reorder_pages(
+        &self,
+        quest_id: QuestId,
+        order: Vec<u32>,
+    ) -> impl Future<Output = Result<(), GeneralError>> + Send + Sync;
 
     fn active_quest(&self) -> Result<Option<(QuestId, u32, Timestamp)>, GeneralError>;
 
@@ -102,8 +178,10 @@ pub trait Api: Clone + Send + Sync + 'static {
         quest_id: QuestId,
     ) -> impl Future<Output = Result<(), GameError>> + Send + Sync;
 
-    fn quest_page(&self)
-        -> impl Future<Output = Result<(QuestPage, u32), GameError>> + Send + Sync;
+    /// Page to answer, with correct answers stripped
+    fn quest_page(
+        &self,
+    ) -> impl Future<Output = Result<(AskQuestPage, u32), GameError>> + Send + Sync;
 
     fn answer_page(
         &self,
@@ -1,137 +1,575 @@
-use super::Api;
+use std::{
+    fmt::Debug,
+    future::Future,
+    sync::{Arc, Mutex, MutexGuard},
+};
 
-pub struct RealApi {}
+use common::{
+    endpoints::{
+        CreateQuest, DeleteQuestPage, GetAppliedQuests, GetOwnedQuests, GetQuestInfo, GetQuestPage,
+        GetUserInfo, InsertQuestPage, JoinQuest, Login, Logout, QuestParticipation, Register,
+        ReorderQuestPages, UpdateAvatar, UpdateQuestInfo, UpdateQuestPage,
+    },
+    ApiResponse, AskQuestPage, Avatar, Endpoint, LoginRequest, Method, QuestHistoryPage, QuestId,
+    QuestInfo, QuestPageSource, RegisterRequest, Timestamp, UserId, UserInfo, UserOwnedQuestsPage,
+    WsClientMessage, WsServerMessage,
+};
+use futures::{lock::Mutex as AsyncMutex, SinkExt, StreamExt};
+use gloo_net::{
+    http::{Request, Response},
+    websocket::{futures::WebSocket, Message},
+};
+use gloo_storage::{LocalStorage, Storage};
+use leptos::logging;
+use send_wrapper::SendWrapper;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{EntityKind, GeneralError};
+
+use super::{error::GameError, Api, LoginError, RegisterError};
+
+/// Session cookie is http only, so logged in user is remembered separately
+const AUTH_USER_KEY: &str = "auth_user";
+
+/// Quest, page to answer and start time
+type ActiveQuest = (QuestId, u32, Timestamp);
+
+fn now() -> Timestamp {
+    chrono::Utc::now().naive_local()
+}
+
+/// Failed request, before it is mapped onto the error of a specific method
+#[derive(Debug)]
+enum RequestError<E> {
+    /// server responded with [`ApiResponse::Error`]
+    Api(u16, E),
+    /// server responded with something, that is not [`ApiResponse`]
+    Status(u16),
+    /// request was not sent, or response was not received
+    Network,
+}
+
+impl<E: Debug> RequestError<E> {
+    fn status(&self) -> Option<u16> {
+        match self {
+            RequestError::Api(status, _) | RequestError::Status(status) => Some(*status),
+            RequestError::Network => None,
+        }
+    }
+
+    /// Maps status code onto [`GeneralError`], `entity` is the one reported as unknown on 404
+    fn general(self, entity: EntityKind) -> GeneralError {
+        logging::warn!("Request failed: {self:?}");
+        match self.status() {
+            Some(401) => GeneralError::RequestLogIn,
+            Some(403) => GeneralError::Unauthorized,
+            Some(404) => GeneralError::UnknownEntity(entity),
+            Some(409) => GeneralError::Outdated,
+            _ => GeneralError::Unknown,
+        }
+    }
+}
+
+async fn read_response<E>(response: Response) -> Result<E::Response, RequestError<E::Error>>
+where
+    E: Endpoint,
+    E::Response: DeserializeOwned,
+    E::Error: DeserializeOwned,
+{
+    let status = response.status();
+    match response.json::<ApiResponse<E::Response, E::Error>>().await {
+        Ok(ApiResponse::Response(data)) if response.ok() => Ok(data),
+        Ok(ApiResponse::Error(err)) => Err(RequestError::Api(status, err)),
+        _ => Err(RequestError::Status(status)),
+    }
+}
+
+/// Sends json request to the endpoint, `params` fill its path
+async fn send<E>(params: &[&str], body: &E::Request) -> Result<E::Response, RequestError<E::Error>>
+where
+    E: Endpoint,
+    E::Request: Serialize,
+    E::Response: DeserializeOwned,
+    E::Error: DeserializeOwned,
+{
+    let path = E::path(params);
+    let request = match E::METHOD {
+        Method::Get => Request::get(&path).build(),
+        Method::Post => Request::post(&path).json(body),
+    }
+    .map_err(|_| RequestError::Network)?;
+    let response = request.send().await.map_err(|_| RequestError::Network)?;
+    read_response::<E>(response).await
+}
+
+/// Websocket url of the endpoint, relative to the current page
+fn ws_url(path: &str) -> Result<String, GeneralError> {
+    let location = gloo_utils::window().location();
+    let protocol = match location
+        .protocol()
+        .map_err(|_| GeneralError::Unknown)?
+        .as_str()
+    {
+        "https:" => "wss:",
+        _ => "ws:",
+    };
+    let host = location.host().map_err(|_| GeneralError::Unknown)?;
+    Ok(format!("{protocol}//{host}{path}"))
+}
+
+/// Api, backed by the actual server
+#[derive(Clone)]
+pub struct RealApi {
+    auth_user: Arc<Mutex<Option<UserId>>>,
+    active_quest: Arc<Mutex<Option<ActiveQuest>>>,
+    /// socket of the active quest, browser types are only ever touched from the main thread
+    socket: Arc<AsyncMutex<Option<SendWrapper<WebSocket>>>>,
+}
+
+impl Default for RealApi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RealApi {
+    pub fn new() -> Self {
+        Self {
+            auth_user: Arc::new(Mutex::new(LocalStorage::get(AUTH_USER_KEY).ok())),
+            active_quest: Arc::new(Mutex::new(None)),
+            socket: Arc::new(AsyncMutex::new(None)),
+        }
+    }
+
+    fn lock_auth_user(&self) -> Result<MutexGuard<'_, Option<UserId>>, GeneralError> {
+        self.auth_user.lock().map_err(|_| GeneralError::Unknown)
+    }
+
+    fn lock_active_quest(&self) -> Result<MutexGuard<'_, Option<ActiveQuest>>, GeneralError> {
+        self.active_quest.lock().map_err(|_| GeneralError::Unknown)
+    }
+
+    fn set_auth_user(&self, user_id: Option<UserId>) -> Result<(), GeneralError> {
+        match user_id {
+            Some(user_id) => {
+                LocalStorage::set(AUTH_USER_KEY, user_id).map_err(|_| GeneralError::Unknown)?
+            }
+            None => LocalStorage::delete(AUTH_USER_KEY),
+        }
+        *self.lock_auth_user()? = user_id;
+        Ok(())
+    }
+
+    /// Server only shares data of the logged in user
+    fn require_self(&self, user_id: UserId) -> Result<(), GeneralError> {
+        if self.require_auth_user()? != user_id {
+            return Err(GeneralError::Unauthorized);
+        }
+        Ok(())
+    }
+
+    /// Drops active quest, closing its socket
+    async fn end_quest(&self) -> Result<(), GeneralError> {
+        *self.lock_active_quest()? = None;
+        if let Some(socket) = self.socket.lock().await.take() {
+            let _ = socket.take().close(None, None);
+        }
+        Ok(())
+    }
+
+    /// Page was submitted by the server without answers, next one is answered now
+    fn skip_timed_out_page(&self, page: u32) -> Result<(), GeneralError> {
+        let mut active_quest = self.lock_active_quest()?;
+        if let Some((_, active_page, _)) = active_quest.as_mut() {
+            if *active_page == page {
+                *active_page += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends message over the quest socket and waits for the reply
+    async fn exchange(&self, message: WsClientMessage) -> Result<WsServerMessage, GameError> {
+        let text = serde_json::to_string(&message).map_err(|_| GeneralError::Unknown)?;
+
+        let reply = {
+            let mut socket = self.socket.lock().await;
+            let socket = socket.as_mut().ok_or(GameError::NoActiveQuest)?;
+            if socket.send(Message::Text(text)).await.is_err() {
+                None
+            } else {
+                loop {
+                    let message = match socket.next().await {
+                        Some(Ok(Message::Text(text))) => {
+                            serde_json::from_str::<WsServerMessage>(&text).ok()
+                        }
+                        Some(Ok(Message::Bytes(_))) => continue,
+                        Some(Err(_)) | None => None,
+                    };
+                    // pushed messages are not replies, they are handled while waiting for one
+                    match message {
+                        Some(
+                            WsServerMessage::TimeLeft(_, _) | WsServerMessage::QuestTimeLeft(_),
+                        ) => continue,
+                        Some(WsServerMessage::PageTimeout(page)) => {
+                            self.skip_timed_out_page(page)?;
+                            continue;
+                        }
+                        message => break message,
+                    }
+                }
+            }
+        };
+
+        match reply {
+            Some(WsServerMessage::QuestTimeout) => {
+                self.end_quest().await?;
+                Err(GameError::QuestTimeout)
+            }
+            Some(WsServerMessage::RequestBail) | None => {
+                logging::warn!("Server bailed out of the quest");
+                self.end_quest().await?;
+                Err(GameError::NoActiveQuest)
+            }
+            Some(reply) => Ok(reply),
+        }
+    }
+}
 
 impl Api for RealApi {
-    fn login(
-        &self,
-        info: common::LoginRequest,
-    ) -> impl std::future::Future<Output = Result<(), super::error::LoginError>> + Send {
-        todo!()
+    fn login(&self, info: LoginRequest) -> impl Future<Output = Result<(), LoginError>> + Send {
+        let api = self.clone();
+        SendWrapper::new(async move {
+            let response = send::<Login>(&[], &info).await.map_err(|err| {
+                match err.status() {
+                    // unknown user and wrong password are not told apart
+                    Some(400 | 404) => LoginError::InvalidCredentials,
+                    _ => err.general(EntityKind::User).into(),
+                }
+            })?;
+            api.set_auth_user(Some(response.id))?;
+            Ok(())
+        })
     }
 
     fn logout(&self) {
-        todo!()
+        if let Err(err) = self.set_auth_user(None) {
+            logging::warn!("Failed to forget user on logout: {err:?}");
+        }
+        let api = self.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let _ = api.end_quest().await;
+            if let Err(err) = send::<Logout>(&[], &()).await {
+                logging::warn!("Logout failed: {err:?}");
+            }
+        });
     }
 
-    fn auth_user(&self) -> Result<Option<common::UserId>, crate::GeneralError> {
-        todo!()
+    fn auth_user(&self) -> Result<Option<UserId>, GeneralError> {
+        Ok(*self.lock_auth_user()?)
     }
 
     fn register(
         &self,
-        info: common::RegisterRequest,
-    ) -> impl std::future::Future<Output = Result<(), super::error::RegisterError>> + Send + Sync
-    {
-        todo!()
+        info: RegisterRequest,
+    ) -> impl Future<Output = Result<(), RegisterError>> + Send + Sync {
+        let api = self.clone();
+        SendWrapper::new(async move {
+            let response =
+                send::<Register>(&[], &info)
+                    .await
+                    .map_err(|err| match err.status() {
+                        Some(409) => RegisterError::NameInUse,
+                        _ => err.general(EntityKind::User).into(),
+                    })?;
+            api.set_auth_user(Some(response.id))?;
+            Ok(())
+        })
     }
 
     fn get_user_info(
         &self,
-        user_id: common::UserId,
-    ) -> impl std::future::Future<Output = Result<common::UserInfo, crate::GeneralError>> + Send + Sync
-    {
-        todo!()
+        user_id: UserId,
+    ) -> impl Future<Output = Result<UserInfo, GeneralError>> + Send + Sync {
+        SendWrapper::new(async move {
+            send::<GetUserInfo>(&[&user_id.0.to_string()], &())
+                .await
+                .map_err(|err| err.general(EntityKind::User))
+        })
     }
 
     fn set_avatar(
         &self,
-        user_id: common::UserId,
-        avatar: common::Avatar,
-    ) -> impl std::future::Future<Output = Result<(), crate::GeneralError>> {
-        todo!()
+        user_id: UserId,
+        avatar: Avatar,
+    ) -> impl Future<Output = Result<(), GeneralError>> {
+        let api = self.clone();
+        async move {
+            api.require_self(user_id)?;
+
+            let bytes = js_sys::Uint8Array::from(avatar.0.as_slice());
+            let blob = web_sys::Blob::new_with_u8_array_sequence(&js_sys::Array::of1(&bytes))
+                .map_err(|_| GeneralError::Unknown)?;
+            let form = web_sys::FormData::new().map_err(|_| GeneralError::Unknown)?;
+            form.append_with_blob("avatar", &blob)
+                .map_err(|_| GeneralError::Unknown)?;
+
+            let response = Request::post(&UpdateAvatar::path(&[]))
+                .body(form)
+                .map_err(|_| GeneralError::Unknown)?
+                .send()
+                .await
+                .map_err(|_| GeneralError::Unknown)?;
+            read_response::<UpdateAvatar>(response)
+                .await
+                .map_err(|err| err.general(EntityKind::User))
+        }
     }
 
     fn quest_history(
         &self,
-        user_id: common::UserId,
+        user_id: UserId,
         page: u32,
-    ) -> impl std::future::Future<Output = Result<common::QuestHistoryPage, crate::GeneralError>>
-           + Sync
-           + Send {
-        todo!()
+    ) -> impl Future<Output = Result<QuestHistoryPage, GeneralError>> + Sync + Send {
+        let api = self.clone();
+        SendWrapper::new(async move {
+            api.require_self(user_id)?;
+            send::<GetAppliedQuests>(&[&page.to_string()], &())
+                .await
+                .map_err(|err| err.general(EntityKind::User))
+        })
     }
 
     fn user_quests(
         &self,
-        user_id: common::UserId,
+        user_id: UserId,
         page: u32,
-    ) -> impl std::future::Future<Output = Result<common::UserOwnedQuestsPage, crate::GeneralError>>
-           + Sync
-           + Send {
-        todo!()
+    ) -> impl Future<Output = Result<UserOwnedQuestsPage, GeneralError>> + Sync + Send {
+        let api = self.clone();
+        SendWrapper::new(async move {
+            api.require_self(user_id)?;
+            send::<GetOwnedQuests>(&[&page.to_string()], &())
+                .await
+                .map_err(|err| err.general(EntityKind::User))
+        })
     }
 
-    fn create_quest(
-        &self,
-    ) -> impl std::future::Future<Output = Result<common::QuestId, crate::GeneralError>> + Send + Sync
-    {
-        todo!()
+    fn create_quest(&self) -> impl Future<Output = Result<QuestId, GeneralError>> + Send + Sync {
+        SendWrapper::new(async move {
+            send::<CreateQuest>(&[], &())
+                .await
+                .map_err(|err| err.general(EntityKind::Quest))
+        })
     }
 
     fn get_quest_info(
         &self,
-        quest_id: common::QuestId,
-    ) -> impl std::future::Future<Output = Result<common::QuestInfo, crate::GeneralError>> + Send + Sync
-    {
-        todo!()
+        quest_id: QuestId,
+    ) -> impl Future<Output = Result<QuestInfo, GeneralError>> + Send + Sync {
+        SendWrapper::new(async move {
+            send::<GetQuestInfo>(&[&quest_id.0.to_string()], &())
+                .await
+                .map_err(|err| err.general(EntityKind::Quest))
+        })
     }
 
     fn set_quest_info(
         &self,
-        quest_info: common::QuestInfo,
-    ) -> impl std::future::Future<Output = Result<(), crate::GeneralError>> + Send + Sync {
-        todo!()
+        quest_info: QuestInfo,
+    ) -> impl Future<Output = Result<(), GeneralError>> + Send + Sync {
+        SendWrapper::new(async move {
+            send::<UpdateQuestInfo>(&[&quest_info.id.0.to_string()], &quest_info)
+                .await
+                .map_err(|err| err.general(EntityKind::Quest))
+        })
     }
 
     fn set_page_source(
         &self,
-        quest_id: common::QuestId,
+        quest_id: QuestId,
         page: u32,
-        source: String,
-    ) -> impl std::future::Future<Output = Result<(), crate::GeneralError>> + Send + Sync {
-        todo!()
+        source: QuestPageSource,
+    ) -> impl Future<Output = Result<u32, GeneralError>> + Send + Sync {
+        SendWrapper::new(async move {
+            send::<UpdateQuestPage>(&[&quest_id.0.to_string(), &page.to_string()], &source)
+                .await
+                .map_err(|err| err.general(EntityKind::QuestPage))
+        })
     }
 
     fn get_page_source(
         &self,
-        quest_id: common::QuestId,
+        quest_id: QuestId,
         page: u32,
-    ) -> impl std::future::Future<Output = Result<String, crate::GeneralError>> + Send + Sync {
-        todo!()
+    ) -> impl Future<Output = Result<QuestPageSource, GeneralError>> + Send + Sync {
+        let api = self.clone();
+        SendWrapper::new(async move {
+            let params = [quest_id.0.to_string(), page.to_string()];
+            match send::<GetQuestPage>(&[&params[0], &params[1]], &()).await {
+                Ok(source) => Ok(source),
+                // page right after the last one is created empty, same as in the editor
+                Err(err) if err.status() == Some(404) => {
+                    let info = api.get_quest_info(quest_id).await?;
+                    if info.pages != page {
+                        return Err(GeneralError::UnknownEntity(EntityKind::QuestPage));
+                    }
+                    let revision = api
+                        .set_page_source(quest_id, page, QuestPageSource::default())
+                        .await?;
+                    Ok(QuestPageSource {
+                        revision,
+                        ..Default::default()
+                    })
+                }
+                Err(err) => Err(err.general(EntityKind::QuestPage)),
+            }
+        })
     }
 
-    fn active_quest(
+    fn insert_page(
         &self,
-    ) -> Result<Option<(common::QuestId, u32, common::Timestamp)>, crate::GeneralError> {
-        todo!()
+        quest_id: QuestId,
+        page: u32,
+        source: QuestPageSource,
+    ) -> impl Future<Output = Result<(), GeneralError>> + Send + Sync {
+        SendWrapper::new(async move {
+            send::<InsertQuestPage>(&[&quest_id.0.to_string(), &page.to_string()], &source)
+                .await
+                .map_err(|err| err.general(EntityKind::QuestPage))
+        })
+    }
+
+    fn delete_page(
+        &self,
+        quest_id: QuestId,
+        page: u32,
+    ) -> impl Future<Output = Result<(), GeneralError>> + Send + Sync {
+        SendWrapper::new(async move {
+            send::<DeleteQuestPage>(&[&quest_id.0.to_string(), &page.to_string()], &())
+                .await
+                .map_err(|err| err.general(EntityKind::QuestPage))
+        })
+    }
+
+    fn reorder_pages(
+        &self,
+        quest_id: QuestId,
+        order: Vec<u32>,
+    ) -> impl Future<Output = Result<(), GeneralError>> + Send + Sync {
+        SendWrapper::new(async move {
+            send::<ReorderQuestPages>(&[&quest_id.0.to_string()], &order)
+                .await
+                .map_err(|err| err.general(EntityKind::Quest))
+        })
+    }
+
+    fn active_quest(&self) -> Result<Option<(QuestId, u32, Timestamp)>, GeneralError> {
+        Ok(*self.lock_active_quest()?)
     }
 
     fn start_quest(
         &self,
-        quest_id: common::QuestId,
-    ) -> impl std::future::Future<Output = Result<(), super::error::GameError>> + Send + Sync {
-        todo!()
+        quest_id: QuestId,
+    ) -> impl Future<Output = Result<(), GameError>> + Send + Sync {
+        let api = self.clone();
+        SendWrapper::new(async move {
+            if let Some((active, _, _)) = api.active_quest()? {
+                return if active == quest_id {
+                    Ok(())
+                } else {
+                    Err(GameError::AlreadyActiveQuest)
+                };
+            }
+
+            let id = quest_id.0.to_string();
+            match send::<JoinQuest>(&[&id], &()).await {
+                // 409 - joined before, quest is continued
+                Ok(()) => {}
+                Err(err) if err.status() == Some(409) => {}
+                // not published, not open yet or already closed
+                Err(err) if err.status() == Some(403) => return Err(GameError::QuestClosed),
+                Err(err) => return Err(err.general(EntityKind::Quest).into()),
+            }
+
+            let url = ws_url(&QuestParticipation::path(&[&id]))?;
+            let socket = WebSocket::open(&url).map_err(|_| GeneralError::Unknown)?;
+            *api.socket.lock().await = Some(SendWrapper::new(socket));
+            // server tells the actual page on the first request
+            *api.lock_active_quest()? = Some((quest_id, 0, now()));
+            Ok(())
+        })
     }
 
     fn quest_page(
         &self,
-    ) -> impl std::future::Future<Output = Result<(common::QuestPage, u32), super::error::GameError>>
-           + Send
-           + Sync {
-        todo!()
+    ) -> impl Future<Output = Result<(AskQuestPage, u32), GameError>> + Send + Sync {
+        let api = self.clone();
+        SendWrapper::new(async move {
+            let (quest_id, mut page, started_at) =
+                api.active_quest()?.ok_or(GameError::NoActiveQuest)?;
+
+            // second try is for continued quest, when server tells which page is next
+            for _ in 0..2 {
+                match api.exchange(WsClientMessage::RequestPage(page)).await? {
+                    WsServerMessage::ResponsePage(Ok(ask_page)) => return Ok((ask_page, page)),
+                    WsServerMessage::ResponsePage(Err(next_page)) if next_page != page => {
+                        page = next_page;
+                        *api.lock_active_quest()? = Some((quest_id, page, started_at));
+                    }
+                    WsServerMessage::ResponsePage(Err(_)) => break,
+                    unexpected => {
+                        logging::error!("Unexpected reply on page request: {unexpected:?}");
+                        return Err(GeneralError::Unknown.into());
+                    }
+                }
+            }
+            Err(GameError::PageOutOfOrder)
+        })
     }
 
     fn answer_page(
         &self,
         answers: Box<[common::Answer]>,
-    ) -> impl std::future::Future<Output = Result<(), super::error::GameError>> + Send + Sync {
-        todo!()
+    ) -> impl Future<Output = Result<(), GameError>> + Send + Sync {
+        let api = self.clone();
+        SendWrapper::new(async move {
+            let (quest_id, page, started_at) =
+                api.active_quest()?.ok_or(GameError::NoActiveQuest)?;
+
+            match api
+                .exchange(WsClientMessage::RequestSubmit(page, answers))
+                .await?
+            {
+                WsServerMessage::ResponseSubmit(Ok(_)) => {
+                    *api.lock_active_quest()? = Some((quest_id, page + 1, started_at));
+                    Ok(())
+                }
+                WsServerMessage::ResponseSubmit(Err(reason)) => {
+                    logging::warn!("Answers were not accepted: {reason}");
+                    let timed_out = api
+                        .active_quest()?
+                        .is_some_and(|(_, active_page, _)| active_page > page);
+                    Err(if timed_out {
+                        GameError::PageTimeout
+                    } else {
+                        GameError::PageOutOfOrder
+                    })
+                }
+                unexpected => {
+                    logging::error!("Unexpected reply on submit: {unexpected:?}");
+                    Err(GeneralError::Unknown.into())
+                }
+            }
+        })
     }
 
-    fn finish_quest(
-        &self,
-    ) -> impl std::future::Future<Output = Result<(), super::error::GameError>> + Send + Sync {
-        todo!()
+    fn finish_quest(&self) -> impl Future<Output = Result<(), GameError>> + Send + Sync {
+        let api = self.clone();
+        SendWrapper::new(async move {
+            api.active_quest()?.ok_or(GameError::NoActiveQuest)?;
+            // server marks quest finished itself, once the last page is submitted
+            api.end_quest().await?;
+            Ok(())
+        })
     }
 }
@@ -1,10 +1,28 @@
-use frontend::{api::dummy::DummyApi, App};
-use leptos::{mount::mount_to_body, view};
+use frontend::{
+    api::{dummy::DummyApi, fixture_url, real::RealApi, Api, ApiKind},
+    App,
+};
+use leptos::{logging, mount::mount_to_body, view};
 
-fn main() {
-    console_error_panic_hook::set_once();
-    let api = DummyApi::new();
+fn mount<A: Api>(api: A) {
     mount_to_body(move || {
         view! { <App api /> }
     });
 }
+
+fn main() {
+    console_error_panic_hook::set_once();
+    match ApiKind::select() {
+        ApiKind::Real => mount(RealApi::new()),
+        ApiKind::Dummy => match fixture_url() {
+            Some(url) => wasm_bindgen_futures::spawn_local(async move {
+                let api = DummyApi::load_fixture(&url).await.unwrap_or_else(|err| {
+                    logging::error!("Failed to load fixture from {url}: {err}");
+                    DummyApi::new()
+                });
+                mount(api);
+            }),
+            None => mount(DummyApi::new()),
+        },
+    }
+}
@@ -0,0 +1,979 @@
+use common::{
+    serialize_quest_page, ImageRegion, Number, OpenedAnswer, PageCheck, QuestPageElement, Question,
+    Scoring, IMAGE_SCALE, MAX_POINTS,
+};
+use leptos::{component, ev, html, prelude::*, web_sys, IntoView};
+
+use super::{
+    page::{natural_size, region_shapes},
+    IconButton,
+};
+
+/// Page being built with forms. Every change is written back to the source right away,
+/// while the forms are only rendered again after changes of page structure, so inputs keep focus
+#[derive(Clone, Copy)]
+struct Draft {
+    page: StoredValue<Vec<QuestPageElement>>,
+    source: RwSignal<String>,
+    reshaped: Trigger,
+}
+
+impl Draft {
+    fn get<T>(self, element: usize, read: impl FnOnce(&QuestPageElement) -> T) -> T {
+        self.page.with_value(|page| read(&page[element]))
+    }
+
+    fn edit(self, element: usize, change: impl FnOnce(&mut QuestPageElement)) {
+        self.page.update_value(|page| change(&mut page[element]));
+        self.source
+            .set(self.page.with_value(|page| serialize_quest_page(page)));
+    }
+
+    fn edit_question(self, element: usize, change: impl FnOnce(&mut Question)) {
+        self.edit(element, |element| {
+            if let QuestPageElement::Question(question, _) = element {
+                change(question);
+            }
+        });
+    }
+
+    /// Change, that adds, removes or reorders something, so forms have to be rendered again
+    fn reshape_question(self, element: usize, change: impl FnOnce(&mut Question)) {
+        self.edit_question(element, change);
+        self.reshaped.notify();
+    }
+
+    fn edit_page(self, change: impl FnOnce(&mut Vec<QuestPageElement>)) {
+        self.page.update_value(change);
+        self.source
+            .set(self.page.with_value(|page| serialize_quest_page(page)));
+        self.reshaped.notify();
+    }
+}
+
+fn edit_vec<T>(items: &mut Box<[T]>, change: impl FnOnce(&mut Vec<T>)) {
+    let mut vec = std::mem::take(items).into_vec();
+    change(&mut vec);
+    *items = vec.into_boxed_slice();
+}
+
+/// Variants or items, the question is built from
+fn items(question: &Question) -> &[String] {
+    match question {
+        Question::Choice { variants, .. } | Question::MultipleChoice { variants, .. } => variants,
+        Question::Ordering { items } => items,
+        _ => &[],
+    }
+}
+
+fn items_mut(question: &mut Question) -> Option<&mut Box<[String]>> {
+    match question {
+        Question::Choice { variants, .. } | Question::MultipleChoice { variants, .. } => {
+            Some(variants)
+        }
+        Question::Ordering { items } => Some(items),
+        _ => None,
+    }
+}
+
+/// Moves correct answers after items were removed or swapped, `map` gives new index of the old one
+fn remap_correct(question: &mut Question, map: impl Fn(u32) -> Option<u32>) {
+    match question {
+        Question::Choice { correct, .. } => *correct = map(*correct).unwrap_or(0),
+        Question::MultipleChoice { correct, .. } => {
+            *correct = correct.iter().filter_map(|&no| map(no)).collect();
+        }
+        _ => {}
+    }
+}
+
+fn move_item_up(question: &mut Question, no: u32) {
+    let Some(above) = no.checked_sub(1) else {
+        return;
+    };
+    if let Some(items) = items_mut(question) {
+        items.swap(above as usize, no as usize);
+    }
+    remap_correct(question, |old| {
+        Some(match old {
+            _ if old == no => above,
+            _ if old == above => no,
+            _ => old,
+        })
+    });
+}
+
+fn remove_item(question: &mut Question, no: u32) {
+    if let Some(items) = items_mut(question) {
+        edit_vec(items, |items| {
+            items.remove(no as usize);
+        });
+    }
+    remap_correct(question, |old| match old.cmp(&no) {
+        core::cmp::Ordering::Less => Some(old),
+        core::cmp::Ordering::Equal => None,
+        core::cmp::Ordering::Greater => Some(old - 1),
+    });
+}
+
+fn is_correct(question: &Question, no: u32) -> bool {
+    match question {
+        Question::Choice { correct, .. } => *correct == no,
+        Question::MultipleChoice { correct, .. } => correct.contains(&no),
+        _ => false,
+    }
+}
+
+fn toggle_correct(question: &mut Question, no: u32) {
+    match question {
+        Question::Choice { correct, .. } => *correct = no,
+        Question::MultipleChoice { correct, .. } => edit_vec(correct, |correct| {
+            match correct.iter().position(|&correct| correct == no) {
+                Some(position) => {
+                    correct.remove(position);
+                }
+                None => {
+                    correct.push(no);
+                    correct.sort_unstable();
+                }
+            }
+        }),
+        _ => {}
+    }
+}
+
+fn text_input(value: String, write: impl Fn(String) + 'static) -> impl IntoView {
+    view! { <input type="text" prop:value=value on:input=move |ev| write(event_target_value(&ev)) /> }
+}
+
+/// Number input, that calls `write` once a number is entered
+fn number_input<N: ToString + core::str::FromStr + 'static>(
+    value: N,
+    write: impl Fn(N) + 'static,
+) -> impl IntoView {
+    view! {
+        <input
+            type="number"
+            prop:value=value.to_string()
+            on:change=move |ev| {
+                if let Ok(value) = event_target_value(&ev).trim().parse() {
+                    write(value);
+                }
+            }
+        />
+    }
+}
+
+#[component]
+fn ScoringBuilder(draft: Draft, element: usize) -> impl IntoView {
+    let scoring = draft.get(element, |element| match element {
+        QuestPageElement::Question(_, scoring) => *scoring,
+        QuestPageElement::Text(_) => Scoring::default(),
+    });
+    let edit = move |change: fn(&mut Scoring, u32)| {
+        move |value| {
+            draft.edit(element, |element| {
+                if let QuestPageElement::Question(_, scoring) = element {
+                    // larger ones are rejected by the parser
+                    change(scoring, value.min(MAX_POINTS));
+                }
+            });
+        }
+    };
+    let points = number_input(
+        scoring.points,
+        edit(|scoring, points| scoring.points = points),
+    );
+    let penalty = number_input(
+        scoring.penalty,
+        edit(|scoring, penalty| scoring.penalty = penalty),
+    );
+    view! {
+        <div class="scoring">
+            <label>"Points " {points}</label>
+            <label>"Penalty " {penalty}</label>
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=scoring.partial
+                    on:change=move |ev| {
+                        let partial = event_target_checked(&ev);
+                        draft
+                            .edit(element, |element| {
+                                if let QuestPageElement::Question(_, scoring) = element {
+                                    scoring.partial = partial;
+                                }
+                            });
+                    }
+                />
+                " Partial answers count"
+            </label>
+        </div>
+    }
+}
+
+/// Variants of choice questions with toggles for correct ones, or items of ordering question
+#[component]
+fn ItemsBuilder(draft: Draft, element: usize) -> impl IntoView {
+    let (shown, toggle) = draft.get(element, |element| match element {
+        QuestPageElement::Question(question, _) => {
+            let toggle = match question {
+                Question::Choice { .. } => Some("radio"),
+                Question::MultipleChoice { .. } => Some("checkbox"),
+                _ => None,
+            };
+            let shown = items(question)
+                .iter()
+                .zip(0..)
+                .map(|(item, no)| (item.clone(), is_correct(question, no)))
+                .collect::<Vec<_>>();
+            (shown, toggle)
+        }
+        QuestPageElement::Text(_) => (Vec::new(), None),
+    });
+    // every question needs two of them
+    let last = shown.len() <= 2;
+
+    let rows = shown
+        .into_iter()
+        .zip(0u32..)
+        .map(|((item, correct), no)| {
+            let toggle = toggle.map(|toggle| {
+                view! {
+                    <input
+                        type=toggle
+                        prop:checked=correct
+                        on:change=move |_| {
+                            draft.reshape_question(element, |question| toggle_correct(question, no))
+                        }
+                    />
+                }
+            });
+            let write = move |item| {
+                draft.edit_question(element, |question| {
+                    if let Some(items) = items_mut(question) {
+                        items[no as usize] = item;
+                    }
+                })
+            };
+            let first = no == 0;
+            view! {
+                <li>
+                    {toggle}
+                    {text_input(item, write)}
+                    <IconButton
+                        text="Up"
+                        icon=icondata::AiArrowUpOutlined
+                        disabled=first
+                        on_click=move || {
+                            draft.reshape_question(element, |question| move_item_up(question, no))
+                        }
+                    />
+                    <IconButton
+                        text="Remove"
+                        icon=icondata::AiDeleteOutlined
+                        disabled=last
+                        on_click=move || {
+                            draft.reshape_question(element, |question| remove_item(question, no))
+                        }
+                    />
+                </li>
+            }
+        })
+        .collect_view();
+    view! {
+        <ol>{rows}</ol>
+        <IconButton
+            text="Add"
+            icon=icondata::AiPlusOutlined
+            on_click=move || {
+                draft
+                    .reshape_question(
+                        element,
+                        |question| {
+                            if let Some(items) = items_mut(question) {
+                                edit_vec(
+                                    items,
+                                    |items| items.push(format!("Item {}", items.len() + 1)),
+                                );
+                            }
+                        },
+                    )
+            }
+        />
+    }
+}
+
+#[component]
+fn MatchingBuilder(draft: Draft, element: usize) -> impl IntoView {
+    let pairs = draft.get(element, |element| match element {
+        QuestPageElement::Question(Question::Matching { pairs }, _) => pairs.to_vec(),
+        _ => Vec::new(),
+    });
+    // every question needs two pairs
+    let last = pairs.len() <= 2;
+
+    let edit_pairs = move |change: Box<dyn FnOnce(&mut Vec<(String, String)>)>, reshape: bool| {
+        let change = |question: &mut Question| {
+            if let Question::Matching { pairs } = question {
+                edit_vec(pairs, change);
+            }
+        };
+        if reshape {
+            draft.reshape_question(element, change);
+        } else {
+            draft.edit_question(element, change);
+        }
+    };
+    let rows = pairs
+        .into_iter()
+        .enumerate()
+        .map(|(no, (left, right))| {
+            let left = text_input(left, move |left| {
+                edit_pairs(Box::new(move |pairs| pairs[no].0 = left), false)
+            });
+            let right = text_input(right, move |right| {
+                edit_pairs(Box::new(move |pairs| pairs[no].1 = right), false)
+            });
+            view! {
+                <li>
+                    {left}
+                    " — "
+                    {right}
+                    <IconButton
+                        text="Remove"
+                        icon=icondata::AiDeleteOutlined
+                        disabled=last
+                        on_click=move || {
+                            edit_pairs(
+                                Box::new(move |pairs| {
+                                    pairs.remove(no);
+                                }),
+                                true,
+                            )
+                        }
+                    />
+                </li>
+            }
+        })
+        .collect_view();
+    view! {
+        <ol>{rows}</ol>
+        <IconButton
+            text="Add"
+            icon=icondata::AiPlusOutlined
+            on_click=move || {
+                edit_pairs(
+                    Box::new(|pairs| {
+                        let no = pairs.len() + 1;
+                        pairs.push((format!("Left {no}"), format!("Right {no}")));
+                    }),
+                    true,
+                )
+            }
+        />
+    }
+}
+
+#[component]
+fn OpenedBuilder(draft: Draft, element: usize) -> impl IntoView {
+    let answers = draft.get(element, |element| match element {
+        QuestPageElement::Question(Question::Opened(answers), _) => answers.to_vec(),
+        _ => Vec::new(),
+    });
+    // every question needs an answer
+    let last = answers.len() <= 1;
+
+    let edit_answer = move |no: usize, change: Box<dyn FnOnce(&mut OpenedAnswer)>| {
+        draft.edit_question(element, |question| {
+            if let Question::Opened(answers) = question {
+                change(&mut answers[no]);
+            }
+        });
+    };
+    let rows = answers
+        .into_iter()
+        .enumerate()
+        .map(|(no, answer)| {
+            let kind = match answer {
+                OpenedAnswer::Text { .. } => "text",
+                OpenedAnswer::Regex(_) => "regex",
+                OpenedAnswer::Number { .. } => "number",
+            };
+            let fields = match answer {
+                OpenedAnswer::Text { text, typos } => {
+                    let text = text_input(text, move |text| {
+                        edit_answer(
+                            no,
+                            Box::new(move |answer| {
+                                if let OpenedAnswer::Text { text: old, .. } = answer {
+                                    *old = text;
+                                }
+                            }),
+                        )
+                    });
+                    let typos = number_input(typos, move |typos| {
+                        edit_answer(
+                            no,
+                            Box::new(move |answer| {
+                                if let OpenedAnswer::Text { typos: old, .. } = answer {
+                                    *old = typos;
+                                }
+                            }),
+                        )
+                    });
+                    view! {
+                        {text}
+                        <label>" Typos forgiven " {typos}</label>
+                    }
+                    .into_any()
+                }
+                OpenedAnswer::Regex(pattern) => text_input(pattern, move |pattern| {
+                    edit_answer(
+                        no,
+                        Box::new(move |answer| *answer = OpenedAnswer::Regex(pattern)),
+                    )
+                })
+                .into_any(),
+                OpenedAnswer::Number { value, tolerance } => {
+                    let value = number_input(value.0, move |value| {
+                        edit_answer(
+                            no,
+                            Box::new(move |answer| {
+                                if let OpenedAnswer::Number { value: old, .. } = answer {
+                                    *old = Number(value);
+                                }
+                            }),
+                        )
+                    });
+                    let tolerance = number_input(tolerance.0, move |tolerance| {
+                        edit_answer(
+                            no,
+                            Box::new(move |answer| {
+                                if let OpenedAnswer::Number { tolerance: old, .. } = answer {
+                                    *old = Number(tolerance);
+                                }
+                            }),
+                        )
+                    });
+                    view! {
+                        {value}
+                        <label>" ± " {tolerance}</label>
+                    }
+                    .into_any()
+                }
+            };
+            view! {
+                <li>
+                    <select
+                        prop:value=kind
+                        on:change=move |ev| {
+                            let answer = match event_target_value(&ev).as_str() {
+                                "regex" => OpenedAnswer::Regex(".*".to_owned()),
+                                "number" => {
+                                    OpenedAnswer::Number {
+                                        value: Number(0.0),
+                                        tolerance: Number(0.0),
+                                    }
+                                }
+                                _ => {
+                                    OpenedAnswer::Text {
+                                        text: "Answer".to_owned(),
+                                        typos: 0,
+                                    }
+                                }
+                            };
+                            draft
+                                .reshape_question(
+                                    element,
+                                    |question| {
+                                        if let Question::Opened(answers) = question {
+                                            answers[no] = answer;
+                                        }
+                                    },
+                                );
+                        }
+                    >
+                        <option value="text">"Text"</option>
+                        <option value="regex">"Regex"</option>
+                        <option value="number">"Number"</option>
+                    </select>
+                    {fields}
+                    <IconButton
+                        text="Remove"
+                        icon=icondata::AiDeleteOutlined
+                        disabled=last
+                        on_click=move || {
+                            draft
+                                .reshape_question(
+                                    element,
+                                    |question| {
+                                        if let Question::Opened(answers) = question {
+                                            edit_vec(answers, |answers| { answers.remove(no); });
+                                        }
+                                    },
+                                )
+                        }
+                    />
+                </li>
+            }
+        })
+        .collect_view();
+    view! {
+        <ul>{rows}</ul>
+        <IconButton
+            text="Add"
+            icon=icondata::AiPlusOutlined
+            on_click=move || {
+                draft
+                    .reshape_question(
+                        element,
+                        |question| {
+                            if let Question::Opened(answers) = question {
+                                edit_vec(
+                                    answers,
+                                    |answers| {
+                                        answers
+                                            .push(OpenedAnswer::Text {
+                                                text: "Answer".to_owned(),
+                                                typos: 0,
+                                            })
+                                    },
+                                );
+                            }
+                        },
+                    )
+            }
+        />
+    }
+}
+
+fn percents(value: u32) -> String {
+    format!("{}%", f64::from(value) * 100.0 / f64::from(IMAGE_SCALE))
+}
+
+fn describe_region(region: &ImageRegion) -> String {
+    match region {
+        &ImageRegion::PixelRectangle {
+            left,
+            top,
+            width,
+            height,
+        } => format!("Rectangle at {left}px, {top}px, {width}px by {height}px"),
+        &ImageRegion::Rectangle {
+            left,
+            top,
+            width,
+            height,
+        } => format!(
+            "Rectangle at {}, {}, {} by {}",
+            percents(left),
+            percents(top),
+            percents(width),
+            percents(height)
+        ),
+        &ImageRegion::Circle { x, y, radius } => format!(
+            "Circle at {}, {}, radius {}",
+            percents(x),
+            percents(y),
+            percents(radius)
+        ),
+        ImageRegion::Polygon(points) => format!("Polygon of {} points", points.len()),
+    }
+}
+
+/// Point of the mouse over `image`, in [`IMAGE_SCALE`] units
+fn image_point(ev: &ev::MouseEvent, image: &web_sys::Element) -> (u32, u32) {
+    let rect = image.get_bounding_client_rect();
+    let scale = |offset: f64, size: f64| {
+        ((offset / size).clamp(0.0, 1.0) * f64::from(IMAGE_SCALE)).round() as u32
+    };
+    (
+        scale(f64::from(ev.client_x()) - rect.left(), rect.width()),
+        scale(f64::from(ev.client_y()) - rect.top(), rect.height()),
+    )
+}
+
+/// Left, top, width and height of the rectangle between two corners
+fn bounds((x1, y1): (u32, u32), (x2, y2): (u32, u32)) -> (u32, u32, u32, u32) {
+    (x1.min(x2), y1.min(y2), x1.abs_diff(x2), y1.abs_diff(y2))
+}
+
+/// Image source and regions, new rectangle regions are added by dragging over the image
+#[component]
+fn ImageBuilder(draft: Draft, element: usize) -> impl IntoView {
+    let (src, regions) = draft.get(element, |element| match element {
+        QuestPageElement::Question(Question::Image { src, regions }, _) => {
+            (src.clone(), regions.to_vec())
+        }
+        _ => (String::new(), Vec::new()),
+    });
+    let image = NodeRef::<html::Div>::new();
+    let size = RwSignal::new(None);
+    // corners of the rectangle being dragged
+    let drag = RwSignal::new(None::<((u32, u32), (u32, u32))>);
+
+    let point =
+        move |ev: &ev::MouseEvent| image.get_untracked().map(|image| image_point(ev, &image));
+    let dragged = move || {
+        drag.get().map(|(start, end)| {
+            let (left, top, width, height) = bounds(start, end);
+            view! { <rect class="dragged" x=left y=top width=width height=height /> }
+        })
+    };
+    // every question needs a region
+    let last = regions.len() <= 1;
+    let region_list = regions
+        .iter()
+        .enumerate()
+        .map(|(no, region)| {
+            view! {
+                <li>
+                    {describe_region(region)}
+                    <IconButton
+                        text="Remove"
+                        icon=icondata::AiDeleteOutlined
+                        disabled=last
+                        on_click=move || {
+                            draft
+                                .reshape_question(
+                                    element,
+                                    |question| {
+                                        if let Question::Image { regions, .. } = question {
+                                            edit_vec(regions, |regions| { regions.remove(no); });
+                                        }
+                                    },
+                                )
+                        }
+                    />
+                </li>
+            }
+        })
+        .collect_view();
+    view! {
+        <label>
+            "Image "
+            <input
+                type="text"
+                prop:value=src.clone()
+                on:change=move |ev| {
+                    let new_src = event_target_value(&ev);
+                    draft
+                        .reshape_question(
+                            element,
+                            |question| {
+                                if let Question::Image { src, .. } = question {
+                                    *src = new_src;
+                                }
+                            },
+                        )
+                }
+            />
+        </label>
+        <div
+            class="image-question region-picker"
+            node_ref=image
+            on:mousedown=move |ev| {
+                ev.prevent_default();
+                drag.set(point(&ev).map(|start| (start, start)));
+            }
+            on:mousemove=move |ev| {
+                if let Some(end) = point(&ev) {
+                    drag.update(|drag| {
+                        if let Some((_, old)) = drag {
+                            *old = end;
+                        }
+                    });
+                }
+            }
+            on:mouseup=move |_| {
+                let Some((start, end)) = drag.get() else {
+                    return;
+                };
+                drag.set(None);
+                let (left, top, width, height) = bounds(start, end);
+                if width == 0 || height == 0 {
+                    return;
+                }
+                let region = ImageRegion::Rectangle {
+                    left,
+                    top,
+                    width,
+                    height,
+                };
+                draft
+                    .reshape_question(
+                        element,
+                        |question| {
+                            if let Question::Image { regions, .. } = question {
+                                edit_vec(regions, |regions| regions.push(region));
+                            }
+                        },
+                    )
+            }
+            on:mouseleave=move |_| drag.set(None)
+        >
+            <img src=src draggable="false" on:load=move |ev| size.set(natural_size(&ev)) />
+            <svg viewBox=format!("0 0 {IMAGE_SCALE} {IMAGE_SCALE}") preserveAspectRatio="none">
+                {move || region_shapes(&regions, size.get())}
+                {dragged}
+            </svg>
+        </div>
+        <p>"Drag over the image to add a rectangle region"</p>
+        <ul>{region_list}</ul>
+    }
+}
+
+#[component]
+fn ClozeBuilder(draft: Draft, element: usize) -> impl IntoView {
+    let (text, blanks) = draft.get(element, |element| match element {
+        QuestPageElement::Question(Question::Cloze { text, blanks }, _) => {
+            (text.to_vec(), blanks.to_vec())
+        }
+        _ => (Vec::new(), Vec::new()),
+    });
+
+    let edit_cloze =
+        move |change: Box<dyn FnOnce(&mut Box<[String]>, &mut Box<[Box<[String]>]>)>| {
+            draft.edit_question(element, |question| {
+                if let Question::Cloze { text, blanks } = question {
+                    change(text, blanks);
+                }
+            })
+        };
+    let parts = text
+        .into_iter()
+        .enumerate()
+        .map(|(no, part)| {
+            let blank = blanks.get(no).map(|blank| {
+                // answers of a blank are written the same way, as in the source
+                text_input(blank.join("|"), move |blank| {
+                    let answers = blank
+                        .split('|')
+                        .map(str::trim)
+                        .filter(|answer| !answer.is_empty())
+                        .map(str::to_owned)
+                        .collect();
+                    edit_cloze(Box::new(move |_, blanks| blanks[no] = answers));
+                })
+            });
+            let part = text_input(part, move |part| {
+                edit_cloze(Box::new(move |text, _| text[no] = part))
+            });
+            view! {
+                {part}
+                {blank.map(|blank| view! { <span class="blank">{blank}</span> })}
+            }
+        })
+        .collect_view();
+    view! {
+        <p>"Text with blanks, answers of a blank are separated with |"</p>
+        <div class="cloze">{parts}</div>
+    }
+}
+
+#[component]
+fn ElementBuilder(draft: Draft, element: usize) -> impl IntoView {
+    let form = match draft.get(element, QuestPageElement::clone) {
+        QuestPageElement::Text(text) => {
+            let write = move |ev: ev::Event| {
+                let text = event_target_value(&ev);
+                draft.edit(element, |element| {
+                    *element = QuestPageElement::Text(text.into());
+                });
+            };
+            view! { <textarea class="text-builder" prop:value=text.to_string() on:input=write /> }
+                .into_any()
+        }
+        QuestPageElement::Question(question, _) => {
+            let form = match question {
+                Question::Choice { .. }
+                | Question::MultipleChoice { .. }
+                | Question::Ordering { .. } => view! { <ItemsBuilder draft element /> }.into_any(),
+                Question::Matching { .. } => view! { <MatchingBuilder draft element /> }.into_any(),
+                Question::Opened(_) => view! { <OpenedBuilder draft element /> }.into_any(),
+                Question::Image { .. } => view! { <ImageBuilder draft element /> }.into_any(),
+                Question::Cloze { .. } => view! { <ClozeBuilder draft element /> }.into_any(),
+            };
+            // cloze question is written inline, so it can not have scoring of its own
+            let scoring = (!matches!(question, Question::Cloze { .. }))
+                .then(|| view! { <ScoringBuilder draft element /> });
+            view! {
+                <div class="question">
+                    {form}
+                    {scoring}
+                </div>
+            }
+            .into_any()
+        }
+    };
+    view! {
+        <div class="element-builder">
+            {form}
+            <IconButton
+                text="Delete"
+                icon=icondata::AiDeleteOutlined
+                on_click=move || {
+                    draft
+                        .edit_page(|page| {
+                            page.remove(element);
+                        })
+                }
+            />
+        </div>
+    }
+}
+
+/// Elements, that can be added to the page
+const NEW_ELEMENTS: &[(&str, fn() -> QuestPageElement)] = &[
+    ("Text", || QuestPageElement::Text("Text".into())),
+    ("Choice", || {
+        QuestPageElement::Question(
+            Question::Choice {
+                variants: ["Yes", "No"].map(String::from).into(),
+                correct: 0,
+            },
+            Scoring::default(),
+        )
+    }),
+    ("Multiple choice", || {
+        QuestPageElement::Question(
+            Question::MultipleChoice {
+                variants: ["First", "Second"].map(String::from).into(),
+                correct: [0].into(),
+            },
+            Scoring::default(),
+        )
+    }),
+    ("Opened", || {
+        QuestPageElement::Question(
+            Question::Opened(
+                [OpenedAnswer::Text {
+                    text: "Answer".to_owned(),
+                    typos: 0,
+                }]
+                .into(),
+            ),
+            Scoring::default(),
+        )
+    }),
+    ("Image", || {
+        QuestPageElement::Question(
+            Question::Image {
+                src: "image.png".to_owned(),
+                regions: [ImageRegion::Rectangle {
+                    left: 0,
+                    top: 0,
+                    width: IMAGE_SCALE / 2,
+                    height: IMAGE_SCALE / 2,
+                }]
+                .into(),
+            },
+            Scoring::default(),
+        )
+    }),
+    ("Ordering", || {
+        QuestPageElement::Question(
+            Question::Ordering {
+                items: ["First", "Second"].map(String::from).into(),
+            },
+            Scoring::default(),
+        )
+    }),
+    ("Matching", || {
+        QuestPageElement::Question(
+            Question::Matching {
+                pairs: [("Left 1", "Right 1"), ("Left 2", "Right 2")]
+                    .map(|(left, right)| (left.to_owned(), right.to_owned()))
+                    .into(),
+            },
+            Scoring::default(),
+        )
+    }),
+];
+
+/// Forms for every element of the page, that are read from its source and write it back.
+/// Page has to parse to be built this way
+#[component]
+pub fn PageBuilder(source: RwSignal<String>, check: Memo<PageCheck>) -> impl IntoView {
+    let page = check.with_untracked(|check| {
+        check
+            .page
+            .as_ref()
+            .map(|page| page.to_vec())
+            .unwrap_or_default()
+    });
+    let draft = Draft {
+        page: StoredValue::new(page),
+        source,
+        reshaped: Trigger::new(),
+    };
+
+    let elements = move || {
+        draft.reshaped.track();
+        (0..draft.page.with_value(Vec::len))
+            .map(|element| view! { <ElementBuilder draft element /> })
+            .collect_view()
+    };
+    let new_elements = NEW_ELEMENTS
+        .iter()
+        .map(|&(name, new)| {
+            view! {
+                <IconButton
+                    text=name
+                    icon=icondata::AiPlusOutlined
+                    on_click=move || draft.edit_page(|page| page.push(new()))
+                />
+            }
+        })
+        .collect_view();
+    view! {
+        <div class="page-builder">
+            {elements}
+            <div class="new-elements">{new_elements}</div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod builder_tests {
+    use super::NEW_ELEMENTS;
+    use crate::api::dummy::Fixture;
+    use common::{parse_quest_page, serialize_quest_page, QuestPageElement};
+
+    /// Builder overwrites the source with the serialized page, that must read back the same
+    fn assert_round_trip(page: &[QuestPageElement]) {
+        let source = serialize_quest_page(page);
+        let reparsed = parse_quest_page(source.as_str())
+            .unwrap_or_else(|err| panic!("{err:?} in serialized page:\n{source}"));
+        assert_eq!(*reparsed, *page, "serialized page:\n{source}");
+    }
+
+    #[test]
+    fn fixture_pages_round_trip() {
+        let fixture: Fixture =
+            serde_json::from_str(include_str!("../../fixtures/demo.json")).unwrap();
+        let sources = fixture
+            .users
+            .into_iter()
+            .flat_map(|user| user.quests)
+            .flat_map(|quest| quest.pages);
+        for source in sources {
+            let page = parse_quest_page(source.as_str())
+                .unwrap_or_else(|err| panic!("{err:?} in fixture page:\n{source}"));
+            assert_round_trip(&page);
+        }
+    }
+
+    #[test]
+    fn new_elements_round_trip() {
+        for (_, new) in NEW_ELEMENTS {
+            assert_round_trip(&[new()]);
+        }
+        let page = NEW_ELEMENTS
+            .iter()
+            .map(|(_, new)| new())
+            .collect::<Vec<_>>();
+        assert_round_trip(&page);
+    }
+}
@@ -0,0 +1,401 @@
+use std::{borrow::Cow, collections::HashSet};
+
+use ::markdown::{mdast::Node, to_mdast, ParseOptions};
+use ammonia::UrlRelative;
+use leptos::{component, prelude::*, IntoView};
+
+/// Tags of rendered markdown, with their allowed attributes
+const MARKDOWN_TAGS: &[(&str, &[&str])] = &[
+    ("p", &[]),
+    ("h1", &[]),
+    ("h2", &[]),
+    ("h3", &[]),
+    ("h4", &[]),
+    ("h5", &[]),
+    ("h6", &[]),
+    ("blockquote", &[]),
+    ("em", &[]),
+    ("strong", &[]),
+    ("del", &[]),
+    ("ol", &["start"]),
+    ("ul", &[]),
+    ("li", &[]),
+    ("input", &["type", "disabled", "checked"]),
+    ("table", &[]),
+    ("tr", &[]),
+    ("th", &[]),
+    ("td", &[]),
+    ("pre", &[]),
+    ("code", &["class"]),
+    ("span", &["class"]),
+    ("hr", &[]),
+    ("a", &["href", "title"]),
+];
+/// Tags, that raw html in page text may use, with their allowed attributes
+const RAW_HTML_TAGS: &[(&str, &[&str])] = &[
+    ("img", &["src", "alt", "title", "width", "height"]),
+    (
+        "video",
+        &[
+            "src", "poster", "controls", "loop", "muted", "width", "height",
+        ],
+    ),
+    ("audio", &["src", "controls", "loop"]),
+    ("source", &["src", "type"]),
+    ("br", &[]),
+];
+/// Schemes of links and media, relative urls are not allowed either
+const URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Languages, code blocks are highlighted for: names, line comment start and keywords
+const LANGUAGES: &[(&[&str], &str, &str)] = &[
+    (
+        &["rust", "rs"],
+        "//",
+        "as async await break const continue crate dyn else enum extern false fn for if impl \
+            in let loop match mod move mut pub ref return self Self static struct super trait \
+            true type unsafe use where while",
+    ),
+    (
+        &["python", "py"],
+        "#",
+        "and as assert async await break class continue def del elif else except False \
+            finally for from global if import in is lambda None nonlocal not or pass raise \
+            return True try while with yield",
+    ),
+    (
+        &["javascript", "js", "typescript", "ts"],
+        "//",
+        "async await break case catch class const continue default delete do else export \
+            extends false finally for function if import in instanceof let new null return \
+            switch this throw true try typeof undefined var void while yield",
+    ),
+    (
+        &["c", "cpp", "c++", "java", "cs", "c#", "go"],
+        "//",
+        "auto bool break case catch char class const continue default do double else enum \
+            extern false float for func if import int long namespace new null nullptr package \
+            private protected public return short signed sizeof static struct switch this throw \
+            true try typedef union unsigned using var void while",
+    ),
+    (
+        &["sh", "bash", "shell"],
+        "#",
+        "case do done elif else esac export fi for function if in local return then while",
+    ),
+];
+
+/// Text of a page, rendered from markdown.
+/// Raw html is sanitized, only images, videos and audio are kept
+#[component]
+pub fn Markdown(#[prop(into)] source: Signal<String>) -> impl IntoView {
+    view! { <div class="markdown" inner_html=move || source.with(|source| render_markdown(source)) /> }
+}
+
+/// Renders markdown (with github extensions) to html, that is safe to insert into the page
+pub fn render_markdown(source: &str) -> String {
+    // only mdx parsing can fail, and it is off
+    let Ok(root) = to_mdast(source, &ParseOptions::gfm()) else {
+        return escape(source);
+    };
+    let mut html = String::new();
+    render_node(&root, &mut html);
+    sanitizer().clean(&html).to_string()
+}
+
+/// Allow-list of tags, attributes and url schemes, that rendered html is cleaned with.
+/// Raw html is passed to it as is, so it is parsed as a browser would, and unclosed tags are balanced
+fn sanitizer() -> ammonia::Builder<'static> {
+    let mut builder = ammonia::Builder::empty();
+    for &(tag, attributes) in MARKDOWN_TAGS.iter().chain(RAW_HTML_TAGS) {
+        builder
+            .add_tags([tag])
+            .add_tag_attributes(tag, attributes.iter().copied());
+    }
+    builder
+        .url_schemes(URL_SCHEMES.iter().copied().collect::<HashSet<_>>())
+        .url_relative(UrlRelative::Deny)
+        .link_rel(Some("noopener noreferrer"))
+        .set_tag_attribute_value("a", "target", "_blank")
+        .clean_content_tags(["script", "style"].into_iter().collect())
+        .attribute_filter(|tag, attribute, value| match (tag, attribute) {
+            // task list checkboxes and highlighted code, see `render_node` and `highlight`
+            ("input", "type") => (value == "checkbox").then_some(Cow::Borrowed(value)),
+            ("code", "class") => value
+                .starts_with("language-")
+                .then_some(Cow::Borrowed(value)),
+            ("span", "class") => value.starts_with("hl-").then_some(Cow::Borrowed(value)),
+            _ => Some(Cow::Borrowed(value)),
+        });
+    builder
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn render_children(node: &Node, html: &mut String) {
+    for child in node.children().into_iter().flatten() {
+        render_node(child, html);
+    }
+}
+
+fn wrap(tag: &str, node: &Node, html: &mut String) {
+    html.push_str(&format!("<{tag}>"));
+    render_children(node, html);
+    html.push_str(&format!("</{tag}>"));
+}
+
+fn render_node(node: &Node, html: &mut String) {
+    match node {
+        Node::Paragraph(_) => wrap("p", node, html),
+        Node::Heading(heading) => wrap(&format!("h{}", heading.depth.clamp(1, 6)), node, html),
+        Node::Blockquote(_) => wrap("blockquote", node, html),
+        Node::Emphasis(_) => wrap("em", node, html),
+        Node::Strong(_) => wrap("strong", node, html),
+        Node::Delete(_) => wrap("del", node, html),
+        Node::List(list) => match (list.ordered, list.start) {
+            (true, Some(start)) if start != 1 => {
+                html.push_str(&format!("<ol start=\"{start}\">"));
+                render_children(node, html);
+                html.push_str("</ol>");
+            }
+            (true, _) => wrap("ol", node, html),
+            (false, _) => wrap("ul", node, html),
+        },
+        Node::ListItem(item) => {
+            html.push_str("<li>");
+            match item.checked {
+                Some(true) => html.push_str("<input type=\"checkbox\" disabled checked> "),
+                Some(false) => html.push_str("<input type=\"checkbox\" disabled> "),
+                None => {}
+            }
+            render_children(node, html);
+            html.push_str("</li>");
+        }
+        Node::Table(_) => {
+            html.push_str("<table>");
+            for (i, row) in node.children().into_iter().flatten().enumerate() {
+                // first row is the header
+                let cell = if i == 0 { "th" } else { "td" };
+                html.push_str("<tr>");
+                for column in row.children().into_iter().flatten() {
+                    wrap(cell, column, html);
+                }
+                html.push_str("</tr>");
+            }
+            html.push_str("</table>");
+        }
+        Node::Text(text) => html.push_str(&escape(&text.value)),
+        Node::InlineCode(code) => html.push_str(&format!("<code>{}</code>", escape(&code.value))),
+        Node::InlineMath(math) => html.push_str(&format!("<code>{}</code>", escape(&math.value))),
+        Node::Code(code) => {
+            let lang = code.lang.as_deref();
+            let class = lang.map_or(String::new(), |lang| {
+                format!(" class=\"language-{}\"", escape(lang))
+            });
+            let code = highlight(&code.value, lang);
+            html.push_str(&format!("<pre><code{class}>{code}</code></pre>"));
+        }
+        Node::Math(math) => {
+            html.push_str(&format!("<pre><code>{}</code></pre>", escape(&math.value)))
+        }
+        Node::Break(_) => html.push_str("<br>"),
+        Node::ThematicBreak(_) => html.push_str("<hr>"),
+        // urls are checked by the sanitizer
+        Node::Link(link) => {
+            html.push_str(&format!("<a href=\"{}\"", escape(&link.url)));
+            if let Some(title) = &link.title {
+                html.push_str(&format!(" title=\"{}\"", escape(title)));
+            }
+            html.push('>');
+            render_children(node, html);
+            html.push_str("</a>");
+        }
+        Node::Image(image) => {
+            html.push_str(&format!(
+                "<img src=\"{}\" alt=\"{}\"",
+                escape(&image.url),
+                escape(&image.alt)
+            ));
+            if let Some(title) = &image.title {
+                html.push_str(&format!(" title=\"{}\"", escape(title)));
+            }
+            html.push('>');
+        }
+        Node::ImageReference(image) => html.push_str(&escape(&image.alt)),
+        Node::Html(raw) => html.push_str(&raw.value),
+        // root, references and everything else keep just their text
+        _ => render_children(node, html),
+    }
+}
+
+/// Escaped code, with keywords, strings, comments and numbers wrapped into `hl-*` classes
+/// for known languages
+fn highlight(code: &str, lang: Option<&str>) -> String {
+    let language = lang.map(str::to_ascii_lowercase).and_then(|lang| {
+        LANGUAGES
+            .iter()
+            .find(|(names, ..)| names.contains(&lang.as_str()))
+    });
+    let Some(&(_, comment, keywords)) = language else {
+        return escape(code);
+    };
+
+    let mut html = String::new();
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        let word_len = |rest: &str, part: fn(char) -> bool| {
+            rest.find(|c: char| !part(c)).unwrap_or(rest.len())
+        };
+        let (class, len) = if rest.starts_with(comment) {
+            (Some("comment"), rest.find('\n').unwrap_or(rest.len()))
+        } else if let Some(len) = (c == '"' || c == '\'')
+            .then(|| string_len(rest, c))
+            .flatten()
+        {
+            (Some("string"), len)
+        } else if c.is_ascii_digit() {
+            let len = word_len(rest, |c| c.is_ascii_alphanumeric() || c == '.' || c == '_');
+            (Some("number"), len)
+        } else if c.is_alphabetic() || c == '_' {
+            let len = word_len(rest, |c| c.is_alphanumeric() || c == '_');
+            let keyword = keywords
+                .split_whitespace()
+                .any(|keyword| keyword == &rest[..len]);
+            (keyword.then_some("keyword"), len)
+        } else {
+            (None, c.len_utf8())
+        };
+
+        let token = escape(&rest[..len]);
+        match class {
+            Some(class) => html.push_str(&format!("<span class=\"hl-{class}\">{token}</span>")),
+            None => html.push_str(&token),
+        }
+        rest = &rest[len..];
+    }
+    html
+}
+
+/// Length of a string literal at the start of `source`, if it is closed on the same line
+fn string_len(source: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in source.char_indices().skip(1) {
+        match c {
+            '\n' => return None,
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == quote => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod markdown_tests {
+    use super::render_markdown;
+
+    #[test]
+    fn event_handlers_dropped() {
+        let html = render_markdown(r#"<img src="https://example.com/a.png" onerror="alert(1)">"#);
+        assert!(
+            html.contains(r#"src="https://example.com/a.png""#),
+            "{html}"
+        );
+        assert!(!html.contains("onerror"), "{html}");
+
+        let html = render_markdown(
+            r#"<video src="https://example.com/a.mp4" onplay=alert(1) controls></video>"#,
+        );
+        assert!(!html.contains("onplay"), "{html}");
+        assert!(html.contains("controls"), "{html}");
+    }
+
+    #[test]
+    fn quoted_angle_bracket() {
+        let html = render_markdown(r#"<img alt="a>b" src="https://example.com/a.png">"#);
+        assert!(html.contains(r#"alt="a&gt;b""#), "{html}");
+        assert!(
+            html.contains(r#"src="https://example.com/a.png""#),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn unsafe_urls_dropped() {
+        for source in [
+            "[link](javascript:alert(1))",
+            "[link](JavaScript:alert(1))",
+            "![image](data:image/png;base64,AAAA)",
+            r#"<img src="data:image/svg+xml,<svg onload=alert(1)>">"#,
+            r#"<audio src="javascript:alert(1)"></audio>"#,
+            r#"<video src="//evil.example.com/a.mp4"></video>"#,
+            "[link](/relative/path)",
+        ] {
+            let html = render_markdown(source);
+            for unsafe_part in ["javascript", "JavaScript", "data:", "evil", "relative"] {
+                assert!(
+                    !html.contains(unsafe_part),
+                    "{source} is rendered as {html}"
+                );
+            }
+        }
+
+        let html = render_markdown("[link](https://example.com)");
+        assert!(html.contains(r#"href="https://example.com""#), "{html}");
+        assert!(html.contains(r#"rel="noopener noreferrer""#), "{html}");
+    }
+
+    #[test]
+    fn unbalanced_tags() {
+        // unclosed tag is closed before the end of the page text
+        let html = render_markdown("<video controls>\n\nafter");
+        assert!(html.contains("</video>"), "{html}");
+
+        // stray closing tags and tags outside the list are dropped, their text is kept
+        let html = render_markdown(
+            "text</audio></div> <b onclick=alert(1)>bold</b><script>alert(1)</script>",
+        );
+        assert!(!html.contains("</audio>"), "{html}");
+        assert!(!html.contains("div"), "{html}");
+        assert!(!html.contains("<b"), "{html}");
+        assert!(
+            !html.contains("script") && !html.contains("alert"),
+            "{html}"
+        );
+        assert!(html.contains("bold"), "{html}");
+    }
+
+    #[test]
+    fn markdown_kept() {
+        let html = render_markdown("- [x] done\n\n```rust\nfn main() {}\n```");
+        assert!(
+            html.contains(r#"type="checkbox""#) && html.contains("checked"),
+            "{html}"
+        );
+        assert!(html.contains(r#"<code class="language-rust">"#), "{html}");
+        assert!(
+            html.contains(r#"<span class="hl-keyword">fn</span>"#),
+            "{html}"
+        );
+
+        let html = render_markdown(r#"<span class="evil">x</span> <code class="x">y</code>"#);
+        assert!(
+            !html.contains("evil") && !html.contains(r#"class="x""#),
+            "{html}"
+        );
+    }
+}
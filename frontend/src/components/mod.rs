@@ -9,3 +9,12 @@ pub use input::EditableText;
 
 mod quest;
 pub use quest::Quest as QuestInfo;
+
+mod markdown;
+pub use markdown::Markdown;
+
+mod page;
+pub use page::{AskPage, SolvedPage};
+
+mod builder;
+pub use builder::PageBuilder;
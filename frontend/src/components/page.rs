@@ -0,0 +1,242 @@
+use common::{
+    shown_order, AskQuestPageElement, AskQuestion, ImageRegion, OpenedAnswer, QuestPageElement,
+    Question as SolvedQuestion, Scoring, ShuffleSeed, IMAGE_SCALE,
+};
+use leptos::{component, ev, prelude::*, IntoView};
+
+use super::Markdown;
+
+/// Solved pages are shown to their authors, who know the answers anyway
+const SOLVED_PAGE_SEED: ShuffleSeed = ShuffleSeed(0);
+
+fn points(scoring: Scoring) -> String {
+    let mut points = format!("{} point(s)", scoring.points);
+    if scoring.penalty > 0 {
+        points.push_str(&format!(", -{} for a wrong answer", scoring.penalty));
+    }
+    if scoring.partial {
+        points.push_str(", partial answers count");
+    }
+    points
+}
+
+/// Items, the correct ones are marked
+fn items(items: &[String], correct: impl Fn(u32) -> bool) -> AnyView {
+    items
+        .iter()
+        .zip(0..)
+        .map(|(item, no)| view! { <li class:correct=correct(no)>{item.clone()}</li> })
+        .collect_view()
+        .into_any()
+}
+
+fn opened_answer(answer: &OpenedAnswer) -> String {
+    match answer {
+        OpenedAnswer::Text { text, typos: 0 } => text.clone(),
+        OpenedAnswer::Text { text, typos } => format!("{text} (up to {typos} typo(s))"),
+        OpenedAnswer::Regex(pattern) => format!("anything matching /{pattern}/"),
+        OpenedAnswer::Number { value, tolerance } if tolerance.0 == 0.0 => value.0.to_string(),
+        OpenedAnswer::Number { value, tolerance } => format!("{} ± {}", value.0, tolerance.0),
+    }
+}
+
+/// Natural size of the loaded image, that pixel regions are measured in
+pub fn natural_size(ev: &ev::Event) -> Option<(u32, u32)> {
+    let image = event_target::<web_sys::HtmlImageElement>(ev);
+    Some((image.natural_width(), image.natural_height()))
+        .filter(|&(width, height)| width > 0 && height > 0)
+}
+
+/// Shapes of regions, for an svg laid over the image with `viewBox` of [`IMAGE_SCALE`] size.
+/// Pixel regions are shown once natural size of the image is known
+pub fn region_shapes(regions: &[ImageRegion], natural_size: Option<(u32, u32)>) -> impl IntoView {
+    let scale =
+        move |value: u32, size: u32| u64::from(value) * u64::from(IMAGE_SCALE) / u64::from(size);
+    regions
+        .iter()
+        .map(|region| match region {
+            &ImageRegion::PixelRectangle {
+                left,
+                top,
+                width,
+                height,
+            } => natural_size
+                .map(|(natural_width, natural_height)| {
+                    view! {
+                        <rect
+                            x=scale(left, natural_width)
+                            y=scale(top, natural_height)
+                            width=scale(width, natural_width)
+                            height=scale(height, natural_height)
+                        />
+                    }
+                })
+                .into_any(),
+            &ImageRegion::Rectangle {
+                left,
+                top,
+                width,
+                height,
+            } => view! { <rect x=left y=top width=width height=height /> }.into_any(),
+            &ImageRegion::Circle { x, y, radius } => {
+                view! { <circle cx=x cy=y r=radius /> }.into_any()
+            }
+            ImageRegion::Polygon(points) => {
+                let points = points
+                    .iter()
+                    .map(|(x, y)| format!("{x},{y}"))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                view! { <polygon points=points /> }.into_any()
+            }
+        })
+        .collect_view()
+}
+
+/// Correct answer of questions, that can not be marked among the shown items
+fn answers(solution: &SolvedQuestion) -> Option<AnyView> {
+    let answers: Vec<String> = match solution {
+        SolvedQuestion::Opened(answers) => answers.iter().map(opened_answer).collect(),
+        SolvedQuestion::Matching { pairs } => pairs
+            .iter()
+            .map(|(left, right)| format!("{left} — {right}"))
+            .collect(),
+        SolvedQuestion::Cloze { blanks, .. } => {
+            blanks.iter().map(|blank| blank.join(" / ")).collect()
+        }
+        _ => return None,
+    };
+    let answers = answers
+        .into_iter()
+        .map(|answer| view! { <li>{answer}</li> })
+        .collect_view();
+    Some(view! { <ul class="answers">{answers}</ul> }.into_any())
+}
+
+#[component]
+fn Question(
+    question: AskQuestion,
+    scoring: Scoring,
+    /// shown as well, if present
+    #[prop(optional)]
+    solution: Option<SolvedQuestion>,
+) -> impl IntoView {
+    let body = match question {
+        AskQuestion::Opened => view! { <p>"(text answer)"</p> }.into_any(),
+        AskQuestion::Choice { variants } => {
+            let correct = match &solution {
+                Some(SolvedQuestion::Choice { correct, .. }) => Some(*correct),
+                _ => None,
+            };
+            view! { <ol>{items(&variants, |no| correct == Some(no))}</ol> }.into_any()
+        }
+        AskQuestion::MultipleChoice { variants } => {
+            let correct = match &solution {
+                Some(SolvedQuestion::MultipleChoice { correct, .. }) => correct.clone(),
+                _ => Box::default(),
+            };
+            view! {
+                <p>"(several answers)"</p>
+                <ol>{items(&variants, |no| correct.contains(&no))}</ol>
+            }
+            .into_any()
+        }
+        AskQuestion::Image { src } => {
+            let regions = match &solution {
+                Some(SolvedQuestion::Image { regions, .. }) => Some(regions.clone()),
+                _ => None,
+            };
+            let size = RwSignal::new(None);
+            view! {
+                <div class="image-question">
+                    <img src=src on:load=move |ev| size.set(natural_size(&ev)) />
+                    {regions
+                        .map(|regions| {
+                            view! {
+                                <svg
+                                    viewBox=format!("0 0 {IMAGE_SCALE} {IMAGE_SCALE}")
+                                    preserveAspectRatio="none"
+                                >
+                                    {move || region_shapes(&regions, size.get())}
+                                </svg>
+                            }
+                        })}
+                </div>
+            }
+            .into_any()
+        }
+        AskQuestion::Ordering { items: shown } => {
+            // position of every shown item in the correct order
+            let positions = match &solution {
+                Some(SolvedQuestion::Ordering { items }) => {
+                    Some(shown_order(SOLVED_PAGE_SEED, items))
+                }
+                _ => None,
+            };
+            let shown = shown
+                .iter()
+                .zip(0..)
+                .map(|(item, no)| {
+                    let position = positions
+                        .as_ref()
+                        .and_then(|positions| positions.get(no))
+                        .map(|position| view! { <span class="answer">{position + 1}</span> });
+                    view! { <li>{item.clone()} {position}</li> }
+                })
+                .collect_view();
+            view! {
+                <p>"(put in order)"</p>
+                <ol>{shown}</ol>
+            }
+            .into_any()
+        }
+        AskQuestion::Matching { left, right } => view! {
+            <p>"(match pairs)"</p>
+            <ol>{items(&left, |_| false)}</ol>
+            <ol>{items(&right, |_| false)}</ol>
+        }
+        .into_any(),
+        AskQuestion::Cloze { text } => view! { <p>{text.join(" ____ ")}</p> }.into_any(),
+    };
+    view! {
+        <div class="question">
+            {body}
+            {solution.as_ref().and_then(answers)}
+            <small>{points(scoring)}</small>
+        </div>
+    }
+}
+
+/// Page as players see it, text is rendered from markdown
+#[component]
+pub fn AskPage(page: Vec<AskQuestPageElement>) -> impl IntoView {
+    page.into_iter()
+        .map(|element| match element {
+            AskQuestPageElement::Text(text) => {
+                view! { <Markdown source=String::from(text) /> }.into_any()
+            }
+            AskQuestPageElement::Question(question, scoring) => {
+                view! { <Question question scoring /> }.into_any()
+            }
+        })
+        .collect_view()
+}
+
+/// Page as players see it, with correct answers revealed
+#[component]
+pub fn SolvedPage(page: Vec<QuestPageElement>) -> impl IntoView {
+    page.into_iter()
+        .map(|element| {
+            let ask = AskQuestPageElement::new(&element, SOLVED_PAGE_SEED);
+            match (ask, element) {
+                (
+                    AskQuestPageElement::Question(question, scoring),
+                    QuestPageElement::Question(solution, _),
+                ) => view! { <Question question scoring solution /> }.into_any(),
+                (AskQuestPageElement::Text(text), _) | (_, QuestPageElement::Text(text)) => {
+                    view! { <Markdown source=String::from(text) /> }.into_any()
+                }
+            }
+        })
+        .collect_view()
+}
@@ -10,8 +10,6 @@ use thiserror::Error;
 
 mod components;
 
-mod quest;
-
 use std::{future::Future, sync::Arc};
 
 use api::Api;
@@ -111,12 +109,24 @@ pub enum GeneralError {
     #[error("Failed parsing path params")]
     ParamsError,
     #[error(transparent)]
-    SourceParse(common::PageParseError),
+    SourceParse(common::SourceError),
+    #[error("Changed by someone else")]
+    Outdated,
     /// Implementation-specific
     #[error("Unknown error")]
     Unknown,
 }
 
+/// Only the first error of the page is kept
+impl From<Box<[common::SourceError]>> for GeneralError {
+    fn from(errors: Box<[common::SourceError]>) -> Self {
+        errors
+            .first()
+            .cloned()
+            .map_or(GeneralError::Unknown, GeneralError::SourceParse)
+    }
+}
+
 impl ErrorAction for GeneralError {
     fn toast_info(&self) -> Option<ToastInfo> {
         match self {
@@ -146,6 +156,11 @@ impl ErrorAction for GeneralError {
                 ToastKind::Info,
             )),
             GeneralError::SourceParse(_) => None,
+            GeneralError::Outdated => Some(ToastInfo::new(
+                "Someone was faster",
+                "this was changed in another tab, compare the changes before saving",
+                ToastKind::Warn,
+            )),
         }
     }
 
@@ -155,7 +170,9 @@ impl ErrorAction for GeneralError {
             | GeneralError::UnknownEntity(_)
             | GeneralError::Unauthorized
             | GeneralError::SourceParse(_) => true,
-            GeneralError::RequestLogIn | GeneralError::ParamsError => false,
+            GeneralError::RequestLogIn | GeneralError::ParamsError | GeneralError::Outdated => {
+                false
+            }
         }
     }
 
@@ -165,7 +182,8 @@ impl ErrorAction for GeneralError {
             | GeneralError::Unknown
             | GeneralError::UnknownEntity(_)
             | GeneralError::ParamsError
-            | GeneralError::SourceParse(_) => false,
+            | GeneralError::SourceParse(_)
+            | GeneralError::Outdated => false,
             GeneralError::RequestLogIn => true,
         }
     }
@@ -176,7 +194,9 @@ impl ErrorAction for GeneralError {
             | GeneralError::Unauthorized
             | GeneralError::Unknown
             | GeneralError::ParamsError => true,
-            GeneralError::RequestLogIn | GeneralError::SourceParse(_) => false,
+            GeneralError::RequestLogIn | GeneralError::SourceParse(_) | GeneralError::Outdated => {
+                false
+            }
         }
     }
 }
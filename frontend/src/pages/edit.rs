@@ -1,16 +1,21 @@
-use common::{QuestId, QuestInfo};
+use std::{collections::HashMap, time::Duration};
+
+use common::{DiffLine, PageCheck, QuestId, QuestInfo, QuestPageSource};
 use leptos::{component, prelude::*, view, IntoView};
 use leptos_flavour::{GetAnyExt, GetOptionOverResultExt, GetResultExt};
 use leptos_router::hooks::use_params;
-use thaw::{Spinner, Textarea, TextareaResize, TextareaSize};
+use thaw::{Input, InputType, Spinner};
 
 use crate::{
     api::Api,
-    components::{EditableText, IconButton},
+    components::{EditableText, IconButton, PageBuilder, SolvedPage},
     react_errors, GeneralError,
 };
 use core::marker::PhantomData;
 
+/// Page is saved once it is not edited for this long
+const AUTOSAVE_DELAY: Duration = Duration::from_secs(2);
+
 use leptos_router::params::Params;
 #[derive(Params, Clone, Debug, PartialEq)]
 struct PathParamsOpt {
@@ -46,39 +51,382 @@ fn Info(quest_info: RwSignal<QuestInfo>) -> impl IntoView {
     }
 }
 
+/// Page source in a textarea, laid over its copy, that highlights question blocks
+/// and shows diagnostics right at their lines
 #[component]
-fn QuestPageRender(source: impl Fn() -> String + Copy + Send + Sync + 'static) -> impl IntoView {
-    view! { {move || format!("{:#?}", common::parse_quest_page(source()))} }
+fn SourceEditor(value: RwSignal<String>, check: Memo<PageCheck>) -> impl IntoView {
+    let backdrop = move || {
+        check.with(|check| {
+            let errors = check
+                .page
+                .as_ref()
+                .err()
+                .map_or(&[][..], |errors| &errors[..]);
+            value.with(|source| {
+                source
+                    .split('\n')
+                    .zip(1..)
+                    .map(|(line, no)| {
+                        let question = check
+                            .questions
+                            .iter()
+                            .any(|span| (span.line..=span.end_line).contains(&no));
+                        let errors = errors
+                            .iter()
+                            .filter(|error| error.span.line == no)
+                            .map(|error| ("error", error.kind.to_string()));
+                        let warnings = check
+                            .warnings
+                            .iter()
+                            .filter(|warning| warning.span.line == no)
+                            .map(|warning| ("warning", warning.kind.to_string()));
+                        let markers = errors.chain(warnings).collect::<Vec<_>>();
+                        let failing = markers.iter().any(|&(kind, _)| kind == "error");
+                        let markers = markers
+                            .into_iter()
+                            .map(|(kind, message)| {
+                                view! { <span class=format!("marker {kind}")>{message}</span> }
+                            })
+                            .collect_view();
+                        view! {
+                            <div class="source-line" class:question=question class:failing=failing>
+                                <span class="source-text">{line.to_owned()}</span>
+                                {markers}
+                            </div>
+                        }
+                    })
+                    .collect_view()
+            })
+        })
+    };
+    view! {
+        <div class="source-editor">
+            <div class="source-backdrop" aria-hidden="true">
+                {backdrop}
+            </div>
+            <textarea
+                class="source-input"
+                bind:value=value
+                wrap="off"
+                spellcheck="false"
+            />
+        </div>
+    }
 }
 
+/// Page as players will see it, with answers revealed, or a list of what is wrong with it
 #[component]
-fn QuestPage<A: Api>(
+fn QuestPageRender(check: Memo<PageCheck>) -> impl IntoView {
+    view! {
+        <ul>
+            {move || {
+                check
+                    .with(|check| {
+                        let errors = check.page.as_ref().err().into_iter().flatten();
+                        errors
+                            .map(|error| format!("Error, {error}"))
+                            .chain(check.warnings.iter().map(|warning| format!("Warning, {warning}")))
+                            .map(|diagnostic| view! { <li>{diagnostic}</li> })
+                            .collect_view()
+                    })
+            }}
+        </ul>
+        {move || {
+            check
+                .with(|check| {
+                    check
+                        .page
+                        .as_ref()
+                        .ok()
+                        .map(|page| {
+                            view! { <SolvedPage page=page.to_vec() /> }
+                        })
+                })
+        }}
+    }
+}
+
+/// Lines of the saved page against the edited one, saved lines are removed
+#[component]
+fn SourceDiff(saved: String, #[prop(into)] edited: Signal<String>) -> impl IntoView {
+    move || {
+        edited.with(|edited| {
+            common::diff_lines(&saved, edited)
+                .into_iter()
+                .map(|line| {
+                    let (class, line) = match line {
+                        DiffLine::Same(line) => ("same", line),
+                        DiffLine::Removed(line) => ("removed", line),
+                        DiffLine::Added(line) => ("added", line),
+                    };
+                    view! { <div class=class>{line}</div> }
+                })
+                .collect_view()
+        })
+    }
+}
+
+/// Seconds, empty for no limit
+fn parse_time_limit(time_limit: &str) -> Option<Duration> {
+    time_limit.trim().parse().ok().map(Duration::from_secs)
+}
+
+fn format_time_limit(time_limit: Option<Duration>) -> String {
+    time_limit.map_or_else(String::new, |limit| limit.as_secs().to_string())
+}
+
+/// Edits of the pages, that are not saved yet, along with the last saved page. Pages are saved
+/// right away before they are inserted, deleted or reordered, as page editors are loaded again
+#[derive(Clone, Copy)]
+struct PageEdits {
+    unsaved: StoredValue<HashMap<u32, (QuestPageSource, RwSignal<QuestPageSource>)>>,
+    /// pages are being inserted, deleted or reordered, editors do not save meanwhile
+    reloading: RwSignal<bool>,
+}
+
+impl PageEdits {
+    fn new() -> Self {
+        Self {
+            unsaved: StoredValue::new(HashMap::new()),
+            reloading: RwSignal::new(false),
+        }
+    }
+
+    async fn save_all<A: Api>(self, api: &A, quest_id: QuestId) -> Result<(), GeneralError> {
+        for (page, (mut source, saved)) in self.unsaved.get_value() {
+            source.revision = api.set_page_source(quest_id, page, source.clone()).await?;
+            self.unsaved.update_value(|unsaved| {
+                unsaved.remove(&page);
+            });
+            saved.try_set(source);
+        }
+        Ok(())
+    }
+}
+
+/// Editor of the loaded page, saving it a bit after every change
+#[component]
+fn PageEditor<A: Api>(
     #[prop(optional)] _ph: PhantomData<A>,
     quest_id: QuestId,
     page: u32,
+    loaded: QuestPageSource,
+    edits: PageEdits,
 ) -> impl IntoView {
     let api = expect_context::<A>();
 
-    let (quest_page, quest_page_err) = Resource::new(move || (quest_id, page), {
+    let value = RwSignal::new(loaded.source.clone());
+    let check = Memo::new(move |_| value.with(|source| common::check_quest_page(source.as_str())));
+    let time_limit = RwSignal::new(format_time_limit(loaded.time_limit));
+    // forms instead of the source, only for pages that parse
+    let visual = RwSignal::new(false);
+    // last page known to the server, edits are based on its revision
+    let saved = RwSignal::new(loaded);
+    // page saved by someone else, while it was edited here
+    let conflict = RwSignal::new(None::<QuestPageSource>);
+    let edited = move || QuestPageSource {
+        source: value.get_untracked(),
+        time_limit: time_limit.with_untracked(|limit| parse_time_limit(limit)),
+        revision: saved.with_untracked(|saved| saved.revision),
+    };
+
+    let save_action = Action::new(move |new_source: &QuestPageSource| {
+        let mut new_source = new_source.clone();
         let api = api.clone();
+        async move {
+            match api
+                .set_page_source(quest_id, page, new_source.clone())
+                .await
+            {
+                Ok(revision) => {
+                    new_source.revision = revision;
+                    saved.set(new_source);
+                    Ok(())
+                }
+                Err(GeneralError::Outdated) => {
+                    conflict.set(Some(api.get_page_source(quest_id, page).await?));
+                    Err(GeneralError::Outdated)
+                }
+                Err(err) => Err(err),
+            }
+        }
+    });
+    let (_, save_err) = save_action.split();
+
+    react_errors!(save_err, GeneralError);
+
+    let dirty = Memo::new(move |_| {
+        saved.with(|saved| {
+            value.with(|source| *source != saved.source)
+                || time_limit.with(|limit| parse_time_limit(limit) != saved.time_limit)
+        })
+    });
+    // kept for the pages to be saved before they are reloaded
+    Effect::new(move |_| {
+        value.track();
+        time_limit.track();
+        saved.track();
+        if dirty.get() && !save_action.pending().get() && conflict.with(Option::is_none) {
+            let edited = edited();
+            edits.unsaved.update_value(|unsaved| {
+                unsaved.insert(page, (edited, saved));
+            });
+        } else {
+            edits.unsaved.update_value(|unsaved| {
+                unsaved.remove(&page);
+            });
+        }
+    });
+    // saves once there are no changes for a while, and not before the previous save is done
+    let autosave = StoredValue::new(None::<TimeoutHandle>);
+    Effect::new(move |_| {
+        value.track();
+        time_limit.track();
+        if let Some(handle) = autosave.try_update_value(Option::take).flatten() {
+            handle.clear();
+        }
+        if !dirty.get()
+            || save_action.pending().get()
+            || conflict.with(Option::is_some)
+            || edits.reloading.get()
+        {
+            return;
+        }
+        let handle = set_timeout_with_handle(
+            move || {
+                save_action.dispatch(edited());
+            },
+            AUTOSAVE_DELAY,
+        );
+        autosave.set_value(handle.ok());
+    });
+    // pages are saved by now, if the editor is loaded again
+    on_cleanup(move || {
+        if let Some(handle) = autosave.try_update_value(Option::take).flatten() {
+            handle.clear();
+        }
+        edits
+            .unsaved
+            .try_update_value(|unsaved| unsaved.remove(&page));
+    });
+
+    let status = move || {
+        if conflict.with(Option::is_some) {
+            "Not saved, page was changed elsewhere"
+        } else if save_action.pending().get() {
+            "Saving..."
+        } else if dirty.get() {
+            "Unsaved changes"
+        } else {
+            "All changes saved"
+        }
+    };
+
+    // edits are kept, and saved over the newer page
+    let keep_mine = move || {
+        if let Some(theirs) = conflict.get_untracked() {
+            saved.set(theirs);
+            conflict.set(None);
+        }
+    };
+    // edits are dropped for the newer page
+    let take_theirs = move || {
+        if let Some(theirs) = conflict.get_untracked() {
+            value.set(theirs.source.clone());
+            time_limit.set(format_time_limit(theirs.time_limit));
+            saved.set(theirs);
+            conflict.set(None);
+        }
+    };
+
+    view! {
+        <IconButton
+            text="Source"
+            icon=icondata::AiCodeOutlined
+            on_click=move || visual.set(false)
+            disabled=Signal::derive(move || !visual.get())
+        />
+        <IconButton
+            text="Visual"
+            icon=icondata::AiFormOutlined
+            on_click=move || visual.set(true)
+            disabled=Signal::derive(move || {
+                visual.get() || check.with(|check| check.page.is_err())
+            })
+        />
+        <div class="page-editor">
+            {move || {
+                if visual.get() {
+                    view! { <PageBuilder source=value check /> }.into_any()
+                } else {
+                    view! { <SourceEditor value check /> }
+                        .into_any()
+                }
+            }}
+            <div class="page-preview">
+                <QuestPageRender check />
+            </div>
+        </div>
+        {move || {
+            conflict
+                .get()
+                .map(|theirs| {
+                    view! {
+                        <div class="conflict">
+                            <p>
+                                "This page was saved elsewhere. Lines of that version are removed, your lines are added:"
+                            </p>
+                            <div class="diff">
+                                <SourceDiff saved=theirs.source edited=value />
+                            </div>
+                            <IconButton
+                                text="Keep mine"
+                                icon=icondata::AiCheckOutlined
+                                on_click=keep_mine
+                            />
+                            <IconButton
+                                text="Take theirs"
+                                icon=icondata::AiRollbackOutlined
+                                on_click=take_theirs
+                            />
+                        </div>
+                    }
+                })
+        }}
+        <Input input_type=InputType::Number value=time_limit placeholder="Time limit, seconds" />
+        <IconButton
+            text="Save page"
+            icon=icondata::AiSaveOutlined
+            on_click=move || {
+                save_action.dispatch(edited());
+            }
+            disabled=Signal::derive(move || {
+                save_action.pending().get() || conflict.with(Option::is_some)
+            })
+        />
+        <small class="save-status">{status}</small>
+    }
+}
+
+#[component]
+fn QuestPage<A: Api>(
+    #[prop(optional)] _ph: PhantomData<A>,
+    quest_id: QuestId,
+    page: u32,
+    edits: PageEdits,
+) -> impl IntoView {
+    let api = expect_context::<A>();
+
+    let (quest_page, quest_page_err) = Resource::new(
+        move || (quest_id, page),
         move |(quest_id, page)| {
             let api = api.clone();
             async move { api.get_page_source(quest_id, page).await }
-        }
-    })
+        },
+    )
     .split();
 
-    let set_quest_page_source_action = Action::new(move |new_source: &String| {
-        let new_source = new_source.clone();
-        let api = api.clone();
-        async move { api.set_page_source(quest_id, page, new_source).await }
-    });
-    let (_, set_quest_page_source_err) = set_quest_page_source_action.split();
-
-    react_errors!(
-        quest_page_err, GeneralError;
-        set_quest_page_source_err, GeneralError;
-    );
+    react_errors!(quest_page_err, GeneralError);
 
     view! {
         <Transition fallback=move || {
@@ -87,31 +435,155 @@ fn QuestPage<A: Api>(
             {move || {
                 quest_page
                     .get()
-                    .map(|quest_page_source| {
-                        let value = RwSignal::new(quest_page_source);
-                        view! {
-                            <Textarea
-                                disabled=set_quest_page_source_action.pending()
-                                value
-                                resize=TextareaResize::Vertical
-                                size=TextareaSize::Large
-                            />
-                            <QuestPageRender source=move || value.get() />
-                            <IconButton
-                                text="Save page"
-                                icon=icondata::AiSaveOutlined
-                                on_click=move || {
-                                    set_quest_page_source_action.dispatch(value.get());
-                                }
-                                disabled=set_quest_page_source_action.pending()
-                            />
-                        }
+                    .map(|loaded| {
+                        view! { <PageEditor<A> quest_id page loaded edits /> }
                     })
             }}
         </Transition>
     }
 }
 
+/// Tabs of the pages, that can be dragged to reorder pages
+#[component]
+fn PageTabs<A: Api>(
+    #[prop(optional)] _ph: PhantomData<A>,
+    quest_info: RwSignal<QuestInfo>,
+    selected: RwSignal<u32>,
+    revision: RwSignal<u32>,
+    edits: PageEdits,
+) -> impl IntoView {
+    let api = expect_context::<A>();
+    let quest_id = quest_info.with_untracked(|info| info.id);
+
+    // pages are renumbered on the server first, so these save edits of every page before,
+    // and reload every page afterwards
+    let insert_page_action = Action::new({
+        let api = api.clone();
+        move |&page: &u32| {
+            let api = api.clone();
+            async move {
+                edits.save_all(&api, quest_id).await?;
+                api.insert_page(quest_id, page, QuestPageSource::default())
+                    .await?;
+                quest_info.update(|info| info.pages += 1);
+                selected.set(page);
+                revision.update(|revision| *revision += 1);
+                Ok(())
+            }
+        }
+    });
+    let delete_page_action = Action::new({
+        let api = api.clone();
+        move |&page: &u32| {
+            let api = api.clone();
+            async move {
+                edits.save_all(&api, quest_id).await?;
+                api.delete_page(quest_id, page).await?;
+                quest_info.update(|info| info.pages -= 1);
+                // the following page takes place of the deleted one
+                let pages = quest_info.with_untracked(|info| info.pages);
+                selected.set(page.min(pages.saturating_sub(1)));
+                revision.update(|revision| *revision += 1);
+                Ok(())
+            }
+        }
+    });
+    let reorder_pages_action = Action::new(move |&(from, to): &(u32, u32)| {
+        let api = api.clone();
+        async move {
+            edits.save_all(&api, quest_id).await?;
+            let mut order = (0..quest_info.with_untracked(|info| info.pages)).collect::<Vec<_>>();
+            order.remove(from as usize);
+            order.insert(to as usize, from);
+            api.reorder_pages(quest_id, order).await?;
+            selected.set(to);
+            revision.update(|revision| *revision += 1);
+            Ok(())
+        }
+    });
+    let (_, insert_page_err) = insert_page_action.split();
+    let (_, delete_page_err) = delete_page_action.split();
+    let (_, reorder_pages_err) = reorder_pages_action.split();
+
+    react_errors!(
+        insert_page_err, GeneralError;
+        delete_page_err, GeneralError;
+        reorder_pages_err, GeneralError;
+    );
+
+    let pending = Signal::derive(move || {
+        insert_page_action.pending().get()
+            || delete_page_action.pending().get()
+            || reorder_pages_action.pending().get()
+    });
+    Effect::new(move |_| edits.reloading.set(pending.get()));
+    // page, that is being dragged
+    let dragged = RwSignal::new(None::<u32>);
+
+    view! {
+        <div class="page-tabs">
+            <For
+                each=move || 0..quest_info.get().pages
+                key=|page| *page
+                children=move |page| {
+                    view! {
+                        <div
+                            class="page-tab"
+                            class:selected=move || selected.get() == page
+                            draggable="true"
+                            on:click=move |_| selected.set(page)
+                            on:dragstart=move |ev| {
+                                if let Some(transfer) = ev.data_transfer() {
+                                    let _ = transfer.set_data("text/plain", &page.to_string());
+                                }
+                                dragged.set(Some(page));
+                            }
+                            on:dragover=move |ev| ev.prevent_default()
+                            on:drop=move |ev| {
+                                ev.prevent_default();
+                                if let Some(from) = dragged.get_untracked().filter(|&from| from != page)
+                                {
+                                    if !pending.get_untracked() {
+                                        reorder_pages_action.dispatch((from, page));
+                                    }
+                                }
+                                dragged.set(None);
+                            }
+                            on:dragend=move |_| dragged.set(None)
+                        >
+                            {format!("Page {}", page + 1)}
+                        </div>
+                    }
+                }
+            />
+        </div>
+        <IconButton
+            text="Insert before"
+            icon=icondata::AiPlusSquareOutlined
+            on_click=move || {
+                insert_page_action.dispatch(selected.get_untracked());
+            }
+            disabled=Signal::derive(move || pending.get() || quest_info.with(|info| info.pages == 0))
+        />
+        <IconButton
+            text="New page"
+            icon=icondata::AiPlusOutlined
+            on_click=move || {
+                insert_page_action.dispatch(quest_info.with_untracked(|info| info.pages));
+            }
+            disabled=pending
+        />
+        <IconButton
+            text="Delete page"
+            icon=icondata::AiDeleteOutlined
+            on_click=move || {
+                delete_page_action.dispatch(selected.get_untracked());
+            }
+            disabled=Signal::derive(move || pending.get() || quest_info.with(|info| info.pages == 0))
+        />
+    }
+}
+
 #[component]
 fn Edit<A: Api>(#[prop(optional)] _ph: PhantomData<A>, quest_info: QuestInfo) -> impl IntoView {
     let api = expect_context::<A>();
@@ -127,6 +599,12 @@ fn Edit<A: Api>(#[prop(optional)] _ph: PhantomData<A>, quest_info: QuestInfo) ->
 
     react_errors!(set_quest_info_err, GeneralError);
 
+    // page being edited
+    let selected = RwSignal::new(0);
+    // pages are reloaded, once it changes
+    let revision = RwSignal::new(0u32);
+    let edits = PageEdits::new();
+
     view! {
         <h1>"(quest editing)"</h1>
         <Info quest_info />
@@ -140,26 +618,18 @@ fn Edit<A: Api>(#[prop(optional)] _ph: PhantomData<A>, quest_info: QuestInfo) ->
         />
         <hr />
         <h2>"Pages"</h2>
+        <PageTabs<A> quest_info selected revision edits />
         <For
             each=move || 0..quest_info.get().pages
-            key=move |page| (quest_info.get().id, *page)
+            key=move |page| (quest_info.get().id, *page, revision.get())
             children=move |page| {
                 view! {
-                    <div>
-                        <h3>{format!("Page {}", page + 1)}</h3>
-                        <QuestPage<A> quest_id=quest_info.get().id page />
-                        <hr />
+                    <div class="page" class:selected=move || selected.get() == page>
+                        <QuestPage<A> quest_id=quest_info.get().id page edits />
                     </div>
                 }
             }
         />
-        <IconButton
-            text="New page"
-            icon=icondata::AiPlusOutlined
-            on_click=move || {
-                quest_info.update(|info| info.pages += 1);
-            }
-        />
     }
 }
 
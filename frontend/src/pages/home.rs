@@ -90,6 +90,7 @@ fn History<A: Api>(#[prop(optional)] _ph: PhantomData<A>) -> impl IntoView {
                     <div>
                         <h3>"(history record)"</h3>
                         <h4>{format!("Record: {record:?}")}</h4>
+                        <p>{format!("Score: {} / {}", record.score, record.max_score)}</p>
                         <p>"(some text, idk)"</p>
                     </div>
                 }
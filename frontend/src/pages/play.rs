@@ -1,10 +1,11 @@
-use common::{QuestId, QuestInfo, QuestPage, Timestamp};
+use common::{QuestId, Timestamp};
 use leptos::prelude::*;
 use leptos_flavour::{GetAnyExt, GetOptionExt, GetOptionOverResultExt, GetResultExt};
 use thaw::Spinner;
 
 use crate::{
     api::{error::GameError, Api},
+    components::AskPage,
     react_errors, AppRouter, GeneralError,
 };
 
@@ -72,9 +73,9 @@ pub fn Game<A: Api>(#[prop(optional)] _ph: PhantomData<A>) -> impl IntoView {
 
     view! {
         <Transition fallback=||view!{<Spinner/>}>
-        {move|| quest_page.get().map(|quest_page| {
+        {move|| quest_page.get().map(|(page, _)| {
             view!{
-                <p>{format!("{:#?}", quest_page)}</p>
+                <AskPage page=page.into_vec() />
             }
         })}
         </Transition>